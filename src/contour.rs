@@ -0,0 +1,200 @@
+use data_grid::DataGrid;
+use geo::tex_to_lat_lon;
+
+// Regular interval levels are capped so a tiny interval cannot stall a frame
+pub const MAX_LEVELS: usize = 200;
+
+// A piece of an isoline between two [lat, lon] points
+pub type Segment = [[f32; 2]; 2];
+
+// Every multiple of interval inside range. An interval giving more than
+// MAX_LEVELS lines is widened to a multiple of itself that spans the range
+pub fn interval_levels(range: [f32; 2], interval: f32) -> Vec<f32> {
+    let finite = range[0].is_finite() && range[1].is_finite() && interval.is_finite();
+    if !finite || interval <= 0.0 || range[1] < range[0] {
+        return vec![];
+    }
+    let first = (f64::from(range[0]) / f64::from(interval)).ceil();
+    let last = (f64::from(range[1]) / f64::from(interval)).floor();
+    if last < first {
+        return vec![];
+    }
+    let count = last - first + 1.0;
+    if count > MAX_LEVELS as f64 {
+        let widen = (count / MAX_LEVELS as f64).ceil() as f32;
+        return interval_levels(range, interval * widen);
+    }
+    (first as i64..=last as i64)
+        .map(|i| i as f32 * interval)
+        .collect()
+}
+
+// Reads levels separated by commas or spaces, anything not a number is skipped
+pub fn parse_levels(text: &str) -> Vec<f32> {
+    let mut levels: Vec<f32> = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|part| part.parse::<f32>().ok())
+        .filter(|level| level.is_finite())
+        .collect();
+    levels.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    levels.dedup();
+    levels.truncate(MAX_LEVELS);
+    levels
+}
+
+pub fn format_level(level: f32) -> String {
+    match level.fract() == 0.0 {
+        true => format!("{:.0}", level),
+        false => format!("{:.2}", level),
+    }
+}
+
+fn crossing(from: f32, to: f32, level: f32) -> f32 {
+    match to == from {
+        true => 0.5,
+        false => (level - from) / (to - from),
+    }
+}
+
+// Marching squares over the cell centres of a grid. Columns wrap around at
+// the date line, rows stop at the last centre before each pole.
+pub fn contour_segments(grid: &DataGrid, level: f32) -> Vec<Segment> {
+    let (width, height) = (grid.width(), grid.height());
+    let mut segments = vec![];
+    if width < 2 || height < 2 {
+        return segments;
+    }
+    let centre = |column: f32, row: f32| {
+        tex_to_lat_lon([
+            (column + 0.5) / width as f32,
+            (row + 0.5) / height as f32,
+        ])
+    };
+
+    for row in 0..height - 1 {
+        for column in 0..width {
+            let next = (column + 1) % width;
+            let corners = (
+                grid.get(column, row),
+                grid.get(next, row),
+                grid.get(next, row + 1),
+                grid.get(column, row + 1),
+            );
+            // Bottom left, bottom right, top right and top left
+            let (a, b, c, d) = match corners {
+                (Some(a), Some(b), Some(c), Some(d)) => (a, b, c, d),
+                _ => continue,
+            };
+            let case = (a >= level) as u8
+                | ((b >= level) as u8) << 1
+                | ((c >= level) as u8) << 2
+                | ((d >= level) as u8) << 3;
+            if case == 0 || case == 15 {
+                continue;
+            }
+
+            let (x, y) = (column as f32, row as f32);
+            let bottom = centre(x + crossing(a, b, level), y);
+            let right = centre(x + 1.0, y + crossing(b, c, level));
+            let top = centre(x + crossing(d, c, level), y + 1.0);
+            let left = centre(x, y + crossing(a, d, level));
+            // Saddles are split by whether the middle of the square is above the level
+            let middle_above = (a + b + c + d) * 0.25 >= level;
+
+            match case {
+                1 | 14 => segments.push([left, bottom]),
+                2 | 13 => segments.push([bottom, right]),
+                3 | 12 => segments.push([left, right]),
+                4 | 11 => segments.push([right, top]),
+                6 | 9 => segments.push([bottom, top]),
+                7 | 8 => segments.push([left, top]),
+                5 => match middle_above {
+                    true => {
+                        segments.push([bottom, right]);
+                        segments.push([left, top]);
+                    }
+                    false => {
+                        segments.push([left, bottom]);
+                        segments.push([right, top]);
+                    }
+                },
+                10 => match middle_above {
+                    true => {
+                        segments.push([left, bottom]);
+                        segments.push([right, top]);
+                    }
+                    false => {
+                        segments.push([bottom, right]);
+                        segments.push([left, top]);
+                    }
+                },
+                _ => (),
+            }
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use contour::*;
+    use data_grid::DataGrid;
+
+    #[test]
+    fn interval_levels_stay_in_range() {
+        assert_eq!(interval_levels([-12.0, 21.0], 10.0), vec![-10.0, 0.0, 10.0, 20.0]);
+        assert!(interval_levels([0.0, 10.0], 0.0).is_empty());
+        assert_eq!(interval_levels([0.0, 1e9], 1.0).len(), MAX_LEVELS);
+    }
+
+    #[test]
+    fn small_intervals_are_widened_across_the_range() {
+        let levels = interval_levels([0.0, 1000.0], 0.01);
+        assert!(levels.len() <= MAX_LEVELS);
+        assert!(levels.len() > MAX_LEVELS / 2);
+        assert!(*levels.last().unwrap() > 990.0);
+        // Still multiples of the interval asked for
+        assert!(levels.iter().all(|level| (level / 0.01 - (level / 0.01).round()).abs() < 1e-2));
+
+        // Too many levels to count in an i64 before widening
+        let levels = interval_levels([0.0, 1e9], 1e-30);
+        assert!(!levels.is_empty() && levels.len() <= MAX_LEVELS);
+        assert!(interval_levels([0.0, ::std::f32::INFINITY], 1.0).is_empty());
+    }
+
+    #[test]
+    fn levels_parse_from_a_list() {
+        assert_eq!(parse_levels("10, 0 5,,x"), vec![0.0, 5.0, 10.0]);
+        assert_eq!(format_level(5.0), "5");
+        assert_eq!(format_level(-0.25), "-0.25");
+    }
+
+    #[test]
+    fn contours_cross_between_cell_centres() {
+        // 4 x 3 with a single high cell in the middle row
+        let mut values = vec![Some(0.0); 12];
+        values[5] = Some(10.0);
+        let grid = DataGrid::new(4, 3, values);
+
+        let segments = contour_segments(&grid, 5.0);
+        // One closed diamond around the high cell
+        assert_eq!(segments.len(), 4);
+        for segment in &segments {
+            for point in segment.iter() {
+                // Halfway between the high centre and its neighbours
+                let offset = point[0].abs() / 60.0 + (point[1] + 45.0).abs() / 90.0;
+                assert!((offset - 0.5).abs() < 1e-4);
+            }
+        }
+        assert!(contour_segments(&grid, 20.0).is_empty());
+    }
+
+    #[test]
+    fn missing_cells_leave_gaps() {
+        let mut values = vec![Some(0.0); 12];
+        values[5] = Some(10.0);
+        values[4] = None;
+        let grid = DataGrid::new(4, 3, values);
+        assert_eq!(contour_segments(&grid, 5.0).len(), 2);
+    }
+}
@@ -0,0 +1,191 @@
+use geo::{lat_lon_to_tex, tex_to_lat_lon};
+use glium::backend::Facade;
+use glium::texture::{MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat};
+use heat_map;
+use std::cell::RefCell;
+
+// The physical values of one time step kept on the CPU. Cells are stored in
+// the order heat_map uploads them to a texture, so row 0 is the bottom row
+// (v = 0) and a cell maps to the same place on the globe as its texel.
+#[derive(Clone, Debug)]
+pub struct DataGrid {
+    width: usize,
+    height: usize,
+    values: Vec<Option<f32>>,
+}
+
+impl DataGrid {
+    pub fn new(width: usize, height: usize, values: Vec<Option<f32>>) -> DataGrid {
+        assert_eq!(width * height, values.len(), "Grid size does not match its values");
+        DataGrid {
+            width,
+            height,
+            values,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, column: usize, row: usize) -> Option<f32> {
+        if column >= self.width || row >= self.height {
+            return None;
+        }
+        self.values[row * self.width + column]
+    }
+
+    // The cell a location falls in, the same texel the shaders fetch
+    pub fn cell_at(&self, location: [f32; 2]) -> (usize, usize) {
+        let tex = lat_lon_to_tex(location);
+        let column = (tex[0] * self.width as f32).floor().max(0.0) as usize;
+        let row = (tex[1] * self.height as f32).floor().max(0.0) as usize;
+        (column.min(self.width - 1), row.min(self.height - 1))
+    }
+
+    pub fn sample(&self, location: [f32; 2]) -> Option<f32> {
+        let (column, row) = self.cell_at(location);
+        self.get(column, row)
+    }
+
+    pub fn cell_centre(&self, column: usize, row: usize) -> [f32; 2] {
+        tex_to_lat_lon([
+            (column as f32 + 0.5) / self.width as f32,
+            (row as f32 + 0.5) / self.height as f32,
+        ])
+    }
+
+    // Every cell as (column, row, value)
+    pub fn cells<'a>(&'a self) -> impl Iterator<Item = (usize, usize, Option<f32>)> + 'a {
+        let width = self.width;
+        self.values
+            .iter()
+            .enumerate()
+            .map(move |(i, value)| (i % width, i / width, *value))
+    }
+}
+
+// Grids made into textures here store values the way the loaded temperature
+// textures do, spread over the top half of the texel so 0 can mean no data
+pub const NORMALISED: [f32; 2] = [0.5, 1.0];
+
+pub fn texel_value(value: Option<f32>, range: [f32; 2]) -> f32 {
+    match value {
+        Some(value) => {
+            let span = range[1] - range[0];
+            let t = match span > 0.0 {
+                true => ((value - range[0]) / span).max(0.0).min(1.0),
+                false => 0.5,
+            };
+            NORMALISED[0] + t * (NORMALISED[1] - NORMALISED[0])
+        }
+        None => 0.0,
+    }
+}
+
+// The alpha channel marks which cells have data, so the shaders can tell a
+// missing cell apart whatever the value range
+pub fn texel(value: Option<f32>, range: [f32; 2]) -> [f32; 4] {
+    let texel = texel_value(value, range);
+    let valid = match value {
+        Some(value) if value.is_finite() => 1.0,
+        _ => 0.0,
+    };
+    [texel, texel, texel, valid]
+}
+
+impl DataGrid {
+    // Uploads a grid computed on the CPU, the value goes in the colour
+    // channels and alpha marks the cells with data
+    pub fn to_texture<F: Facade>(&self, display: &F, range: [f32; 2]) -> Texture2d {
+        let mut texels = Vec::with_capacity(self.values.len() * 4);
+        for value in &self.values {
+            texels.extend_from_slice(&texel(*value, range));
+        }
+        let image = RawImage2d::from_raw_rgba(texels, (self.width as u32, self.height as u32));
+        Texture2d::with_format(
+            display,
+            image,
+            UncompressedFloatFormat::F32F32F32F32,
+            MipmapsOption::NoMipmap,
+        ).expect("Failed to create grid texture")
+    }
+}
+
+// The one place that reads cells out of a heat_map grid. into_grid_with
+// visits cells in storage order, which is the order heat_map lays them out in
+// a texture, so taking the size from the grid keeps every cell in its place.
+pub fn from_heat_map(grid: heat_map::grid::Grid<Option<f32>>) -> DataGrid {
+    let values = RefCell::new(Vec::new());
+    grid.into_grid_with(|value: &Option<f32>| {
+        values.borrow_mut().push(*value);
+        *value
+    });
+    let (width, height) = grid.dimensions;
+    DataGrid::new(width, height, values.into_inner())
+}
+
+// The grid of a loaded value with the texture drawn from it. The texture is
+// made here rather than by heat_map so missing cells keep their alpha
+pub fn grid_with_texture<F: Facade>(
+    display: &F,
+    grid: heat_map::grid::Grid<Option<f32>>,
+    range: [f32; 2],
+) -> (Texture2d, DataGrid) {
+    let data = from_heat_map(grid);
+    (data.to_texture(display, range), data)
+}
+
+#[cfg(test)]
+mod tests {
+    use data_grid::{texel, texel_value, DataGrid};
+
+    fn grid() -> DataGrid {
+        // 4 x 2, the bottom row is the southern hemisphere
+        let values = (0..8).map(|i| if i == 5 { None } else { Some(i as f32) }).collect();
+        DataGrid::new(4, 2, values)
+    }
+
+    #[test]
+    fn samples_cells_like_the_texture() {
+        let grid = grid();
+        assert_eq!(grid.sample([-45.0, -170.0]), Some(0.0));
+        assert_eq!(grid.sample([-45.0, 100.0]), Some(3.0));
+        assert_eq!(grid.sample([45.0, -170.0]), Some(4.0));
+        assert_eq!(grid.sample([45.0, -10.0]), None);
+    }
+
+    #[test]
+    fn edges_stay_inside_the_grid() {
+        let grid = grid();
+        assert_eq!(grid.cell_at([90.0, 180.0]), (3, 1));
+        assert_eq!(grid.cell_at([-90.0, -180.0]), (0, 0));
+    }
+
+    #[test]
+    fn cell_centres() {
+        let grid = grid();
+        assert_eq!(grid.cell_centre(0, 0), [-45.0, -135.0]);
+        assert_eq!(grid.cell_centre(3, 1), [45.0, 135.0]);
+    }
+
+    #[test]
+    fn texels_leave_room_for_missing_data() {
+        assert_eq!(texel_value(None, [0.0, 10.0]), 0.0);
+        assert_eq!(texel_value(Some(0.0), [0.0, 10.0]), 0.5);
+        assert_eq!(texel_value(Some(5.0), [0.0, 10.0]), 0.75);
+        assert_eq!(texel_value(Some(20.0), [0.0, 10.0]), 1.0);
+    }
+
+    #[test]
+    fn alpha_marks_cells_with_data() {
+        // Values below the range are clamped to its bottom and still have data
+        assert_eq!(texel(Some(-5.0), [0.0, 10.0]), [0.5, 0.5, 0.5, 1.0]);
+        assert_eq!(texel(None, [0.0, 10.0]), [0.0; 4]);
+        assert_eq!(texel(Some(::std::f32::NAN), [0.0, 10.0])[3], 0.0);
+    }
+}
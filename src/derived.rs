@@ -0,0 +1,148 @@
+use data_grid::DataGrid;
+use std::error::Error;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Operation {
+    Difference,
+    Ratio,
+}
+
+impl Operation {
+    pub fn apply(&self, a: f32, b: f32) -> Option<f32> {
+        match self {
+            Operation::Difference => Some(a - b),
+            Operation::Ratio if b != 0.0 => Some(a / b),
+            Operation::Ratio => None,
+        }
+    }
+
+    // The value nothing has changed at, diverging ranges are centred on it
+    pub fn centre(&self) -> f32 {
+        match self {
+            Operation::Difference => 0.0,
+            Operation::Ratio => 1.0,
+        }
+    }
+}
+
+// Samples a grid at the cell centres of a grid of another size
+pub fn regrid(grid: &DataGrid, width: usize, height: usize) -> DataGrid {
+    if grid.width() == width && grid.height() == height {
+        return grid.clone();
+    }
+    let target = DataGrid::new(width, height, vec![None; width * height]);
+    let values = target
+        .cells()
+        .map(|(column, row, _)| grid.sample(target.cell_centre(column, row)))
+        .collect();
+    DataGrid::new(width, height, values)
+}
+
+// Applies the operation cell by cell, b is regridded to a's size first
+pub fn combine(a: &DataGrid, b: &DataGrid, operation: Operation) -> DataGrid {
+    let b = regrid(b, a.width(), a.height());
+    let values = a
+        .cells()
+        .map(|(column, row, cell)| match (cell, b.get(column, row)) {
+            (Some(a), Some(b)) => operation.apply(a, b),
+            _ => None,
+        }).collect();
+    DataGrid::new(a.width(), a.height(), values)
+}
+
+// The mean of every step, cells without data in a step are left out of its mean
+pub fn mean_grid(grids: &[DataGrid]) -> Option<DataGrid> {
+    let first = grids.first()?;
+    let (width, height) = (first.width(), first.height());
+    let mut sums = vec![(0.0, 0); width * height];
+    for grid in grids {
+        let grid = regrid(grid, width, height);
+        for (column, row, cell) in grid.cells() {
+            if let Some(cell) = cell {
+                let sum = &mut sums[row * width + column];
+                sum.0 += cell;
+                sum.1 += 1;
+            }
+        }
+    }
+    let values = sums
+        .into_iter()
+        .map(|(sum, count)| match count {
+            0 => None,
+            count => Some(sum / count as f32),
+        }).collect();
+    Some(DataGrid::new(width, height, values))
+}
+
+// Pairs every step of a with a step of b, a single step of b is used for all
+pub fn combine_steps(
+    a: &[DataGrid],
+    b: &[DataGrid],
+    operation: Operation,
+) -> Result<Vec<DataGrid>, Box<Error>> {
+    if a.is_empty() || b.is_empty() {
+        return Err("Both variables need data grids".into());
+    }
+    if b.len() != 1 && b.len() != a.len() {
+        return Err(format!("Cannot pair {} time steps with {}", a.len(), b.len()).into());
+    }
+    Ok(a
+        .iter()
+        .enumerate()
+        .map(|(i, grid)| combine(grid, &b[i % b.len()], operation))
+        .collect())
+}
+
+// A range with centre in the middle that covers every value
+pub fn centred_range(grids: &[DataGrid], centre: f32) -> [f32; 2] {
+    let spread = grids
+        .iter()
+        .flat_map(|grid| grid.cells())
+        .filter_map(|(_, _, cell)| cell)
+        .filter(|cell| cell.is_finite())
+        .fold(0.0, |spread: f32, cell| spread.max((cell - centre).abs()));
+    let spread = match spread > 0.0 {
+        true => spread,
+        false => 1.0,
+    };
+    [centre - spread, centre + spread]
+}
+
+#[cfg(test)]
+mod tests {
+    use data_grid::DataGrid;
+    use derived::*;
+
+    fn grid(width: usize, height: usize, values: &[f32]) -> DataGrid {
+        DataGrid::new(width, height, values.iter().map(|v| Some(*v)).collect())
+    }
+
+    #[test]
+    fn regrids_by_nearest_cell() {
+        let coarse = grid(2, 1, &[1.0, 2.0]);
+        let fine = regrid(&coarse, 4, 2);
+        let values: Vec<_> = fine.cells().map(|(_, _, cell)| cell.unwrap()).collect();
+        assert_eq!(values, vec![1.0, 1.0, 2.0, 2.0, 1.0, 1.0, 2.0, 2.0]);
+    }
+
+    #[test]
+    fn combines_steps_and_skips_bad_cells() {
+        let a = vec![grid(2, 1, &[4.0, 6.0]), grid(2, 1, &[8.0, 3.0])];
+        let b = vec![grid(2, 1, &[2.0, 0.0])];
+        let ratio = combine_steps(&a, &b, Operation::Ratio).unwrap();
+        assert_eq!(ratio[0].get(0, 0), Some(2.0));
+        assert_eq!(ratio[0].get(1, 0), None);
+        assert_eq!(ratio[1].get(0, 0), Some(4.0));
+        assert!(combine_steps(&a, &[b[0].clone(), b[0].clone(), b[0].clone()], Operation::Difference).is_err());
+    }
+
+    #[test]
+    fn anomalies_centre_on_zero() {
+        let steps = vec![grid(1, 1, &[1.0]), grid(1, 1, &[5.0])];
+        let mean = mean_grid(&steps).unwrap();
+        assert_eq!(mean.get(0, 0), Some(3.0));
+        let anomaly = combine_steps(&steps, &[mean], Operation::Difference).unwrap();
+        assert_eq!(centred_range(&anomaly, 0.0), [-2.0, 2.0]);
+        assert_eq!(centred_range(&[], 1.0), [0.0, 2.0]);
+    }
+}
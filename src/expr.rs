@@ -0,0 +1,614 @@
+use data_grid::DataGrid;
+use derived::regrid;
+use std::error::Error;
+use std::fmt;
+
+// Formulas over named values, for example `(t_max - t_min) / 2` or
+// `where(height > 0.5, temp, nan)`. Values are referred to by their name in
+// lower case with anything that is not a letter or digit replaced by `_`, or
+// by their full name in backticks. Missing data is carried through as nan.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Number(f32),
+    Variable(String),
+    Negate(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+}
+
+impl BinaryOp {
+    fn apply(&self, a: f32, b: f32) -> f32 {
+        let truth = |test: bool| if test { 1.0 } else { 0.0 };
+        if a.is_nan() || b.is_nan() {
+            return ::std::f32::NAN;
+        }
+        match self {
+            BinaryOp::Add => a + b,
+            BinaryOp::Subtract => a - b,
+            BinaryOp::Multiply => a * b,
+            BinaryOp::Divide => a / b,
+            BinaryOp::Power => a.powf(b),
+            BinaryOp::Less => truth(a < b),
+            BinaryOp::LessEqual => truth(a <= b),
+            BinaryOp::Greater => truth(a > b),
+            BinaryOp::GreaterEqual => truth(a >= b),
+            BinaryOp::Equal => truth(a == b),
+            BinaryOp::NotEqual => truth(a != b),
+        }
+    }
+}
+
+// Where parsing stopped and why, position counts characters from 0
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.position + 1)
+    }
+}
+
+impl Error for ParseError {}
+
+pub fn identifier(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| match c.is_alphanumeric() {
+            true => c.to_ascii_lowercase(),
+            false => '_',
+        }).collect()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f32),
+    Name(String),
+    Symbol(&'static str),
+    End,
+}
+
+const SYMBOLS: [&str; 15] = [
+    "<=", ">=", "==", "!=", "<", ">", "+", "-", "*", "/", "^", "(", ")", ",", "=",
+];
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // Exponents such as 1e-3
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut end = i + 1;
+                if end < chars.len() && (chars[end] == '-' || chars[end] == '+') {
+                    end += 1;
+                }
+                if end < chars.len() && chars[end].is_ascii_digit() {
+                    i = end;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let number: String = chars[start..i].iter().collect();
+            let number = number.parse().map_err(|_| ParseError {
+                message: format!("\"{}\" is not a number", number),
+                position: start,
+            })?;
+            tokens.push((Token::Number(number), start));
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let name: String = chars[start..i].iter().collect();
+            tokens.push((Token::Name(identifier(&name)), start));
+        } else if c == '`' {
+            let end = chars[i + 1..].iter().position(|c| *c == '`').ok_or(ParseError {
+                message: "Unclosed `".to_string(),
+                position: start,
+            })?;
+            let name: String = chars[i + 1..i + 1 + end].iter().collect();
+            tokens.push((Token::Name(identifier(&name)), start));
+            i += end + 2;
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(**symbol))
+                .ok_or(ParseError {
+                    message: format!("Unexpected \"{}\"", c),
+                    position: start,
+                })?;
+            i += symbol.len();
+            // A single = is read as a comparison too
+            let symbol = if *symbol == "=" { "==" } else { *symbol };
+            tokens.push((Token::Symbol(symbol), start));
+        }
+    }
+    tokens.push((Token::End, chars.len()));
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.next].0
+    }
+
+    fn position(&self) -> usize {
+        self.tokens[self.next].1
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.next].0.clone();
+        if self.next + 1 < self.tokens.len() {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, ParseError> {
+        Err(ParseError {
+            message: message.to_string(),
+            position: self.position(),
+        })
+    }
+
+    fn symbol(&mut self, symbols: &[&'static str]) -> Option<&'static str> {
+        let symbol = match *self.peek() {
+            Token::Symbol(symbol) if symbols.contains(&symbol) => symbol,
+            _ => return None,
+        };
+        self.advance();
+        Some(symbol)
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), ParseError> {
+        match self.symbol(&[symbol]) {
+            Some(_) => Ok(()),
+            None => self.error(&format!("Expected \"{}\"", symbol)),
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let left = self.additive()?;
+        let op = match self.symbol(&["<", "<=", ">", ">=", "==", "!="]) {
+            Some("<") => BinaryOp::Less,
+            Some("<=") => BinaryOp::LessEqual,
+            Some(">") => BinaryOp::Greater,
+            Some(">=") => BinaryOp::GreaterEqual,
+            Some("==") => BinaryOp::Equal,
+            Some(_) => BinaryOp::NotEqual,
+            None => return Ok(left),
+        };
+        let right = self.additive()?;
+        Ok(Expr::Binary(op, Box::new(left), Box::new(right)))
+    }
+
+    fn additive(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.term()?;
+        while let Some(symbol) = self.symbol(&["+", "-"]) {
+            let op = match symbol {
+                "+" => BinaryOp::Add,
+                _ => BinaryOp::Subtract,
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(self.term()?));
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.unary()?;
+        while let Some(symbol) = self.symbol(&["*", "/"]) {
+            let op = match symbol {
+                "*" => BinaryOp::Multiply,
+                _ => BinaryOp::Divide,
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        match self.symbol(&["-", "+"]) {
+            Some("-") => Ok(Expr::Negate(Box::new(self.unary()?))),
+            Some(_) => self.unary(),
+            None => self.power(),
+        }
+    }
+
+    // Powers bind tighter than negation on their left, so -2^2 is -4
+    fn power(&mut self) -> Result<Expr, ParseError> {
+        let base = self.primary()?;
+        match self.symbol(&["^"]) {
+            Some(_) => Ok(Expr::Binary(BinaryOp::Power, Box::new(base), Box::new(self.unary()?))),
+            None => Ok(base),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        match self.peek().clone() {
+            Token::Number(number) => {
+                self.advance();
+                Ok(Expr::Number(number))
+            }
+            Token::Name(name) => {
+                self.advance();
+                if self.symbol(&["("]).is_none() {
+                    return Ok(match name.as_str() {
+                        "nan" => Expr::Number(::std::f32::NAN),
+                        "pi" => Expr::Number(::std::f32::consts::PI),
+                        _ => Expr::Variable(name),
+                    });
+                }
+                let mut args = vec![];
+                if self.symbol(&[")"]).is_none() {
+                    loop {
+                        args.push(self.comparison()?);
+                        if self.symbol(&[","]).is_none() {
+                            break;
+                        }
+                    }
+                    self.expect(")")?;
+                }
+                Ok(Expr::Call(name, args))
+            }
+            Token::Symbol("(") => {
+                self.advance();
+                let inner = self.comparison()?;
+                self.expect(")")?;
+                Ok(inner)
+            }
+            Token::End => self.error("Unexpected end of expression"),
+            Token::Symbol(symbol) => self.error(&format!("Unexpected \"{}\"", symbol)),
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        next: 0,
+    };
+    let expr = parser.comparison()?;
+    match *parser.peek() {
+        Token::End => Ok(expr),
+        _ => parser.error("Expected an operator"),
+    }
+}
+
+impl Expr {
+    // Every variable in the order they are first used
+    pub fn variables(&self) -> Vec<String> {
+        let mut names = vec![];
+        self.collect_variables(&mut names);
+        names
+    }
+
+    fn collect_variables(&self, names: &mut Vec<String>) {
+        match self {
+            Expr::Number(_) => (),
+            Expr::Variable(name) => {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+            Expr::Negate(inner) => inner.collect_variables(names),
+            Expr::Binary(_, left, right) => {
+                left.collect_variables(names);
+                right.collect_variables(names);
+            }
+            Expr::Call(_, args) => {
+                for arg in args {
+                    arg.collect_variables(names);
+                }
+            }
+        }
+    }
+}
+
+// A constant or a grid for every time step, steps all share one size
+#[derive(Clone, Debug)]
+enum Field {
+    Scalar(f32),
+    Steps(Vec<Vec<f32>>),
+}
+
+struct Evaluator<'a> {
+    variables: &'a [(String, &'a [DataGrid])],
+    width: usize,
+    height: usize,
+}
+
+impl<'a> Evaluator<'a> {
+    // Combines fields cell by cell, single steps are repeated to match the others
+    fn apply(&self, args: Vec<Field>, f: &Fn(&[f32]) -> f32) -> Result<Field, String> {
+        let mut steps = 0;
+        for arg in &args {
+            if let Field::Steps(grids) = arg {
+                if grids.len() != 1 && steps > 1 && grids.len() != steps {
+                    return Err(format!("Cannot pair {} time steps with {}", steps, grids.len()));
+                }
+                steps = steps.max(grids.len());
+            }
+        }
+        let mut cell = vec![0.0; args.len()];
+        if steps == 0 {
+            for (value, arg) in cell.iter_mut().zip(&args) {
+                if let Field::Scalar(scalar) = arg {
+                    *value = *scalar;
+                }
+            }
+            return Ok(Field::Scalar(f(&cell)));
+        }
+
+        let size = self.width * self.height;
+        let mut result = Vec::with_capacity(steps);
+        for step in 0..steps {
+            let mut grid = Vec::with_capacity(size);
+            for i in 0..size {
+                for (value, arg) in cell.iter_mut().zip(&args) {
+                    *value = match arg {
+                        Field::Scalar(scalar) => *scalar,
+                        Field::Steps(grids) => grids[step % grids.len()][i],
+                    };
+                }
+                grid.push(f(&cell));
+            }
+            result.push(grid);
+        }
+        Ok(Field::Steps(result))
+    }
+
+    // Collapses the time steps of a field with f over the steps that have data
+    fn reduce(&self, field: Field, f: &Fn(&[f32]) -> f32) -> Field {
+        let grids = match field {
+            Field::Scalar(scalar) => return Field::Scalar(scalar),
+            Field::Steps(grids) => grids,
+        };
+        let size = self.width * self.height;
+        let mut samples = Vec::with_capacity(grids.len());
+        let reduced = (0..size)
+            .map(|i| {
+                samples.clear();
+                samples.extend(grids.iter().map(|grid| grid[i]).filter(|v| !v.is_nan()));
+                match samples.is_empty() {
+                    true => ::std::f32::NAN,
+                    false => f(&samples),
+                }
+            }).collect();
+        Field::Steps(vec![reduced])
+    }
+
+    fn variable(&self, name: &str) -> Result<Field, String> {
+        let grids = self
+            .variables
+            .iter()
+            .find(|(variable, _)| variable == name)
+            .map(|(_, grids)| *grids)
+            .ok_or_else(|| format!("Unknown variable \"{}\"", name))?;
+        if grids.is_empty() {
+            return Err(format!("\"{}\" has no data grid", name));
+        }
+        Ok(Field::Steps(
+            grids
+                .iter()
+                .map(|grid| {
+                    regrid(grid, self.width, self.height)
+                        .cells()
+                        .map(|(_, _, cell)| cell.unwrap_or(::std::f32::NAN))
+                        .collect()
+                }).collect(),
+        ))
+    }
+
+    fn call(&self, name: &str, args: &[Expr]) -> Result<Field, String> {
+        let fields = args
+            .iter()
+            .map(|arg| self.evaluate(arg))
+            .collect::<Result<Vec<Field>, String>>()?;
+        let count = fields.len();
+        let wrong_count = |expected: &str| {
+            Err(format!("{} takes {} arguments, not {}", name, expected, count))
+        };
+        let mut fields = fields.into_iter();
+        match (name, count) {
+            ("abs", 1) => self.apply(fields.collect(), &|v| v[0].abs()),
+            ("sqrt", 1) => self.apply(fields.collect(), &|v| v[0].sqrt()),
+            ("log", 1) => self.apply(fields.collect(), &|v| v[0].ln()),
+            ("exp", 1) => self.apply(fields.collect(), &|v| v[0].exp()),
+            ("min", 2) => self.apply(fields.collect(), &|v| match v[0].is_nan() || v[1].is_nan() {
+                true => ::std::f32::NAN,
+                false => v[0].min(v[1]),
+            }),
+            ("max", 2) => self.apply(fields.collect(), &|v| match v[0].is_nan() || v[1].is_nan() {
+                true => ::std::f32::NAN,
+                false => v[0].max(v[1]),
+            }),
+            ("where", 3) => self.apply(fields.collect(), &|v| match v[0] {
+                test if test.is_nan() => ::std::f32::NAN,
+                test if test != 0.0 => v[1],
+                _ => v[2],
+            }),
+            // With one argument min, max, mean and sum reduce over time steps
+            ("min", 1) => Ok(self.reduce(fields.next().unwrap(), &|v| {
+                v.iter().cloned().fold(::std::f32::INFINITY, f32::min)
+            })),
+            ("max", 1) => Ok(self.reduce(fields.next().unwrap(), &|v| {
+                v.iter().cloned().fold(::std::f32::NEG_INFINITY, f32::max)
+            })),
+            ("mean", 1) => Ok(self.reduce(fields.next().unwrap(), &|v| {
+                v.iter().sum::<f32>() / v.len() as f32
+            })),
+            ("sum", 1) => Ok(self.reduce(fields.next().unwrap(), &|v| v.iter().sum())),
+            ("abs", _) | ("sqrt", _) | ("log", _) | ("exp", _) | ("mean", _) | ("sum", _) => {
+                wrong_count("1")
+            }
+            ("min", _) | ("max", _) => wrong_count("1 or 2"),
+            ("where", _) => wrong_count("3"),
+            _ => Err(format!("Unknown function \"{}\"", name)),
+        }
+    }
+
+    fn evaluate(&self, expr: &Expr) -> Result<Field, String> {
+        match expr {
+            Expr::Number(number) => Ok(Field::Scalar(*number)),
+            Expr::Variable(name) => self.variable(name),
+            Expr::Negate(inner) => {
+                let inner = self.evaluate(inner)?;
+                self.apply(vec![inner], &|v| -v[0])
+            }
+            Expr::Binary(op, left, right) => {
+                let args = vec![self.evaluate(left)?, self.evaluate(right)?];
+                self.apply(args, &|v| op.apply(v[0], v[1]))
+            }
+            Expr::Call(name, args) => self.call(name, args),
+        }
+    }
+}
+
+// Evaluates an expression over named time series of grids. Grids are
+// resampled to the size of the first variable used and any result that is
+// not a finite number becomes missing data.
+pub fn evaluate(expr: &Expr, variables: &[(String, &[DataGrid])]) -> Result<Vec<DataGrid>, String> {
+    let first = expr.variables().into_iter().next().ok_or("The expression uses no variables")?;
+    let grid = variables
+        .iter()
+        .find(|(name, _)| *name == first)
+        .and_then(|(_, grids)| grids.first())
+        .ok_or_else(|| format!("Unknown variable \"{}\"", first))?;
+    let evaluator = Evaluator {
+        variables,
+        width: grid.width(),
+        height: grid.height(),
+    };
+
+    match evaluator.evaluate(expr)? {
+        Field::Scalar(_) => Err("The expression gives a single number".to_string()),
+        Field::Steps(grids) => Ok(grids
+            .into_iter()
+            .map(|grid| {
+                let values = grid
+                    .into_iter()
+                    .map(|value| match value.is_finite() {
+                        true => Some(value),
+                        false => None,
+                    }).collect();
+                DataGrid::new(evaluator.width, evaluator.height, values)
+            }).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data_grid::DataGrid;
+    use expr::*;
+
+    fn grid(values: &[f32]) -> DataGrid {
+        DataGrid::new(values.len(), 1, values.iter().map(|v| Some(*v)).collect())
+    }
+
+    fn cells(grid: &DataGrid) -> Vec<Option<f32>> {
+        grid.cells().map(|(_, _, cell)| cell).collect()
+    }
+
+    #[test]
+    fn parses_with_precedence() {
+        let expr = parse("1 + 2 * -x ^ 2").unwrap();
+        let x = Box::new(Expr::Variable("x".to_string()));
+        let power = Expr::Binary(BinaryOp::Power, x, Box::new(Expr::Number(2.0)));
+        let product = Expr::Binary(
+            BinaryOp::Multiply,
+            Box::new(Expr::Number(2.0)),
+            Box::new(Expr::Negate(Box::new(power))),
+        );
+        assert_eq!(
+            expr,
+            Expr::Binary(BinaryOp::Add, Box::new(Expr::Number(1.0)), Box::new(product))
+        );
+    }
+
+    #[test]
+    fn names_and_backticks_match_identifiers() {
+        assert_eq!(identifier("Average Temperature"), "average_temperature");
+        let expr = parse("`Average Temperature` - Height").unwrap();
+        assert_eq!(expr.variables(), vec!["average_temperature", "height"]);
+        assert_eq!(parse("1.5e-3").unwrap(), Expr::Number(1.5e-3));
+    }
+
+    #[test]
+    fn reports_where_parsing_failed() {
+        let error = parse("(a + b").unwrap_err();
+        assert_eq!(error.position, 6);
+        assert_eq!(parse("a + * b").unwrap_err().position, 4);
+        assert_eq!(parse("a b").unwrap_err().message, "Expected an operator");
+        assert_eq!(parse("a # b").unwrap_err().position, 2);
+    }
+
+    #[test]
+    fn evaluates_arithmetic_and_conditionals() {
+        let t_max = vec![grid(&[10.0, 20.0])];
+        let t_min = vec![grid(&[4.0, 10.0])];
+        let height = vec![grid(&[0.2, 0.8])];
+        let variables: Vec<(String, &[DataGrid])> = vec![
+            ("t_max".to_string(), &t_max[..]),
+            ("t_min".to_string(), &t_min[..]),
+            ("height".to_string(), &height[..]),
+        ];
+
+        let half = evaluate(&parse("(t_max - t_min) / 2").unwrap(), &variables).unwrap();
+        assert_eq!(cells(&half[0]), vec![Some(3.0), Some(5.0)]);
+        let masked = evaluate(&parse("where(height > 0.5, t_max, nan)").unwrap(), &variables).unwrap();
+        assert_eq!(cells(&masked[0]), vec![None, Some(20.0)]);
+        let larger = evaluate(&parse("max(t_min, 5) + abs(-1)").unwrap(), &variables).unwrap();
+        assert_eq!(cells(&larger[0]), vec![Some(6.0), Some(11.0)]);
+    }
+
+    #[test]
+    fn reduces_over_time_steps() {
+        let monthly = vec![grid(&[1.0, 2.0]), grid(&[3.0, 6.0]), grid(&[5.0, 1.0])];
+        let other = vec![grid(&[0.0]), grid(&[0.0])];
+        let variables: Vec<(String, &[DataGrid])> =
+            vec![("t".to_string(), &monthly[..]), ("o".to_string(), &other[..])];
+
+        let anomaly = evaluate(&parse("t - mean(t)").unwrap(), &variables).unwrap();
+        assert_eq!(anomaly.len(), 3);
+        assert_eq!(cells(&anomaly[0]), vec![Some(-2.0), Some(-1.0)]);
+        let peak = evaluate(&parse("max(t)").unwrap(), &variables).unwrap();
+        assert_eq!(cells(&peak[0]), vec![Some(5.0), Some(6.0)]);
+
+        assert!(evaluate(&parse("t + o").unwrap(), &variables).is_err());
+        assert!(evaluate(&parse("unknown * 2").unwrap(), &variables).is_err());
+        assert!(evaluate(&parse("sqrt(t, t)").unwrap(), &variables).is_err());
+    }
+}
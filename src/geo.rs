@@ -0,0 +1,254 @@
+use renderer::Vec3;
+
+// Locations are [latitude, longitude] in degrees, longitude in -180..180.
+// Texture u = 0 is longitude -180 and v = 1 is the north pole.
+
+pub fn lat_lon_to_tex(location: [f32; 2]) -> [f32; 2] {
+    [(location[1] + 180.0) / 360.0, (location[0] + 90.0) / 180.0]
+}
+
+pub fn tex_to_lat_lon(tex: [f32; 2]) -> [f32; 2] {
+    [tex[1] * 180.0 - 90.0, tex[0] * 360.0 - 180.0]
+}
+
+// Matches the vertex positions generated by Sphere
+pub fn lat_lon_to_position(location: [f32; 2]) -> Vec3 {
+    let lat = location[0].to_radians();
+    let lon = location[1].to_radians();
+    Vec3::new(
+        -lon.sin() * lat.cos(),
+        lat.sin(),
+        -lon.cos() * lat.cos(),
+    )
+}
+
+pub fn position_to_lat_lon(position: &Vec3) -> [f32; 2] {
+    let position = position.normalize();
+    let lat = position.y.max(-1.0).min(1.0).asin();
+    let lon = (-position.x).atan2(-position.z);
+    [lat.to_degrees(), lon.to_degrees()]
+}
+
+pub fn wrap_longitude(lon: f32) -> f32 {
+    let wrapped = (lon + 180.0) % 360.0;
+    if wrapped < 0.0 {
+        wrapped + 180.0
+    } else {
+        wrapped - 180.0
+    }
+}
+
+pub fn format_location(location: [f32; 2]) -> String {
+    let (lat, lon) = (location[0], location[1]);
+    format!(
+        "{:.2}°{} {:.2}°{}",
+        lat.abs(),
+        if lat < 0.0 { "S" } else { "N" },
+        lon.abs(),
+        if lon < 0.0 { "W" } else { "E" },
+    )
+}
+
+#[derive(Clone, Debug)]
+pub enum Region {
+    // A west bound larger than the east bound crosses the date line
+    Bounds {
+        south: f32,
+        north: f32,
+        west: f32,
+        east: f32,
+    },
+    // Vertices as [lat, lon], treated as straight lines on the map
+    Polygon(Vec<[f32; 2]>),
+}
+
+impl Region {
+    // The box between two opposite corners, running east from the first
+    // corner when the second is less than half way round the globe that way
+    pub fn from_corners(a: [f32; 2], b: [f32; 2]) -> Region {
+        let (west, east) = match wrap_longitude(b[1] - a[1]) >= 0.0 {
+            true => (a[1], b[1]),
+            false => (b[1], a[1]),
+        };
+        Region::Bounds {
+            south: a[0].min(b[0]),
+            north: a[0].max(b[0]),
+            west: wrap_longitude(west),
+            east: wrap_longitude(east),
+        }
+    }
+
+    // A closed ring around the region. Parallels of a box are split so no
+    // piece is drawn the wrong way round the globe
+    pub fn outline(&self) -> Vec<[f32; 2]> {
+        match self {
+            Region::Bounds {
+                south,
+                north,
+                west,
+                east,
+            } => {
+                let east = match east < west {
+                    true => east + 360.0,
+                    false => *east,
+                };
+                let along = |lat: f32, from: f32, to: f32| {
+                    (0..=4).map(move |i| [lat, wrap_longitude(from + (to - from) * i as f32 / 4.0)])
+                };
+                let mut ring: Vec<[f32; 2]> = along(*south, *west, east)
+                    .chain(along(*north, east, *west))
+                    .collect();
+                ring.push(ring[0]);
+                ring
+            }
+            Region::Polygon(vertices) => {
+                let mut ring = vertices.clone();
+                if let Some(first) = vertices.first() {
+                    ring.push(*first);
+                }
+                ring
+            }
+        }
+    }
+
+    pub fn contains(&self, location: [f32; 2]) -> bool {
+        let (lat, lon) = (location[0], location[1]);
+        match self {
+            Region::Bounds {
+                south,
+                north,
+                west,
+                east,
+            } => {
+                if lat < *south || lat > *north {
+                    return false;
+                }
+                match west <= east {
+                    true => lon >= *west && lon <= *east,
+                    false => lon >= *west || lon <= *east,
+                }
+            }
+            Region::Polygon(vertices) => {
+                if vertices.len() < 3 {
+                    return false;
+                }
+                // Edges take the short way across the date line like the
+                // drawn outline, so the ring may run past +-180
+                let mut unwrapped = Vec::with_capacity(vertices.len());
+                unwrapped.push(vertices[0]);
+                for pair in vertices.windows(2) {
+                    let mut delta = pair[1][1] - pair[0][1];
+                    if delta > 180.0 {
+                        delta -= 360.0;
+                    } else if delta < -180.0 {
+                        delta += 360.0;
+                    }
+                    let previous = unwrapped[unwrapped.len() - 1][1];
+                    unwrapped.push([pair[1][0], previous + delta]);
+                }
+                [lon, lon - 360.0, lon + 360.0]
+                    .iter()
+                    .any(|&lon| ring_contains(&unwrapped, lat, lon))
+            }
+        }
+    }
+}
+
+// The even-odd test on a ring of [lat, lon] vertices taken as flat coordinates
+fn ring_contains(vertices: &[[f32; 2]], lat: f32, lon: f32) -> bool {
+    let mut inside = false;
+    let mut j = vertices.len() - 1;
+    for i in 0..vertices.len() {
+        let (a, b) = (vertices[i], vertices[j]);
+        if (a[0] > lat) != (b[0] > lat) {
+            let crossing = a[1] + (lat - a[0]) / (b[0] - a[0]) * (b[1] - a[1]);
+            if lon < crossing {
+                inside = !inside;
+            }
+        }
+        j = i;
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use geo::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    #[test]
+    fn position_round_trips() {
+        for &location in [[0.0, 0.0], [45.0, 120.0], [-30.0, -75.0], [80.0, 179.0]].iter() {
+            let back = position_to_lat_lon(&lat_lon_to_position(location));
+            assert!(close(back[0], location[0]) && close(back[1], location[1]));
+        }
+    }
+
+    #[test]
+    fn texture_coordinates_match_sphere() {
+        // Sphere puts u = 0.5 on -z at the equator
+        let tex = lat_lon_to_tex([0.0, 0.0]);
+        assert!(close(tex[0], 0.5) && close(tex[1], 0.5));
+        let position = lat_lon_to_position(tex_to_lat_lon([0.5, 0.5]));
+        assert!(close(position.z, -1.0));
+    }
+
+    #[test]
+    fn wraps_longitude() {
+        assert!(close(wrap_longitude(190.0), -170.0));
+        assert!(close(wrap_longitude(-190.0), 170.0));
+        assert!(close(wrap_longitude(45.0), 45.0));
+    }
+
+    #[test]
+    fn bounds_cross_date_line() {
+        let region = Region::Bounds {
+            south: -10.0,
+            north: 10.0,
+            west: 170.0,
+            east: -170.0,
+        };
+        assert!(region.contains([0.0, 175.0]));
+        assert!(region.contains([0.0, -175.0]));
+        assert!(!region.contains([0.0, 0.0]));
+        assert!(!region.contains([20.0, 175.0]));
+    }
+
+    #[test]
+    fn polygons_cross_date_line() {
+        let region = Region::Polygon(vec![
+            [-10.0, 170.0],
+            [10.0, 170.0],
+            [10.0, -170.0],
+            [-10.0, -170.0],
+        ]);
+        assert!(region.contains([0.0, 175.0]));
+        assert!(region.contains([0.0, -175.0]));
+        assert!(region.contains([0.0, 180.0]));
+        assert!(!region.contains([0.0, 0.0]));
+        assert!(!region.contains([0.0, 160.0]));
+        assert!(!region.contains([20.0, 175.0]));
+    }
+
+    #[test]
+    fn boxes_from_corners_take_the_short_way() {
+        let region = Region::from_corners([10.0, 175.0], [-10.0, -175.0]);
+        assert!(region.contains([0.0, 179.0]));
+        assert!(!region.contains([0.0, 0.0]));
+        let ring = region.outline();
+        assert_eq!(ring.first(), ring.last());
+        assert!(ring.iter().all(|point| point[1].abs() >= 175.0 - 1e-3));
+        assert_eq!(Region::Polygon(vec![[0.0, 0.0]; 3]).outline().len(), 4);
+    }
+
+    #[test]
+    fn polygon_contains() {
+        let region = Region::Polygon(vec![[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]]);
+        assert!(region.contains([5.0, 5.0]));
+        assert!(!region.contains([15.0, 5.0]));
+        assert!(!region.contains([5.0, -1.0]));
+    }
+}
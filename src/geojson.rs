@@ -0,0 +1,231 @@
+use serde_json::{self, Map, Value};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// Coordinates are kept as [lat, lon] like the rest of the crate and only
+// swapped to GeoJSON's [lon, lat] order when reading and writing.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Geometry {
+    Point([f32; 2]),
+    MultiPoint(Vec<[f32; 2]>),
+    LineString(Vec<[f32; 2]>),
+    MultiLineString(Vec<Vec<[f32; 2]>>),
+    // The first ring is the outline, any others are holes
+    Polygon(Vec<Vec<[f32; 2]>>),
+    MultiPolygon(Vec<Vec<Vec<[f32; 2]>>>),
+}
+
+impl Geometry {
+    // Every line and polygon ring, points have no outline so give nothing
+    pub fn lines(&self) -> Vec<Vec<[f32; 2]>> {
+        match self {
+            Geometry::Point(_) | Geometry::MultiPoint(_) => vec![],
+            Geometry::LineString(line) => vec![line.clone()],
+            Geometry::MultiLineString(lines) | Geometry::Polygon(lines) => lines.clone(),
+            Geometry::MultiPolygon(polygons) => polygons.iter().flat_map(|rings| rings.clone()).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Feature {
+    pub geometry: Geometry,
+    pub properties: Map<String, Value>,
+}
+
+impl Feature {
+    pub fn new(geometry: Geometry) -> Feature {
+        Feature {
+            geometry,
+            properties: Map::new(),
+        }
+    }
+
+    pub fn property_str(&self, key: &str) -> Option<&str> {
+        self.properties.get(key).and_then(|value| value.as_str())
+    }
+}
+
+fn parse_position(value: &Value) -> Option<[f32; 2]> {
+    let position = value.as_array()?;
+    let lon = position.get(0)?.as_f64()? as f32;
+    let lat = position.get(1)?.as_f64()? as f32;
+    Some([lat, lon])
+}
+
+fn parse_line(value: &Value) -> Option<Vec<[f32; 2]>> {
+    value.as_array()?.iter().map(parse_position).collect()
+}
+
+fn parse_lines(value: &Value) -> Option<Vec<Vec<[f32; 2]>>> {
+    value.as_array()?.iter().map(parse_line).collect()
+}
+
+fn parse_geometry(value: &Value) -> Option<Geometry> {
+    let coordinates = value.get("coordinates")?;
+    let geometry = match value.get("type")?.as_str()? {
+        "Point" => Geometry::Point(parse_position(coordinates)?),
+        "MultiPoint" => Geometry::MultiPoint(parse_line(coordinates)?),
+        "LineString" => Geometry::LineString(parse_line(coordinates)?),
+        "MultiLineString" => Geometry::MultiLineString(parse_lines(coordinates)?),
+        "Polygon" => Geometry::Polygon(parse_lines(coordinates)?),
+        "MultiPolygon" => Geometry::MultiPolygon(
+            coordinates
+                .as_array()?
+                .iter()
+                .map(parse_lines)
+                .collect::<Option<_>>()?,
+        ),
+        _ => return None,
+    };
+    Some(geometry)
+}
+
+// Collects every feature out of a FeatureCollection, Feature or bare geometry,
+// anything that cannot be read is skipped
+pub fn parse(value: &Value, features: &mut Vec<Feature>) {
+    match value.get("type").and_then(|kind| kind.as_str()) {
+        Some("FeatureCollection") => {
+            if let Some(items) = value.get("features").and_then(|items| items.as_array()) {
+                for item in items {
+                    parse(item, features);
+                }
+            }
+        }
+        Some("Feature") => {
+            let geometry = match value.get("geometry").and_then(parse_geometry) {
+                Some(geometry) => geometry,
+                None => return,
+            };
+            let properties = value
+                .get("properties")
+                .and_then(|properties| properties.as_object())
+                .cloned()
+                .unwrap_or_default();
+            features.push(Feature {
+                geometry,
+                properties,
+            });
+        }
+        Some("GeometryCollection") => {
+            if let Some(items) = value.get("geometries").and_then(|items| items.as_array()) {
+                for item in items {
+                    parse(item, features);
+                }
+            }
+        }
+        Some(_) => {
+            if let Some(geometry) = parse_geometry(value) {
+                features.push(Feature::new(geometry));
+            }
+        }
+        None => (),
+    }
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<Feature>, Box<Error>> {
+    let text = fs::read_to_string(path)?;
+    let value: Value = serde_json::from_str(&text)?;
+    let mut features = vec![];
+    parse(&value, &mut features);
+    Ok(features)
+}
+
+fn position_json(position: &[f32; 2]) -> Value {
+    json!([position[1], position[0]])
+}
+
+fn line_json(line: &[[f32; 2]]) -> Value {
+    Value::Array(line.iter().map(position_json).collect())
+}
+
+fn lines_json(lines: &[Vec<[f32; 2]>]) -> Value {
+    Value::Array(lines.iter().map(|line| line_json(line)).collect())
+}
+
+fn geometry_json(geometry: &Geometry) -> Value {
+    let (kind, coordinates) = match geometry {
+        Geometry::Point(position) => ("Point", position_json(position)),
+        Geometry::MultiPoint(points) => ("MultiPoint", line_json(points)),
+        Geometry::LineString(line) => ("LineString", line_json(line)),
+        Geometry::MultiLineString(lines) => ("MultiLineString", lines_json(lines)),
+        Geometry::Polygon(rings) => ("Polygon", lines_json(rings)),
+        Geometry::MultiPolygon(polygons) => (
+            "MultiPolygon",
+            Value::Array(polygons.iter().map(|rings| lines_json(rings)).collect()),
+        ),
+    };
+    json!({ "type": kind, "coordinates": coordinates })
+}
+
+pub fn to_json(features: &[Feature]) -> Value {
+    let features: Vec<Value> = features
+        .iter()
+        .map(|feature| {
+            json!({
+                "type": "Feature",
+                "geometry": geometry_json(&feature.geometry),
+                "properties": feature.properties,
+            })
+        }).collect();
+    json!({ "type": "FeatureCollection", "features": features })
+}
+
+pub fn save(path: impl AsRef<Path>, features: &[Feature]) -> Result<(), Box<Error>> {
+    if let Some(parent) = path.as_ref().parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    let text = serde_json::to_string_pretty(&to_json(features))?;
+    fs::write(path, text)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use geojson::*;
+
+    #[test]
+    fn reads_points_as_lat_lon() {
+        let value: Value = serde_json::from_str(
+            r#"{
+                "type": "FeatureCollection",
+                "features": [
+                    {
+                        "type": "Feature",
+                        "geometry": { "type": "Point", "coordinates": [151.2, -33.9] },
+                        "properties": { "name": "Sydney" }
+                    },
+                    { "type": "Feature", "geometry": null, "properties": {} }
+                ]
+            }"#,
+        ).unwrap();
+        let mut features = vec![];
+        parse(&value, &mut features);
+
+        assert_eq!(features.len(), 1);
+        assert_eq!(features[0].geometry, Geometry::Point([-33.9, 151.2]));
+        assert_eq!(features[0].property_str("name"), Some("Sydney"));
+    }
+
+    #[test]
+    fn round_trips_polygons() {
+        let rings = vec![vec![[0.0, 0.0], [0.0, 10.0], [10.0, 10.0], [0.0, 0.0]]];
+        let features = vec![Feature::new(Geometry::Polygon(rings.clone()))];
+        let mut parsed = vec![];
+        parse(&to_json(&features), &mut parsed);
+
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].geometry, Geometry::Polygon(rings));
+    }
+
+    #[test]
+    fn polygons_give_every_ring_as_a_line() {
+        let ring = vec![[0.0, 0.0], [0.0, 10.0], [10.0, 10.0], [0.0, 0.0]];
+        let geometry = Geometry::MultiPolygon(vec![vec![ring.clone()], vec![ring.clone(), ring]]);
+        assert_eq!(geometry.lines().len(), 3);
+        assert!(Geometry::Point([0.0, 0.0]).lines().is_empty());
+    }
+}
@@ -0,0 +1,231 @@
+use geo::lat_lon_to_position;
+use glium::{
+    backend::{Backend, Context},
+    SwapBuffersError,
+};
+use osmesa_sys;
+use renderer::{
+    camera::{PCamera, Projection},
+    Vec3, PV,
+};
+use std::error::Error;
+use std::f32::consts::PI;
+use std::ffi::CString;
+use std::fs;
+use std::os::raw::c_void;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::rc::Rc;
+use toml;
+use {build_state, Assets};
+
+#[derive(Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub assets: Assets,
+    #[serde(default)]
+    pub output_dir: PathBuf,
+    #[serde(default = "default_size")]
+    pub size: [u32; 2],
+    #[serde(default, rename = "view")]
+    pub views: Vec<ViewScript>,
+}
+
+#[derive(Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectionScript {
+    Perspective,
+    Orthographic,
+}
+
+impl Default for ProjectionScript {
+    fn default() -> ProjectionScript {
+        ProjectionScript::Perspective
+    }
+}
+
+#[derive(Copy, Clone, Deserialize)]
+#[serde(default)]
+pub struct CameraScript {
+    pub latitude: f32,
+    pub longitude: f32,
+    pub distance: f32,
+}
+
+impl Default for CameraScript {
+    fn default() -> CameraScript {
+        CameraScript {
+            latitude: 0.0,
+            longitude: 0.0,
+            distance: 4.0,
+        }
+    }
+}
+
+impl CameraScript {
+    pub fn camera(&self, aspect: f32, projection: ProjectionScript) -> PCamera {
+        // Looking straight down a pole makes the up vector degenerate
+        let lat = self.latitude.max(-89.0).min(89.0);
+        let position = lat_lon_to_position([lat, self.longitude]) * self.distance;
+
+        let mut camera = PCamera::new(
+            position,
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Projection::Perspective(PV::new(aspect, PI * 0.25, 0.1, 10.0)),
+        );
+        if let ProjectionScript::Orthographic = projection {
+            camera.orthographic_projection();
+        }
+        camera
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ViewScript {
+    pub output: PathBuf,
+    pub value: String,
+    #[serde(default)]
+    pub time: f32,
+    #[serde(default)]
+    pub camera: CameraScript,
+    #[serde(default)]
+    pub projection: ProjectionScript,
+    #[serde(default)]
+    pub range: Option<[f32; 2]>,
+    #[serde(default = "default_true")]
+    pub legend: bool,
+    #[serde(default = "default_true")]
+    pub title: bool,
+}
+
+fn default_size() -> [u32; 2] {
+    [1920, 1080]
+}
+
+fn default_true() -> bool {
+    true
+}
+
+pub fn load_manifest(path: impl AsRef<Path>) -> Result<Manifest, Box<Error>> {
+    let text = fs::read_to_string(path)?;
+    Ok(toml::from_str(&text)?)
+}
+
+// GL_UNSIGNED_BYTE, the type of the OSMesa colour buffer
+const UNSIGNED_BYTE: u32 = 0x1401;
+
+// A software OpenGL context that never touches a window system, so batch
+// rendering works on servers without X or Wayland. Views are drawn into
+// framebuffers of their own, the context only needs a one pixel buffer.
+struct OsMesaBackend {
+    context: osmesa_sys::OSMesaContext,
+    buffer: Box<[u8; 4]>,
+}
+
+impl OsMesaBackend {
+    fn new() -> Result<OsMesaBackend, Box<Error>> {
+        osmesa_sys::OsMesa::try_loading()
+            .map_err(|error| format!("Failed to load libOSMesa: {:?}", error))?;
+
+        // The shaders need GLSL 4.40
+        let attributes = [
+            osmesa_sys::OSMESA_PROFILE,
+            osmesa_sys::OSMESA_CORE_PROFILE,
+            osmesa_sys::OSMESA_CONTEXT_MAJOR_VERSION,
+            4,
+            osmesa_sys::OSMESA_CONTEXT_MINOR_VERSION,
+            4,
+            0,
+        ];
+        let context =
+            unsafe { osmesa_sys::OSMesaCreateContextAttribs(attributes.as_ptr(), ptr::null_mut()) };
+        if context.is_null() {
+            return Err("OSMesa could not create an OpenGL 4.4 core context".into());
+        }
+
+        Ok(OsMesaBackend {
+            context,
+            buffer: Box::new([0; 4]),
+        })
+    }
+}
+
+unsafe impl Backend for OsMesaBackend {
+    fn swap_buffers(&self) -> Result<(), SwapBuffersError> {
+        Ok(())
+    }
+
+    unsafe fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        let symbol = match CString::new(symbol) {
+            Ok(symbol) => symbol,
+            Err(_) => return ptr::null(),
+        };
+        match osmesa_sys::OSMesaGetProcAddress(symbol.as_ptr()) {
+            Some(function) => function as *const c_void,
+            None => ptr::null(),
+        }
+    }
+
+    fn get_framebuffer_dimensions(&self) -> (u32, u32) {
+        (1, 1)
+    }
+
+    fn is_current(&self) -> bool {
+        unsafe { osmesa_sys::OSMesaGetCurrentContext() == self.context }
+    }
+
+    unsafe fn make_current(&self) {
+        let buffer = self.buffer.as_ptr() as *mut c_void;
+        if osmesa_sys::OSMesaMakeCurrent(self.context, buffer, UNSIGNED_BYTE, 1, 1) == 0 {
+            panic!("OSMesaMakeCurrent failed");
+        }
+    }
+}
+
+impl Drop for OsMesaBackend {
+    fn drop(&mut self) {
+        unsafe { osmesa_sys::OSMesaDestroyContext(self.context) }
+    }
+}
+
+fn headless_context() -> Result<Rc<Context>, Box<Error>> {
+    let backend = OsMesaBackend::new()?;
+    let context = unsafe { Context::new(backend, true, Default::default())? };
+    Ok(context)
+}
+
+// Renders every view in the manifest without opening a window
+pub fn run(path: impl AsRef<Path>) -> Result<(), Box<Error>> {
+    let manifest = load_manifest(path)?;
+    let display = headless_context()?;
+    let mut glstate = build_state(&display, &manifest.assets);
+    glstate.update_overlays(&display);
+
+    let size = (manifest.size[0].max(1), manifest.size[1].max(1));
+    let aspect = size.0 as f32 / size.1 as f32;
+    if !manifest.output_dir.as_os_str().is_empty() {
+        fs::create_dir_all(&manifest.output_dir)?;
+    }
+
+    for view in &manifest.views {
+        let index = match glstate.find_value(&view.value) {
+            Some(index) => index,
+            None => return Err(format!("Unknown value \"{}\"", view.value).into()),
+        };
+        glstate.show_value(index);
+        glstate.set_value_selection(index, view.time);
+        if let Some(range) = view.range {
+            glstate.set_value_range(index, range);
+        }
+        glstate.camera = view.camera.camera(aspect, view.projection);
+
+        let viewports = glstate.export_viewports(0);
+        let image = glstate.render_image(&display, size, &viewports, view.legend, view.title)?;
+        let output = manifest.output_dir.join(&view.output);
+        image.save(&output)?;
+        println!("Rendered {}", output.display());
+    }
+
+    Ok(())
+}
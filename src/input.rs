@@ -1,4 +1,6 @@
-use glium::glutin::{ElementState, Event, GlWindow, MouseButton, MouseScrollDelta, WindowEvent};
+use glium::glutin::{
+    ElementState, Event, GlWindow, ModifiersState, MouseButton, MouseScrollDelta, WindowEvent,
+};
 use imgui::{ImGui, Ui};
 use renderer::camera::PCamera;
 use renderer::Vec2;
@@ -54,8 +56,9 @@ impl Mouse {
 pub struct MouseState {
     pub mouse: Mouse,
     pub hovered: f32,
-    pub pressed_on_ui: bool,
+    pub pressed_on_ui: (bool, bool, bool),
     pub on_ui: bool,
+    pub modifiers: ModifiersState,
 }
 
 impl MouseState {
@@ -63,8 +66,9 @@ impl MouseState {
         MouseState {
             mouse: Mouse::new(),
             hovered: 0.0,
-            pressed_on_ui: false,
+            pressed_on_ui: (false, false, false),
             on_ui: false,
+            modifiers: ModifiersState::default(),
         }
     }
 
@@ -86,12 +90,36 @@ impl MouseState {
     }
 
     pub fn mouse_one_down(&mut self, state: bool, ui: &Ui) {
-        self.pressed_on_ui = ui.want_capture_mouse() && state;
+        self.pressed_on_ui.0 = ui.want_capture_mouse() && state;
         self.mouse.pressed.0 = state;
     }
 
+    pub fn mouse_two_down(&mut self, state: bool, ui: &Ui) {
+        self.pressed_on_ui.1 = ui.want_capture_mouse() && state;
+        self.mouse.pressed.1 = state;
+    }
+
+    pub fn mouse_three_down(&mut self, state: bool, ui: &Ui) {
+        self.pressed_on_ui.2 = ui.want_capture_mouse() && state;
+        self.mouse.pressed.2 = state;
+    }
+
     pub fn get_drag_off_ui(&self) -> Option<Vec2> {
-        if !self.pressed_on_ui && self.mouse.pressed.0 {
+        if !self.pressed_on_ui.0 && self.mouse.pressed.0 {
+            return Some(self.mouse.movement);
+        }
+        None
+    }
+
+    pub fn get_right_drag_off_ui(&self) -> Option<Vec2> {
+        if !self.pressed_on_ui.1 && self.mouse.pressed.1 {
+            return Some(self.mouse.movement);
+        }
+        None
+    }
+
+    pub fn get_middle_drag_off_ui(&self) -> Option<Vec2> {
+        if !self.pressed_on_ui.2 && self.mouse.pressed.2 {
             return Some(self.mouse.movement);
         }
         None
@@ -111,7 +139,12 @@ impl MouseState {
     ) {
         match event {
             Event::WindowEvent { event, .. } => match event {
-                WindowEvent::CursorMoved { position, .. } => {
+                WindowEvent::CursorMoved {
+                    position,
+                    modifiers,
+                    ..
+                } => {
+                    self.modifiers = *modifiers;
                     let pos = position
                         .to_physical(window.get_hidpi_factor())
                         .to_logical(hidpi_factor);
@@ -125,12 +158,26 @@ impl MouseState {
                     };
                     self.mouse.wheel = y
                 }
-                WindowEvent::MouseInput { button, state, .. } => match button {
-                    MouseButton::Left => self.mouse_one_down(*state == ElementState::Pressed, ui),
-                    MouseButton::Right => self.mouse.pressed.1 = *state == ElementState::Pressed,
-                    MouseButton::Middle => self.mouse.pressed.2 = *state == ElementState::Pressed,
-                    _ => (),
-                },
+                WindowEvent::MouseInput {
+                    button,
+                    state,
+                    modifiers,
+                    ..
+                } => {
+                    self.modifiers = *modifiers;
+                    match button {
+                        MouseButton::Left => {
+                            self.mouse_one_down(*state == ElementState::Pressed, ui)
+                        }
+                        MouseButton::Right => {
+                            self.mouse_two_down(*state == ElementState::Pressed, ui)
+                        }
+                        MouseButton::Middle => {
+                            self.mouse_three_down(*state == ElementState::Pressed, ui)
+                        }
+                        _ => (),
+                    }
+                }
                 _ => (),
             },
             _ => (),
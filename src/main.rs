@@ -1,6 +1,7 @@
 #![windows_subsystem = "windows"]
 #![allow(dead_code)]
 
+extern crate gif;
 #[macro_use]
 extern crate glium;
 extern crate image;
@@ -9,31 +10,81 @@ extern crate imgui;
 extern crate heat_map;
 extern crate imgui_glium_renderer as imrender;
 extern crate nalgebra as na;
+extern crate osmesa_sys;
 extern crate renderer;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+extern crate toml;
 
+mod contour;
+mod data_grid;
+mod derived;
 mod evec;
+mod expr;
+mod geo;
+mod geojson;
+mod headless;
 mod input;
+mod shapefile;
 mod sphere;
 mod state;
+mod stats;
 mod support;
+mod temporal;
+mod text;
+mod threshold;
 mod util;
+mod vector_field;
 mod window;
 
-use glium::backend::glutin::Display;
+use glium::backend::Facade;
 use glium::{glutin::EventsLoop, Program, Surface};
-use heat_map::math::Range;
 use imgui::ImString;
 use renderer::{
     camera::{PCamera, Projection},
     Vec3, PV,
 };
-use state::{GlobalState, value::Measurement};
+use state::{GlobalState, time::TimeAxis, value::Measurement};
+use std::env;
 use std::f32::consts::PI;
+use std::path::Path;
+use std::process;
 use support::load_image;
 use util::*;
+use vector_field::VectorField;
 use window::Window;
 
-fn build_state(display: &Display) -> GlobalState {
+const SESSION_FILE: &str = "session.toml";
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Assets {
+    pub height: String,
+    pub overlay: String,
+    pub map: String,
+    pub temperature: String,
+    // Eastward and northward wind components, skipped unless both exist
+    pub wind_u: String,
+    pub wind_v: String,
+}
+
+impl Default for Assets {
+    fn default() -> Assets {
+        Assets {
+            height: "assets/whms.png".to_string(),
+            overlay: "assets/Pure B and W Map.png".to_string(),
+            map: "assets/map_pic.jpg".to_string(),
+            temperature: "assets/tempgrid.bin".to_string(),
+            wind_u: "assets/wind_u.bin".to_string(),
+            wind_v: "assets/wind_v.bin".to_string(),
+        }
+    }
+}
+
+fn build_state<F: Facade>(display: &F, assets: &Assets) -> GlobalState {
     let camera = PCamera::new(
         Vec3::new(0.0, 0.0, 4.0),
         Vec3::new(0.0, 0.0, 0.0),
@@ -61,72 +112,114 @@ fn build_state(display: &Display) -> GlobalState {
         include_str!("shaders/frag_box.glsl"),
         None,
     ).unwrap();
+    let marker_program = Program::from_source(
+        display,
+        include_str!("shaders/vert_marker.glsl"),
+        include_str!("shaders/frag_marker.glsl"),
+        None,
+    ).unwrap();
+    let line_program = Program::from_source(
+        display,
+        include_str!("shaders/vert_line.glsl"),
+        include_str!("shaders/frag_line.glsl"),
+        Some(include_str!("shaders/geom_line.glsl")),
+    ).unwrap();
     let monthly_range = [-40.0, 50.0];
     let stdrange = [0.0, 40.0];
     let (avg, monthly_values, stddev) = load_temp_values(
         display,
-        "assets/tempgrid.bin",
-        Range::new(monthly_range[0], monthly_range[1]),
-        Range::new(stdrange[0], stdrange[1]),
+        &assets.temperature,
+        monthly_range,
+        stdrange,
     );
 
     let mut glstate = GlobalState::new_default_tex(
         display,
         camera,
-        "assets/whms.png",
-        "assets/Pure B and W Map.png",
+        &assets.height,
+        &assets.overlay,
         ImString::new("World Map"),
-        "assets/map_pic.jpg",
+        &assets.map,
         hsv_program,
         colour_program,
         box_program,
+        marker_program,
+        line_program,
     ).unwrap();
-    let height = vec![load_image(display, "assets/whms.png")];
+    let height = vec![load_image(display, &assets.height)];
 
     glstate.add_new_value(
         height,
+        vec![],
         ImString::new("Height"),
         Measurement::Is {
             normalised: [0.0, 1.0],
             init_range: [0.0, 1.0],
             range: [0.0, 1.0],
         },
+        TimeAxis::Steps,
     );
 
     glstate.add_new_value(
-        avg,
+        avg.0,
+        avg.1,
         ImString::new("Average Temperature"),
         Measurement::Is {
             normalised: [0.5, 1.0],
             init_range: monthly_range,
             range: monthly_range,
         },
+        TimeAxis::Steps,
     );
 
     glstate.add_new_value(
-        monthly_values,
+        monthly_values.0,
+        monthly_values.1,
         ImString::new("Monthly Temperature"),
         Measurement::Is {
             normalised: [0.5, 1.0],
             init_range: monthly_range,
             range: monthly_range,
         },
+        TimeAxis::Climatology,
     );
 
     glstate.add_new_value(
-        stddev,
+        stddev.0,
+        stddev.1,
         ImString::new("Standard Deviation"),
         Measurement::Is {
             normalised: [0.5, 1.0],
             init_range: stdrange,
             range: stdrange,
         },
+        TimeAxis::Steps,
     );
 
+    if Path::new(&assets.wind_u).exists() && Path::new(&assets.wind_v).exists() {
+        let u = load_component_grids(&assets.wind_u);
+        let v = load_component_grids(&assets.wind_v);
+        let fields = u.into_iter().zip(v).map(|(u, v)| VectorField::new(u, v)).collect();
+        glstate.add_vector_value(display, fields, ImString::new("Wind"), TimeAxis::Climatology);
+    }
+
     glstate
 }
 
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    if let Some(flag) = args.iter().position(|arg| arg == "--headless") {
+        let manifest = args
+            .get(flag + 1)
+            .map(|path| path.as_str())
+            .unwrap_or("manifest.toml");
+        if let Err(error) = headless::run(manifest) {
+            eprintln!("Headless rendering failed: {}", error);
+            process::exit(1);
+        }
+        return;
+    }
+
     let mut events_loop = EventsLoop::new();
     let mut window = Window::new(
         "Climate Visualisation",
@@ -137,16 +230,25 @@ fn main() {
         &events_loop,
     );
     let hidpi = window.display.gl_window().get_hidpi_factor() as f32;
-    let mut glstate = build_state(&window.display);
+    let mut glstate = build_state(&window.display, &Assets::default());
+    if Path::new(SESSION_FILE).exists() {
+        if let Err(error) = glstate.load_session(SESSION_FILE) {
+            eprintln!("Failed to load {}: {}", SESSION_FILE, error);
+        }
+    }
     let identity: na::Matrix4<f32> = na::Matrix4::identity();
 
     support::run(
         &mut window,
         &mut events_loop,
-        |target, ui, mouse, events, dt, no_render| {
+        |display, target, ui, mouse, events, dt, no_render| {
             let dims = target.get_dimensions();
             glstate.update_time(dt);
             glstate.build_ui(ui);
+            glstate.handle_exports(display);
+            glstate.handle_animation_exports(display);
+            glstate.handle_derived(display);
+            glstate.update_overlays(display);
             glstate.handle_mouse(mouse, dims, hidpi);
             for event in events {
                 input::handle_input(event, &mut glstate.camera);
@@ -162,4 +264,8 @@ fn main() {
             true
         },
     );
+
+    if let Err(error) = glstate.save_session(SESSION_FILE) {
+        eprintln!("Failed to save {}: {}", SESSION_FILE, error);
+    }
 }
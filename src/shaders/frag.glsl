@@ -2,12 +2,32 @@
 
 uniform sampler2D overlay;
 uniform sampler2D colour_map1;
+// 1 on land, 0.5 over the ocean and 0 where the mask is unknown
+uniform sampler2D land_mask;
+// 0 shows everywhere, 1 only land and 2 only the ocean
+uniform int surface_filter;
 
 in vec3 f_normal;
 in vec2 f_tex;
 
 out vec4 colour;
 
+// Whether a surface filter keeps the cell at a texture coordinate, everything is kept
+// where the mask is unknown
+bool keeps(int surface, vec2 tex) {
+    if (surface == 0) {
+        return true;
+    }
+    ivec2 size = textureSize(land_mask, 0);
+    ivec2 coords = min(ivec2(tex * size), size - 1);
+    float texel = texelFetch(land_mask, coords, 0).x;
+    if (texel < 0.25) {
+        return true;
+    }
+    bool land = texel >= 0.75;
+    return surface == 1 ? land : !land;
+}
+
 void main() {
     vec3 light = normalize(vec3(1, 1, 1));
     float brightness = dot(light, normalize(f_normal));
@@ -24,4 +44,7 @@ void main() {
 
 
     colour = image_colour1;
+    if (!keeps(surface_filter, f_tex)) {
+        colour = vec4(vec3(0.2), 1.0);
+    }
 }
\ No newline at end of file
@@ -1,5 +1,11 @@
 #version 440 
 
+uniform bool diverging;
+// Fills the box with the no-data colour instead, hatched like the globe
+uniform bool swatch;
+uniform vec3 swatch_colour;
+uniform bool swatch_hatched;
+
 in float f_hue;
 out vec4 colour;
 
@@ -31,6 +37,25 @@ vec4 hsv_to_rgb(in float hue) {
     return(vec4(colour, 1.0));
 }
 
+// Blue through white to red as the value goes from 0 to 1
+vec4 diverging_to_rgb(in float value) {
+    vec3 low = vec3(0.23, 0.3, 0.75);
+    vec3 middle = vec3(0.95, 0.95, 0.95);
+    vec3 high = vec3(0.7, 0.02, 0.15);
+    value = clamp(value, 0.0, 1.0);
+    vec3 colour = value < 0.5
+        ? mix(low, middle, value * 2.0)
+        : mix(middle, high, value * 2.0 - 1.0);
+    return vec4(colour, 1.0);
+}
+
 void main() {
-    colour = hsv_to_rgb(1.0 - f_hue);
+    if (swatch) {
+        colour = vec4(swatch_colour, 1.0);
+        if (swatch_hatched && mod(gl_FragCoord.x - gl_FragCoord.y, 8.0) >= 3.0) {
+            colour = vec4(vec3(0.22), 1.0);
+        }
+        return;
+    }
+    colour = diverging ? diverging_to_rgb(f_hue) : hsv_to_rgb(1.0 - f_hue);
 }
\ No newline at end of file
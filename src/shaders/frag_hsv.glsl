@@ -1,6 +1,8 @@
 #version 440
 
 uniform sampler2D overlay;
+// 0 leaves the raster overlay out, 1 multiplies it in fully
+uniform float overlay_strength;
 
 uniform sampler2D colour_map1;
 uniform sampler2D colour_map2;
@@ -9,6 +11,27 @@ uniform vec2 init_range;
 uniform vec2 range;
 uniform vec2 normalised;
 
+// 0 turns thresholds off, then above, below, between and outside the limits
+uniform int threshold_condition;
+uniform vec2 thresholds;
+// 0 hatches matching cells, 1 outlines them and 2 greys out the rest
+uniform int threshold_style;
+uniform vec3 threshold_colour;
+// Limits the threshold to land or ocean, numbered as surface_filter
+uniform int threshold_surface;
+
+// 1 on land, 0.5 over the ocean and 0 where the mask is unknown
+uniform sampler2D land_mask;
+// 0 shows everywhere, 1 only land and 2 only the ocean
+uniform int surface_filter;
+
+// Cells without data are filled with the colour, or hatched with it when the style is 1
+uniform vec3 no_data_colour;
+uniform int no_data_style;
+
+// Uses the blue to red diverging colours instead of the hues
+uniform bool diverging;
+
 in vec3 f_normal;
 in vec2 f_tex;
 
@@ -42,6 +65,22 @@ vec4 hsv_to_rgb(in float hue) {
     return(vec4(colour, 1.0));
 }
 
+// Blue through white to red as the value goes from 0 to 1
+vec4 diverging_to_rgb(in float value) {
+    vec3 low = vec3(0.23, 0.3, 0.75);
+    vec3 middle = vec3(0.95, 0.95, 0.95);
+    vec3 high = vec3(0.7, 0.02, 0.15);
+    value = clamp(value, 0.0, 1.0);
+    vec3 colour = value < 0.5
+        ? mix(low, middle, value * 2.0)
+        : mix(middle, high, value * 2.0 - 1.0);
+    return vec4(colour, 1.0);
+}
+
+vec4 value_to_rgb(in float value) {
+    return diverging ? diverging_to_rgb(value) : hsv_to_rgb(1.0 - value);
+}
+
 float new_range(vec2 old_range, vec2 new_range, float value) {
     float span = old_range.y - old_range.x;
     value = value * span + old_range.x;
@@ -49,6 +88,87 @@ float new_range(vec2 old_range, vec2 new_range, float value) {
     return (value - new_range.x) / span;
 }
 
+// Whether a surface filter keeps the cell at a texture coordinate, everything is kept
+// where the mask is unknown
+bool keeps(int surface, vec2 tex) {
+    if (surface == 0) {
+        return true;
+    }
+    ivec2 size = textureSize(land_mask, 0);
+    ivec2 coords = min(ivec2(tex * size), size - 1);
+    float texel = texelFetch(land_mask, coords, 0).x;
+    if (texel < 0.25) {
+        return true;
+    }
+    bool land = texel >= 0.75;
+    return surface == 1 ? land : !land;
+}
+
+// Maps a texel back to the physical units of the value
+float physical(float texel) {
+    float c = (texel - normalised.x) / (normalised.y - normalised.x);
+    return init_range.x + c * (init_range.y - init_range.x);
+}
+
+bool meets(float value) {
+    if (threshold_condition == 1) {
+        return value > thresholds.x;
+    }
+    if (threshold_condition == 2) {
+        return value < thresholds.x;
+    }
+    if (threshold_condition == 3) {
+        return value >= thresholds.x && value <= thresholds.y;
+    }
+    if (threshold_condition == 4) {
+        return value < thresholds.x || value > thresholds.y;
+    }
+    return false;
+}
+
+// Which steps count as missing. Only a blend between two steps falls back on
+// the other one, a whole step shows its own cells with or without data
+bvec2 shown_missing(bool missing1, bool missing2) {
+    if (interpolation <= 0.0) {
+        return bvec2(missing1, missing1);
+    }
+    if (interpolation >= 1.0) {
+        return bvec2(missing2, missing2);
+    }
+    return bvec2(missing1, missing2);
+}
+
+// Tests the value shown at a cell, blended between the two time steps when both have data
+bool meets_at(ivec2 offset) {
+    ivec2 size1 = textureSize(colour_map1, 0);
+    ivec2 size2 = textureSize(colour_map2, 0);
+    ivec2 coords1 = ivec2(f_tex * size1) + offset;
+    ivec2 coords2 = ivec2(f_tex * size2) + offset;
+    coords1 = ivec2((coords1.x + size1.x) % size1.x, clamp(coords1.y, 0, size1.y - 1));
+    coords2 = ivec2((coords2.x + size2.x) % size2.x, clamp(coords2.y, 0, size2.y - 1));
+
+    if (!keeps(threshold_surface, (vec2(coords1) + 0.5) / vec2(size1))) {
+        return false;
+    }
+
+    vec4 fetched1 = texelFetch(colour_map1, coords1, 0);
+    vec4 fetched2 = texelFetch(colour_map2, coords2, 0);
+    float texel1 = fetched1.x;
+    float texel2 = fetched2.x;
+    // Texels with no alpha have no data, whatever their value
+    bvec2 missing = shown_missing(fetched1.a < 0.5, fetched2.a < 0.5);
+    bool missing1 = missing.x;
+    bool missing2 = missing.y;
+    if (missing1 && missing2) {
+        return false;
+    }
+    float texel = mix(texel1, texel2, interpolation);
+    if (missing1 || missing2) {
+        texel = missing1 ? texel2 : texel1;
+    }
+    return meets(physical(texel));
+}
+
 void main() {
     vec3 light = normalize(vec3(1, 1, 1));
     float brightness = dot(light, normalize(f_normal));
@@ -65,15 +185,58 @@ void main() {
 
     vec4 image_colour1 = texelFetch(colour_map1, coords1, 0);
     vec4 image_colour2 = texelFetch(colour_map2, coords2, 0);
-    vec4 overlay_colour = texelFetch(overlay, overlay_coords, 0);
+    float overlay_colour = mix(1.0, texelFetch(overlay, overlay_coords, 0).x, overlay_strength);
     float difference = normalised.y - normalised.x;
     float c1 = clamp((image_colour1.x - normalised.x) / difference, 0.0, 1.0);
     float c2 = clamp((image_colour2.x - normalised.x) / difference, 0.0, 1.0);
     c1 = new_range(init_range, range, c1);
     c2 = new_range(init_range, range, c2);
 
-    image_colour1 = overlay_colour.x * hsv_to_rgb(1.0 - c1);
-    image_colour2 = overlay_colour.x * hsv_to_rgb(1.0 - c2);
+    // Texels with no alpha have no data, whatever their value
+    bvec2 missing = shown_missing(image_colour1.a < 0.5, image_colour2.a < 0.5);
+    bool missing1 = missing.x;
+    bool missing2 = missing.y;
+    image_colour1 = overlay_colour * value_to_rgb(c1);
+    image_colour2 = overlay_colour * value_to_rgb(c2);
 
+    // While blending, a step without data shows the other step rather than
+    // blending towards nothing
     colour = mix(image_colour1, image_colour2, interpolation);
+    if (missing1 != missing2) {
+        colour = missing1 ? image_colour2 : image_colour1;
+    }
+
+    if (threshold_condition != 0) {
+        bool inside = meets_at(ivec2(0, 0));
+        if (threshold_style == 2) {
+            if (!inside) {
+                float grey = dot(colour.rgb, vec3(0.3, 0.59, 0.11));
+                colour = vec4(vec3(0.1 + grey * 0.3), 1.0);
+            }
+        }
+        else if (inside && threshold_style == 0) {
+            if (mod(gl_FragCoord.x + gl_FragCoord.y, 8.0) < 3.0) {
+                colour = vec4(threshold_colour, 1.0);
+            }
+        }
+        else if (inside) {
+            // Cells on the edge of a matching area are filled in
+            bool edge = !meets_at(ivec2(1, 0)) || !meets_at(ivec2(-1, 0))
+                || !meets_at(ivec2(0, 1)) || !meets_at(ivec2(0, -1));
+            if (edge) {
+                colour = vec4(threshold_colour, 1.0);
+            }
+        }
+    }
+
+    if (missing1 && missing2) {
+        colour = vec4(no_data_colour, 1.0);
+        if (no_data_style == 1 && mod(gl_FragCoord.x - gl_FragCoord.y, 8.0) >= 3.0) {
+            colour = vec4(vec3(0.12 + 0.1 * overlay_colour), 1.0);
+        }
+    }
+
+    if (!keeps(surface_filter, f_tex)) {
+        colour = vec4(vec3(0.12 + 0.1 * overlay_colour), 1.0);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,14 @@
+#version 440
+
+uniform vec4 line_colour;
+uniform float half_width;
+
+in float across;
+
+out vec4 colour;
+
+void main() {
+    // Smooths the edges of the quad the line was widened into
+    float coverage = clamp(half_width + 0.5 - abs(across), 0.0, 1.0);
+    colour = vec4(line_colour.rgb, line_colour.a * coverage);
+}
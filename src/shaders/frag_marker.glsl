@@ -0,0 +1,26 @@
+#version 440
+
+in vec2 f_corner;
+in vec3 f_colour;
+
+out vec4 colour;
+
+void main() {
+    vec2 head = f_corner - vec2(0.0, 0.3);
+    float radius = length(head);
+    bool in_head = radius < 0.65;
+    bool in_tail = f_corner.y < 0.3 && abs(f_corner.x) < 0.5 * (f_corner.y + 1.0);
+    if (!in_head && !in_tail) {
+        discard;
+    }
+
+    if (in_head && radius < 0.22) {
+        colour = vec4(1.0, 1.0, 1.0, 1.0);
+    }
+    else if (in_head && radius > 0.52) {
+        colour = vec4(f_colour * 0.5, 1.0);
+    }
+    else {
+        colour = vec4(f_colour, 1.0);
+    }
+}
@@ -0,0 +1,44 @@
+#version 440
+
+layout(lines) in;
+layout(triangle_strip, max_vertices = 4) out;
+
+// Half the line width and the size of the viewport, both in pixels
+uniform float half_width;
+uniform vec2 viewport_size;
+
+// Distance from the middle of the line in pixels
+out float across;
+
+void main() {
+    vec4 from = gl_in[0].gl_Position;
+    vec4 to = gl_in[1].gl_Position;
+    // Segments reaching behind the camera have nowhere to go on screen
+    if (from.w <= 0.0 || to.w <= 0.0) {
+        return;
+    }
+
+    vec2 half_size = viewport_size * 0.5;
+    vec2 direction = (to.xy / to.w - from.xy / from.w) * half_size;
+    if (length(direction) < 1e-6) {
+        direction = vec2(1.0, 0.0);
+    }
+    vec2 normal = normalize(vec2(-direction.y, direction.x));
+    // A pixel either side is left for the edges to fade out over
+    float extent = half_width + 1.0;
+    vec2 offset = normal * extent / half_size;
+
+    gl_Position = vec4(from.xy + offset * from.w, from.zw);
+    across = extent;
+    EmitVertex();
+    gl_Position = vec4(from.xy - offset * from.w, from.zw);
+    across = -extent;
+    EmitVertex();
+    gl_Position = vec4(to.xy + offset * to.w, to.zw);
+    across = extent;
+    EmitVertex();
+    gl_Position = vec4(to.xy - offset * to.w, to.zw);
+    across = -extent;
+    EmitVertex();
+    EndPrimitive();
+}
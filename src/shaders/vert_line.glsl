@@ -0,0 +1,14 @@
+#version 440
+
+uniform sampler2D height_map;
+uniform mat4 view;
+uniform float height_scale;
+
+in vec3 position;
+in vec2 tex_coord;
+
+void main() {
+    // Lifted the same way as the globe and slightly above it so lines are not buried
+    float height = texture(height_map, tex_coord).x;
+    gl_Position = view * vec4((1.0 + height * height_scale) * position * 1.003, 1.0);
+}
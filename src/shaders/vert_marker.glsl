@@ -0,0 +1,26 @@
+#version 440
+
+uniform sampler2D height_map;
+uniform mat4 view;
+uniform float height_scale;
+uniform vec2 scale;
+
+in vec3 position;
+in vec2 tex_coord;
+in vec2 corner;
+in vec3 colour;
+
+out vec2 f_corner;
+out vec3 f_colour;
+
+void main() {
+    // Lifted the same way as the globe so the pin stays on the surface
+    float height = texture(height_map, tex_coord).x;
+    vec4 anchor = view * vec4((1.0 + height * height_scale) * position * 1.002, 1.0);
+
+    f_corner = corner;
+    f_colour = colour;
+    // The bottom of the quad sits on the anchor, scaled by w to keep a fixed size on screen
+    vec2 offset = (corner + vec2(0.0, 1.0)) * scale * anchor.w;
+    gl_Position = anchor + vec4(offset, 0.0, 0.0);
+}
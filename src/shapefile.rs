@@ -0,0 +1,145 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// Only the outlines of shapes are read, attributes in the .dbf are ignored.
+// Points come back as [lat, lon] to match the rest of the crate.
+const FILE_CODE: i32 = 9994;
+const HEADER_SIZE: usize = 100;
+
+fn read_bytes(bytes: &[u8], at: usize, count: usize) -> Result<&[u8], Box<Error>> {
+    bytes
+        .get(at..at + count)
+        .ok_or_else(|| "Shapefile ends part way through a record".into())
+}
+
+fn big_i32(bytes: &[u8], at: usize) -> Result<i32, Box<Error>> {
+    let b = read_bytes(bytes, at, 4)?;
+    Ok((b[0] as i32) << 24 | (b[1] as i32) << 16 | (b[2] as i32) << 8 | b[3] as i32)
+}
+
+fn little_i32(bytes: &[u8], at: usize) -> Result<i32, Box<Error>> {
+    let b = read_bytes(bytes, at, 4)?;
+    Ok((b[3] as i32) << 24 | (b[2] as i32) << 16 | (b[1] as i32) << 8 | b[0] as i32)
+}
+
+fn little_f64(bytes: &[u8], at: usize) -> Result<f64, Box<Error>> {
+    let b = read_bytes(bytes, at, 8)?;
+    let bits = b.iter().rev().fold(0u64, |bits, byte| bits << 8 | *byte as u64);
+    Ok(f64::from_bits(bits))
+}
+
+// Reads the parts of a polyline or polygon record, content starts at the shape type
+fn parse_parts(bytes: &[u8], content: usize, lines: &mut Vec<Vec<[f32; 2]>>) -> Result<(), Box<Error>> {
+    // Skip the shape type and bounding box
+    let counts = content + 4 + 32;
+    let part_count = little_i32(bytes, counts)?.max(0) as usize;
+    let point_count = little_i32(bytes, counts + 4)?.max(0) as usize;
+    let parts = counts + 8;
+    let points = parts + part_count * 4;
+
+    let mut starts = vec![];
+    for i in 0..part_count {
+        starts.push((little_i32(bytes, parts + i * 4)?.max(0) as usize).min(point_count));
+    }
+    for (i, start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).cloned().unwrap_or(point_count).max(*start);
+        let mut line = Vec::with_capacity(end - start);
+        for point in *start..end {
+            let at = points + point * 16;
+            let lon = little_f64(bytes, at)? as f32;
+            let lat = little_f64(bytes, at + 8)? as f32;
+            line.push([lat, lon]);
+        }
+        if line.len() > 1 {
+            lines.push(line);
+        }
+    }
+    Ok(())
+}
+
+pub fn parse(bytes: &[u8]) -> Result<Vec<Vec<[f32; 2]>>, Box<Error>> {
+    if big_i32(bytes, 0)? != FILE_CODE {
+        return Err("Not a shapefile".into());
+    }
+    let length = (big_i32(bytes, 24)?.max(0) as usize * 2).min(bytes.len());
+
+    let mut lines = vec![];
+    let mut at = HEADER_SIZE;
+    while at + 8 <= length {
+        let content = at + 8;
+        let content_length = big_i32(bytes, at + 4)?.max(0) as usize * 2;
+        match little_i32(bytes, content)? {
+            // PolyLine and Polygon along with their Z and M variants
+            3 | 5 | 13 | 15 | 23 | 25 => parse_parts(bytes, content, &mut lines)?,
+            _ => (),
+        }
+        at = content + content_length;
+    }
+    Ok(lines)
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<Vec<[f32; 2]>>, Box<Error>> {
+    let bytes = fs::read(path)?;
+    parse(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use shapefile::*;
+
+    fn push_big(bytes: &mut Vec<u8>, value: i32) {
+        for shift in [24, 16, 8, 0].iter() {
+            bytes.push((value >> shift) as u8);
+        }
+    }
+
+    fn push_little(bytes: &mut Vec<u8>, value: i32) {
+        for shift in [0, 8, 16, 24].iter() {
+            bytes.push((value >> shift) as u8);
+        }
+    }
+
+    fn push_f64(bytes: &mut Vec<u8>, value: f64) {
+        let bits = value.to_bits();
+        for i in 0..8 {
+            bytes.push((bits >> (i * 8)) as u8);
+        }
+    }
+
+    #[test]
+    fn reads_polyline_parts() {
+        let points = [(10.0, 20.0), (11.0, 21.0), (-170.0, -5.0), (-171.0, -6.0)];
+        let mut record = vec![];
+        push_little(&mut record, 3);
+        for _ in 0..4 {
+            push_f64(&mut record, 0.0);
+        }
+        push_little(&mut record, 2);
+        push_little(&mut record, 4);
+        push_little(&mut record, 0);
+        push_little(&mut record, 2);
+        for (x, y) in points.iter() {
+            push_f64(&mut record, *x);
+            push_f64(&mut record, *y);
+        }
+
+        let mut bytes = vec![];
+        push_big(&mut bytes, FILE_CODE);
+        bytes.resize(24, 0);
+        push_big(&mut bytes, ((HEADER_SIZE + 8 + record.len()) / 2) as i32);
+        push_little(&mut bytes, 1000);
+        push_little(&mut bytes, 3);
+        bytes.resize(HEADER_SIZE, 0);
+        push_big(&mut bytes, 1);
+        push_big(&mut bytes, (record.len() / 2) as i32);
+        bytes.extend(record);
+
+        let lines = parse(&bytes).unwrap();
+        assert_eq!(lines, vec![
+            vec![[20.0, 10.0], [21.0, 11.0]],
+            vec![[-5.0, -170.0], [-6.0, -171.0]],
+        ]);
+        assert!(parse(&bytes[4..]).is_err());
+    }
+}
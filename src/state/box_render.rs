@@ -1,22 +1,52 @@
 use glium::index::{NoIndices, PrimitiveType::TriangleStrip};
-use glium::{backend::glutin::Display, DrawParameters, Program, Surface, VertexBuffer};
+use glium::{backend::Facade, DrawParameters, Program, Surface, VertexBuffer};
 use util::{build_box, BoxVertex};
 
+// Where the range legend sits in a viewport, in clip space
+pub const LEGEND_TRANSLATION: [f32; 2] = [0.78, 0.0];
+pub const LEGEND_SCALE: [f32; 2] = [0.1, 1.8];
+// The no-data swatch sits to the left of the bottom of the legend
+pub const SWATCH_TRANSLATION: [f32; 2] = [0.68, -0.87];
+pub const SWATCH_SCALE: [f32; 2] = [0.05, 0.06];
+
 pub struct BoxRenderer {
     pub program: Program,
     pub buffer: VertexBuffer<BoxVertex>,
 }
 
 impl BoxRenderer {
-    pub fn new(display: &Display, program: Program) -> BoxRenderer {
+    pub fn new<F: Facade>(display: &F, program: Program) -> BoxRenderer {
         let buffer = build_box(display, 1.0, 1.0);
         BoxRenderer { program, buffer }
     }
 
-    pub fn render<T: Surface + ?Sized>(&self, target: &mut T, translation: impl Into<[f32; 2]>, scale: impl Into<[f32;2]>, draw_params: &DrawParameters) {
+    pub fn render<T: Surface + ?Sized>(&self, target: &mut T, translation: impl Into<[f32; 2]>, scale: impl Into<[f32;2]>, diverging: bool, draw_params: &DrawParameters) {
         let uniforms = uniform!{
             translation: translation.into(),
             scale: scale.into(),
+            diverging: diverging,
+            swatch: false,
+            swatch_colour: [0.0f32; 3],
+            swatch_hatched: false,
+        };
+
+        target.draw(
+            &self.buffer,
+            NoIndices(TriangleStrip),
+            &self.program,
+            &uniforms,
+            draw_params,
+        ).unwrap();
+    }
+
+    pub fn render_swatch<T: Surface + ?Sized>(&self, target: &mut T, translation: [f32; 2], scale: [f32; 2], colour: [f32; 3], hatched: bool, draw_params: &DrawParameters) {
+        let uniforms = uniform!{
+            translation: translation,
+            scale: scale,
+            diverging: false,
+            swatch: true,
+            swatch_colour: colour,
+            swatch_hatched: hatched,
         };
 
         target.draw(
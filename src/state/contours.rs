@@ -0,0 +1,212 @@
+use contour::{contour_segments, format_level, interval_levels, parse_levels, Segment};
+use glium::{backend::Facade, Rect, Surface};
+use imgui::{ImString, Ui};
+use state::line_render::{LineSet, LineStyle};
+use state::picking::Projector;
+use state::tool_tips::*;
+use state::value::{Measurement, Value};
+use state::GlobalState;
+use std::collections::BTreeMap;
+use util::editable_string;
+
+// Segments of each level considered when placing its label
+const LABEL_CANDIDATES: usize = 32;
+
+// What a value's contours were last built from, any change rebuilds them
+#[derive(Clone, Debug, PartialEq)]
+struct ContourKey {
+    step: usize,
+    levels: Vec<f32>,
+}
+
+struct ContourCache {
+    key: ContourKey,
+    lines: Option<LineSet>,
+    // Each level with a few points along it where a label could go
+    labels: Vec<(f32, Vec<[f32; 2]>)>,
+}
+
+pub struct ContourSettings {
+    pub interval: f32,
+    pub use_list: bool,
+    // Levels typed by the user, separated by commas
+    pub list: ImString,
+    pub labels: bool,
+    pub colour: [f32; 3],
+    pub width: f32,
+    caches: BTreeMap<usize, ContourCache>,
+}
+
+// The spread of values contours are drawn across, the displayed range for
+// measurements and the data itself for anything else
+fn value_range(value: &Value) -> Option<[f32; 2]> {
+    if let Measurement::Is { range, .. } = value.measurement {
+        return Some(range);
+    }
+    let grid = value.current_grid()?;
+    let mut range = [::std::f32::MAX, ::std::f32::MIN];
+    for (_, _, cell) in grid.cells() {
+        if let Some(cell) = cell {
+            range = [range[0].min(cell), range[1].max(cell)];
+        }
+    }
+    match range[0] <= range[1] {
+        true => Some(range),
+        false => None,
+    }
+}
+
+fn label_candidates(segments: &[Segment]) -> Vec<[f32; 2]> {
+    let step = (segments.len() / LABEL_CANDIDATES).max(1);
+    segments
+        .iter()
+        .step_by(step)
+        .map(|&[from, to]| [(from[0] + to[0]) * 0.5, (from[1] + to[1]) * 0.5])
+        .collect()
+}
+
+impl ContourSettings {
+    pub fn new() -> ContourSettings {
+        ContourSettings {
+            interval: 5.0,
+            use_list: false,
+            list: editable_string("0", 256),
+            labels: true,
+            colour: [0.1, 0.1, 0.1],
+            width: 1.5,
+            caches: BTreeMap::new(),
+        }
+    }
+
+    pub fn levels(&self, value: &Value) -> Vec<f32> {
+        match self.use_list {
+            true => parse_levels(self.list.to_str()),
+            false => value_range(value)
+                .map(|range| interval_levels(range, self.interval))
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn build_contour_ui(&mut self, ui: &Ui, width: f32, hovered: bool) {
+        if !ui.collapsing_header(im_str!("Contours")).build() {
+            return;
+        }
+        ui.checkbox(im_str!("Use Levels"), &mut self.use_list);
+        contour_list_tt(ui, hovered);
+        ui.with_item_width(width, || {
+            match self.use_list {
+                true => {
+                    ui.input_text(im_str!("Levels"), &mut self.list).build();
+                }
+                false => {
+                    ui.input_float(im_str!("Interval"), &mut self.interval)
+                        .build();
+                    contour_interval_tt(ui, hovered);
+                }
+            }
+            ui.color_edit(im_str!("Contour Colour"), &mut self.colour)
+                .build();
+            ui.slider_float(im_str!("Contour Width"), &mut self.width, 1.0, 5.0)
+                .build();
+        });
+        ui.checkbox(im_str!("Contour Labels"), &mut self.labels);
+        ui.text("Turn contours on in each viewport's menu");
+    }
+}
+
+impl GlobalState {
+    // Builds contours for every value shown in a viewport with contours on
+    pub fn update_contours<F: Facade>(&mut self, display: &F) {
+        let shown = self.shown_values(|settings| settings.contours);
+        self.contours.caches.retain(|index, _| shown.contains(index));
+
+        for index in shown {
+            let value = match self.values.get(index) {
+                Some(value) if value.has_data() => value,
+                _ => continue,
+            };
+            let key = ContourKey {
+                step: value.current_step(),
+                levels: self.contours.levels(value),
+            };
+            if self.contours.caches.get(&index).map_or(false, |cache| cache.key == key) {
+                continue;
+            }
+
+            let grid = match value.current_grid() {
+                Some(grid) => grid,
+                None => continue,
+            };
+            let mut lines = vec![];
+            let mut labels = vec![];
+            for level in &key.levels {
+                let segments = contour_segments(grid, *level);
+                labels.push((*level, label_candidates(&segments)));
+                lines.extend(segments.iter().map(|segment| segment.to_vec()));
+            }
+            let cache = ContourCache {
+                key,
+                lines: LineSet::new(display, &lines),
+                labels,
+            };
+            self.contours.caches.insert(index, cache);
+        }
+    }
+
+    pub fn render_contours<T: Surface + ?Sized>(
+        &self,
+        target: &mut T,
+        index: usize,
+        view: [[f32; 4]; 4],
+        rect: Rect,
+    ) {
+        let lines = match self.contours.caches.get(&index) {
+            Some(ContourCache { lines: Some(lines), .. }) => lines,
+            _ => return,
+        };
+        let [r, g, b] = self.contours.colour;
+        self.line_renderer.render(
+            target,
+            lines,
+            view,
+            &self.height_map,
+            self.variables.height,
+            LineStyle::new([r, g, b, 1.0], self.contours.width),
+            rect,
+        );
+    }
+
+    // One label per level, placed on the visible part nearest the middle of the viewport
+    pub fn build_contour_labels(
+        &self,
+        ui: &Ui,
+        viewport_index: usize,
+        index: usize,
+        projector: &Projector,
+        rect: Rect,
+        frame_height: f32,
+    ) {
+        let cache = match self.contours.caches.get(&index) {
+            Some(cache) => cache,
+            None => return,
+        };
+        for (i, (level, candidates)) in cache.labels.iter().enumerate() {
+            let clip = candidates
+                .iter()
+                .filter_map(|location| projector.project(*location))
+                .min_by(|a, b| {
+                    let distance = |clip: &[f32; 2]| clip[0] * clip[0] + clip[1] * clip[1];
+                    distance(a).partial_cmp(&distance(b)).unwrap()
+                });
+            let clip = match clip {
+                Some(clip) => clip,
+                None => continue,
+            };
+            let (x, y) = GlobalState::clip_to_screen(rect, frame_height, clip);
+            let text = format_level(*level);
+            let width = text.chars().count() as f32 * 7.0 + 16.0;
+            let name = ImString::new(format!("##Contour Label {} {}", viewport_index, i));
+            GlobalState::label_window(ui, name.as_ref(), (x - width * 0.5, y - 15.0), width, &text);
+        }
+    }
+}
@@ -0,0 +1,469 @@
+use data_grid::NORMALISED;
+use data_grid::DataGrid;
+use derived::{centred_range, combine, combine_steps, mean_grid, Operation};
+use expr::{evaluate, identifier, parse};
+use glium::backend::Facade;
+use imgui::{ImStr, ImString, Ui};
+use state::time::TimeAxis;
+use state::tool_tips::*;
+use state::value::{Measurement, Value};
+use state::GlobalState;
+use std::error::Error;
+use std::slice;
+use std::fs;
+use std::path::Path;
+use temporal::{
+    correlation_map, parse_index_series, temporal_stat, Against, TemporalStat, STAT_COUNT,
+};
+use util::editable_string;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DeriveKind {
+    Difference,
+    Ratio,
+    Anomaly,
+    Formula,
+    // Statistics of each cell over the first variable's time steps
+    Temporal,
+    // Correlation over time with a second variable or an index
+    Correlation,
+    // One time step minus another, of the same variable or two different ones
+    StepDifference,
+}
+
+impl DeriveKind {
+    pub fn from_index(index: i32) -> DeriveKind {
+        match index {
+            1 => DeriveKind::Ratio,
+            2 => DeriveKind::Anomaly,
+            3 => DeriveKind::Formula,
+            4 => DeriveKind::Temporal,
+            5 => DeriveKind::Correlation,
+            6 => DeriveKind::StepDifference,
+            _ => DeriveKind::Difference,
+        }
+    }
+}
+
+pub struct DeriveSettings {
+    pub kind: i32,
+    pub first: i32,
+    pub second: i32,
+    // The steps compared by a step difference, counted from 1
+    pub first_step: i32,
+    pub second_step: i32,
+    // Anomalies are taken against the first variable's own mean when set
+    pub own_mean: bool,
+    pub formula: ImString,
+    // A TemporalStat index, or STAT_COUNT for all of them
+    pub stat: i32,
+    // Correlations are taken against the index file rather than the second variable
+    pub against_index: bool,
+    pub index_path: ImString,
+    pub significant_only: bool,
+    // Left empty to name the value after its inputs
+    pub name: ImString,
+    requested: bool,
+    pub status: String,
+}
+
+impl DeriveSettings {
+    pub fn new() -> DeriveSettings {
+        DeriveSettings {
+            kind: 0,
+            first: 0,
+            second: 0,
+            first_step: 1,
+            second_step: 1,
+            own_mean: true,
+            formula: editable_string("", 256),
+            stat: 0,
+            against_index: false,
+            index_path: editable_string("index.csv", 256),
+            significant_only: false,
+            name: editable_string("", 64),
+            requested: false,
+            status: String::new(),
+        }
+    }
+
+    pub fn build_derive_ui(&mut self, ui: &Ui, width: f32, names: &[&ImStr], hovered: bool) {
+        if !ui.collapsing_header(im_str!("Derived Values")).build() {
+            return;
+        }
+        let kinds = [
+            im_str!("Difference"),
+            im_str!("Ratio"),
+            im_str!("Anomaly"),
+            im_str!("Formula"),
+            im_str!("Over Time"),
+            im_str!("Correlation"),
+            im_str!("Step Difference"),
+        ];
+        let kind = DeriveKind::from_index(self.kind);
+        ui.with_item_width(width, || {
+            ui.combo(im_str!("Operation"), &mut self.kind, &kinds, 7);
+            if kind == DeriveKind::Formula {
+                ui.input_text(im_str!("Formula"), &mut self.formula).build();
+                ui.input_text(im_str!("Name"), &mut self.name).build();
+                return;
+            }
+            ui.combo(im_str!("First"), &mut self.first, names, 5);
+            if kind == DeriveKind::Temporal {
+                let stats = [
+                    im_str!("Trend"),
+                    im_str!("Mean"),
+                    im_str!("Standard Deviation"),
+                    im_str!("Min"),
+                    im_str!("Max"),
+                    im_str!("Step of Max"),
+                    im_str!("All"),
+                ];
+                ui.combo(im_str!("Statistic"), &mut self.stat, &stats, 7);
+                ui.input_text(im_str!("Name"), &mut self.name).build();
+                return;
+            }
+            if kind == DeriveKind::Correlation {
+                ui.checkbox(im_str!("Against Index"), &mut self.against_index);
+                match self.against_index {
+                    true => ui.input_text(im_str!("Index File"), &mut self.index_path).build(),
+                    false => ui.combo(im_str!("With"), &mut self.second, names, 5),
+                };
+                ui.checkbox(im_str!("Significant Only"), &mut self.significant_only);
+                ui.input_text(im_str!("Name"), &mut self.name).build();
+                return;
+            }
+            if kind == DeriveKind::StepDifference {
+                ui.input_int(im_str!("Step"), &mut self.first_step).build();
+                ui.combo(im_str!("Minus"), &mut self.second, names, 5);
+                ui.input_int(im_str!("Minus Step"), &mut self.second_step).build();
+                ui.input_text(im_str!("Name"), &mut self.name).build();
+                return;
+            }
+            if kind == DeriveKind::Anomaly {
+                ui.checkbox(im_str!("Own Mean"), &mut self.own_mean);
+            }
+            if kind != DeriveKind::Anomaly || !self.own_mean {
+                let label = match kind {
+                    DeriveKind::Difference => im_str!("Minus"),
+                    DeriveKind::Ratio => im_str!("Divided By"),
+                    _ => im_str!("Baseline"),
+                };
+                ui.combo(label, &mut self.second, names, 5);
+            }
+            ui.input_text(im_str!("Name"), &mut self.name).build();
+        });
+        derive_kind_tt(ui, hovered);
+        match kind {
+            DeriveKind::Temporal => temporal_stat_tt(ui, hovered),
+            DeriveKind::Correlation => correlation_tt(ui, hovered),
+            DeriveKind::StepDifference => step_difference_tt(ui, hovered),
+            _ => (),
+        }
+        if kind == DeriveKind::Formula {
+            formula_tt(ui, hovered);
+            // Parsed as it is typed so mistakes show before anything is computed
+            let formula = self.formula.to_str();
+            if !formula.trim().is_empty() {
+                if let Err(error) = parse(formula) {
+                    let text = ImString::new(error.to_string());
+                    ui.text_colored([1.0, 0.3, 0.3, 1.0], &text);
+                }
+            }
+        }
+        if ui.button(im_str!("Create"), (100.0, 30.0)) {
+            self.requested = true;
+        }
+        if !self.status.is_empty() {
+            ui.text(&self.status);
+        }
+    }
+}
+
+impl GlobalState {
+    pub fn handle_derived<F: Facade>(&mut self, display: &F) {
+        if !self.derive.requested {
+            return;
+        }
+        self.derive.requested = false;
+        self.derive.status = match self.derive_value(display) {
+            Ok(name) => format!("Added {}", name),
+            Err(error) => format!("Failed: {}", error),
+        };
+    }
+
+    // Builds the chosen derived value from the CPU grids and adds it, returning its name
+    pub fn derive_value<F: Facade>(&mut self, display: &F) -> Result<String, Box<Error>> {
+        let kind = DeriveKind::from_index(self.derive.kind);
+        if kind == DeriveKind::Formula {
+            return self.formula_value(display);
+        }
+        if kind == DeriveKind::Temporal {
+            return self.temporal_values(display);
+        }
+        if kind == DeriveKind::Correlation {
+            return self.correlation_value(display);
+        }
+        if kind == DeriveKind::StepDifference {
+            return self.step_difference_value(display);
+        }
+        let first = self.get_selected(self.derive.first as usize).ok_or("No such variable")?;
+        let needs_second = kind != DeriveKind::Anomaly || !self.derive.own_mean;
+        let second = match needs_second {
+            true => Some(self.get_selected(self.derive.second as usize).ok_or("No such variable")?),
+            false => None,
+        };
+        for value in Some(first).into_iter().chain(second) {
+            if !value.has_data() {
+                return Err(format!("{} has no data grid", value.name.to_str()).into());
+            }
+        }
+
+        let (first_name, second_name) = (
+            first.name.to_str(),
+            second.map_or("", |value| value.name.to_str()),
+        );
+        let (grids, centre, default_name) = match (kind, second) {
+            (DeriveKind::Difference, Some(second)) => (
+                combine_steps(&first.grids, &second.grids, Operation::Difference)?,
+                Operation::Difference.centre(),
+                format!("{} - {}", first_name, second_name),
+            ),
+            (DeriveKind::Ratio, Some(second)) => (
+                combine_steps(&first.grids, &second.grids, Operation::Ratio)?,
+                Operation::Ratio.centre(),
+                format!("{} / {}", first_name, second_name),
+            ),
+            (_, baseline) => {
+                let baseline = baseline.unwrap_or(first);
+                let mean = mean_grid(&baseline.grids).ok_or("The baseline has no data")?;
+                (
+                    combine_steps(&first.grids, &[mean], Operation::Difference)?,
+                    Operation::Difference.centre(),
+                    format!("{} Anomaly", first_name),
+                )
+            }
+        };
+        let axis = first.axis.clone();
+
+        let name = match self.derive.name.to_str().trim() {
+            "" => default_name,
+            name => name.to_string(),
+        };
+        let range = centred_range(&grids, centre);
+        self.add_derived_value(display, grids, name.clone(), range, axis);
+        Ok(name)
+    }
+
+    fn formula_value<F: Facade>(&mut self, display: &F) -> Result<String, Box<Error>> {
+        let formula = self.derive.formula.to_str().trim().to_string();
+        if formula.is_empty() {
+            return Err("Enter a formula first".into());
+        }
+        let expr = parse(&formula)?;
+        let names: Vec<String> = self
+            .values
+            .iter()
+            .map(|value| identifier(value.name.to_str()))
+            .collect();
+        let variables: Vec<(String, &[DataGrid])> = names
+            .iter()
+            .cloned()
+            .zip(self.values.iter().map(|value| &value.grids[..]))
+            .collect();
+        let grids = evaluate(&expr, &variables)?;
+
+        // The time axis comes from the first value used that has as many steps
+        let used = expr.variables();
+        let axis = names
+            .iter()
+            .zip(&self.values)
+            .filter(|(name, value)| used.contains(name) && value.grids.len() == grids.len())
+            .map(|(_, value)| value.axis.clone())
+            .next()
+            .unwrap_or(TimeAxis::Steps);
+
+        let name = match self.derive.name.to_str().trim() {
+            "" => formula,
+            name => name.to_string(),
+        };
+        let range = data_range(&grids);
+        self.add_derived_value(display, grids, name.clone(), range, axis);
+        Ok(name)
+    }
+
+    // Adds one value per chosen statistic, each a single map over all time steps
+    fn temporal_values<F: Facade>(&mut self, display: &F) -> Result<String, Box<Error>> {
+        let stats = match self.derive.stat as usize {
+            STAT_COUNT => TemporalStat::all().to_vec(),
+            _ => vec![TemporalStat::from_index(self.derive.stat)],
+        };
+        let mut added = vec![];
+        for (grid, name, range) in self.temporal_grids(&stats)? {
+            self.add_derived_value(display, vec![grid], name.clone(), range, TimeAxis::Steps);
+            added.push(name);
+        }
+        Ok(added.join(", "))
+    }
+
+    fn temporal_grids(
+        &self,
+        stats: &[TemporalStat],
+    ) -> Result<Vec<(DataGrid, String, [f32; 2])>, Box<Error>> {
+        let value = self.get_selected(self.derive.first as usize).ok_or("No such variable")?;
+        if !value.has_data() {
+            return Err(format!("{} has no data grid", value.name.to_str()).into());
+        }
+        // Trends are per decade on a calendar and per step otherwise, while the
+        // months of a climatology are not a sequence to take one over
+        let (step_length, per, has_trend) = match value.axis {
+            TimeAxis::Monthly { .. } => (1.0 / 120.0, "per Decade", true),
+            TimeAxis::Yearly { .. } => (0.1, "per Decade", true),
+            TimeAxis::Climatology => (1.0, "", false),
+            _ => (1.0, "per Step", true),
+        };
+        if !has_trend && stats == [TemporalStat::Trend] {
+            return Err("A climatology has no trend".into());
+        }
+        let base_name = match self.derive.name.to_str().trim() {
+            "" => value.name.to_str().to_string(),
+            name => name.to_string(),
+        };
+
+        let mut results = vec![];
+        for &stat in stats {
+            if stat == TemporalStat::Trend && !has_trend {
+                continue;
+            }
+            let grid = temporal_stat(&value.grids, stat, step_length)?;
+            let own_range = data_range(slice::from_ref(&grid));
+            let range = match (stat, &value.measurement) {
+                (TemporalStat::Trend, _) => centred_range(slice::from_ref(&grid), 0.0),
+                (TemporalStat::StdDev, _) => [0.0, own_range[1].max(0.0)],
+                (TemporalStat::StepOfMax, _) => [1.0, value.grids.len() as f32],
+                (_, Measurement::Is { init_range, .. }) => *init_range,
+                (_, Measurement::IsNot) => own_range,
+            };
+            let name = match stat {
+                TemporalStat::Trend => format!("{} Trend {}", base_name, per),
+                _ => format!("{} {}", base_name, stat.name()),
+            };
+            results.push((grid, name, range));
+        }
+        Ok(results)
+    }
+
+    fn correlation_value<F: Facade>(&mut self, display: &F) -> Result<String, Box<Error>> {
+        let first = self.get_selected(self.derive.first as usize).ok_or("No such variable")?;
+        if !first.has_data() {
+            return Err(format!("{} has no data grid", first.name.to_str()).into());
+        }
+        let significant_only = self.derive.significant_only;
+        let (grid, against_name) = match self.derive.against_index {
+            true => {
+                let path = self.derive.index_path.to_str().trim();
+                let series = parse_index_series(&fs::read_to_string(path)?)?;
+                let against = Against::Series(&series);
+                let grid = correlation_map(&first.grids, against, significant_only)?;
+                let stem = Path::new(path).file_stem().and_then(|stem| stem.to_str());
+                (grid, stem.unwrap_or(path).to_string())
+            }
+            false => {
+                let second =
+                    self.get_selected(self.derive.second as usize).ok_or("No such variable")?;
+                if !second.has_data() {
+                    return Err(format!("{} has no data grid", second.name.to_str()).into());
+                }
+                let against = Against::Grids(&second.grids);
+                let grid = correlation_map(&first.grids, against, significant_only)?;
+                (grid, second.name.to_str().to_string())
+            }
+        };
+        let name = match self.derive.name.to_str().trim() {
+            "" => format!("{} Correlation with {}", first.name.to_str(), against_name),
+            name => name.to_string(),
+        };
+        self.add_derived_value(display, vec![grid], name.clone(), [-1.0, 1.0], TimeAxis::Steps);
+        if let Some(value) = self.values.last_mut() {
+            value.diverging = true;
+        }
+        Ok(name)
+    }
+
+    fn step_difference_value<F: Facade>(&mut self, display: &F) -> Result<String, Box<Error>> {
+        let first = self.get_selected(self.derive.first as usize).ok_or("No such variable")?;
+        let second = self.get_selected(self.derive.second as usize).ok_or("No such variable")?;
+        let (first_grid, first_label) = chosen_step(first, self.derive.first_step)?;
+        let (second_grid, second_label) = chosen_step(second, self.derive.second_step)?;
+        let grid = combine(first_grid, second_grid, Operation::Difference);
+
+        let name = match self.derive.name.to_str().trim() {
+            "" => format!(
+                "{} {} - {} {}",
+                first.name.to_str(),
+                first_label,
+                second.name.to_str(),
+                second_label
+            ),
+            name => name.to_string(),
+        };
+        let range = centred_range(slice::from_ref(&grid), Operation::Difference.centre());
+        self.add_derived_value(display, vec![grid], name.clone(), range, TimeAxis::Steps);
+        Ok(name)
+    }
+
+    fn add_derived_value<F: Facade>(
+        &mut self,
+        display: &F,
+        grids: Vec<DataGrid>,
+        name: String,
+        range: [f32; 2],
+        axis: TimeAxis,
+    ) {
+        let textures = grids.iter().map(|grid| grid.to_texture(display, range)).collect();
+        self.add_new_value(
+            textures,
+            grids,
+            ImString::new(name),
+            Measurement::Is {
+                normalised: NORMALISED,
+                init_range: range,
+                range,
+            },
+            axis,
+        );
+    }
+}
+
+// The grid of a step counted from 1 along with the step's label
+fn chosen_step(value: &Value, step: i32) -> Result<(&DataGrid, String), Box<Error>> {
+    if !value.has_data() {
+        return Err(format!("{} has no data grid", value.name.to_str()).into());
+    }
+    let steps = value.grids.len();
+    if step < 1 || step as usize > steps {
+        return Err(format!("{} has steps 1 to {}", value.name.to_str(), steps).into());
+    }
+    let index = step as usize - 1;
+    Ok((&value.grids[index], value.axis.label(index)))
+}
+
+// The data's own range, centred on zero when it has values either side of it
+fn data_range(grids: &[DataGrid]) -> [f32; 2] {
+    let (min, max) = grids
+        .iter()
+        .flat_map(|grid| grid.cells())
+        .filter_map(|(_, _, cell)| cell)
+        .fold((::std::f32::MAX, ::std::f32::MIN), |(min, max), cell| {
+            (min.min(cell), max.max(cell))
+        });
+    if min > max {
+        [0.0, 1.0]
+    } else if min < 0.0 && max > 0.0 {
+        centred_range(grids, 0.0)
+    } else if min == max {
+        [min - 0.5, max + 0.5]
+    } else {
+        [min, max]
+    }
+}
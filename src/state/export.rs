@@ -0,0 +1,434 @@
+use gif::{self, SetParameter};
+use glium::backend::Facade;
+use glium::framebuffer::{DepthRenderBuffer, SimpleFrameBuffer};
+use glium::texture::{DepthFormat, MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat};
+use glium::Surface;
+use image::{imageops, RgbaImage};
+use imgui::{ImStr, ImString, Ui};
+use renderer::Mat4;
+use state::box_render::{LEGEND_SCALE, LEGEND_TRANSLATION, SWATCH_SCALE, SWATCH_TRANSLATION};
+use state::tool_tips::*;
+use state::value::Measurement;
+use state::viewports::ViewPort;
+use state::GlobalState;
+use std::error::Error;
+use std::f32::consts::PI;
+use std::fs::{self, File};
+use std::path::Path;
+use text::{stamp_label, text_height, text_width};
+use util::{editable_string, wrapf32};
+
+const MAX_EXPORT_SIZE: i32 = 8192;
+
+pub struct ExportSettings {
+    pub path: ImString,
+    pub size: [i32; 2],
+    // 0 captures every viewport, otherwise the viewport at target - 1
+    pub target: i32,
+    pub legend: bool,
+    pub title: bool,
+    pub requested: bool,
+    pub status: String,
+    pub animation: AnimationSettings,
+}
+
+pub struct AnimationSettings {
+    // Frames are saved as prefix_0000.png and the GIF as prefix.gif
+    pub prefix: ImString,
+    pub frames: i32,
+    pub steps_per_frame: f32,
+    // Degrees the camera turns around the globe each frame
+    pub rotation: f32,
+    pub png: bool,
+    pub gif: bool,
+    // Hundredths of a second each GIF frame is shown
+    pub delay: i32,
+    pub requested: bool,
+    pub status: String,
+}
+
+impl AnimationSettings {
+    pub fn new() -> AnimationSettings {
+        AnimationSettings {
+            prefix: editable_string("animation/frame", 256),
+            frames: 12,
+            steps_per_frame: 1.0,
+            rotation: 0.0,
+            png: true,
+            gif: true,
+            delay: 50,
+            requested: false,
+            status: String::new(),
+        }
+    }
+
+    pub fn build_animation_ui(&mut self, ui: &Ui, width: f32, hovered: bool) {
+        if !ui.collapsing_header(im_str!("Export Animation")).build() {
+            return;
+        }
+
+        ui.with_item_width(width, || {
+            ui.input_text(im_str!("Prefix"), &mut self.prefix).build();
+            animation_prefix_tt(ui, hovered);
+
+            ui.input_int(im_str!("Frames"), &mut self.frames).build();
+            ui.input_float(im_str!("Steps/Frame"), &mut self.steps_per_frame)
+                .build();
+            steps_per_frame_tt(ui, hovered);
+
+            ui.slider_float(im_str!("Rotation"), &mut self.rotation, -30.0, 30.0)
+                .build();
+            animation_rotation_tt(ui, hovered);
+
+            ui.input_int(im_str!("GIF Delay"), &mut self.delay).build();
+            gif_delay_tt(ui, hovered);
+        });
+        if self.frames < 1 {
+            self.frames = 1;
+        }
+        if self.delay < 1 {
+            self.delay = 1;
+        }
+
+        ui.checkbox(im_str!("PNG Frames"), &mut self.png);
+        ui.same_line(0.0);
+        ui.checkbox(im_str!("GIF"), &mut self.gif);
+
+        if ui.button(im_str!("Save Animation"), (120.0, 30.0)) {
+            self.requested = true;
+        }
+        if !self.status.is_empty() {
+            ui.text(&self.status);
+        }
+    }
+}
+
+impl ExportSettings {
+    pub fn new() -> ExportSettings {
+        ExportSettings {
+            path: editable_string("screenshot.png", 256),
+            size: [1920, 1080],
+            target: 0,
+            legend: true,
+            title: true,
+            requested: false,
+            status: String::new(),
+            animation: AnimationSettings::new(),
+        }
+    }
+
+    pub fn resolution(&self) -> (u32, u32) {
+        let clamp = |value: i32| {
+            if value < 16 {
+                16
+            } else if value > MAX_EXPORT_SIZE {
+                MAX_EXPORT_SIZE as u32
+            } else {
+                value as u32
+            }
+        };
+        (clamp(self.size[0]), clamp(self.size[1]))
+    }
+
+    pub fn build_export_ui(&mut self, ui: &Ui, width: f32, viewports: usize, hovered: bool) {
+        if !ui.collapsing_header(im_str!("Export Image")).build() {
+            return;
+        }
+
+        let mut names = vec![ImString::new("All Viewports")];
+        for i in 0..viewports {
+            names.push(ImString::new(format!("Viewport {}", i)));
+        }
+        let items: Vec<&ImStr> = names.iter().map(|name| name.as_ref()).collect();
+
+        ui.with_item_width(width, || {
+            ui.input_text(im_str!("File"), &mut self.path).build();
+            export_path_tt(ui, hovered);
+
+            ui.input_int2(im_str!("Size"), &mut self.size).build();
+
+            ui.combo(im_str!("Capture"), &mut self.target, &items, 5);
+            export_target_tt(ui, hovered);
+        });
+
+        ui.checkbox(im_str!("Legend"), &mut self.legend);
+        export_overlay_tt(ui, hovered);
+        ui.same_line(0.0);
+        ui.checkbox(im_str!("Title"), &mut self.title);
+        export_overlay_tt(ui, hovered);
+
+        if ui.button(im_str!("Save PNG"), (100.0, 30.0)) {
+            self.requested = true;
+        }
+        if !self.status.is_empty() {
+            ui.text(&self.status);
+        }
+
+        self.animation.build_animation_ui(ui, width, hovered);
+    }
+}
+
+impl GlobalState {
+    pub fn handle_exports<F: Facade>(&mut self, display: &F) {
+        if !self.export.requested {
+            return;
+        }
+        self.export.requested = false;
+
+        let path = self.export.path.to_str().to_string();
+        let size = self.export.resolution();
+        let viewports = self.export_viewports(self.export.target);
+        let (legend, title) = (self.export.legend, self.export.title);
+        let result = self
+            .render_image(display, size, &viewports, legend, title)
+            .and_then(|image| image.save(&path).map_err(|error| error.into()));
+
+        self.export.status = match result {
+            Ok(()) => format!("Saved {}", path),
+            Err(error) => format!("Export failed: {}", error),
+        };
+    }
+
+    pub fn handle_animation_exports<F: Facade>(&mut self, display: &F) {
+        if !self.export.animation.requested {
+            return;
+        }
+        self.export.animation.requested = false;
+
+        // Animating moves the time selections and cameras, and can turn bouncing
+        // values around or stop ones played once, so put them back after
+        let playback: Vec<(f32, f32, bool)> = self
+            .values
+            .iter()
+            .map(|value| (value.selection, value.direction, value.time))
+            .collect();
+        let clock = self.timeline.clock;
+        let camera = self.camera;
+        let vp_settings = self.vp_settings.clone();
+
+        let result = self.export_animation(display);
+
+        for (value, (selection, direction, time)) in self.values.iter_mut().zip(playback) {
+            value.selection = selection;
+            value.direction = direction;
+            value.time = time;
+        }
+        self.timeline.clock = clock;
+        self.camera = camera;
+        self.vp_settings = vp_settings;
+
+        self.export.animation.status = match result {
+            Ok(frames) => format!("Saved {} frames", frames),
+            Err(error) => format!("Export failed: {}", error),
+        };
+    }
+
+    fn export_animation<F: Facade>(&mut self, display: &F) -> Result<usize, Box<Error>> {
+        let prefix = self.export.animation.prefix.to_str().to_string();
+        let frames = self.export.animation.frames as usize;
+        let steps = self.export.animation.steps_per_frame;
+        let rotation = self.export.animation.rotation * PI / 180.0;
+        let (png, delay) = (self.export.animation.png, self.export.animation.delay as u16);
+        let (width, height) = self.export.resolution();
+        let (legend, title) = (self.export.legend, self.export.title);
+        let viewports = self.export_viewports(self.export.target);
+        let shown: Vec<usize> = viewports
+            .iter()
+            .filter_map(|viewport| viewport.get_div_selection(&self.divisions.values))
+            .map(|index| index as usize)
+            .collect();
+
+        if let Some(parent) = Path::new(&prefix).parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let mut encoder = match self.export.animation.gif {
+            true => {
+                let file = File::create(format!("{}.gif", prefix))?;
+                let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &[])?;
+                encoder.set(gif::Repeat::Infinite)?;
+                Some(encoder)
+            }
+            false => None,
+        };
+
+        for frame in 0..frames {
+            let image = self.render_image(display, (width, height), &viewports, legend, title)?;
+            if png {
+                image.save(format!("{}_{:04}.png", prefix, frame))?;
+            }
+            if let Some(ref mut encoder) = encoder {
+                let mut pixels = image.into_raw();
+                let mut gif_frame = gif::Frame::from_rgba(width as u16, height as u16, &mut pixels);
+                gif_frame.delay = delay;
+                encoder.write_frame(&gif_frame)?;
+            }
+
+            self.advance_shown_values(&shown, steps);
+            self.rotate_cameras(rotation);
+        }
+        Ok(frames)
+    }
+
+    // Steps the values on screen by a fixed amount, ignoring their play speed
+    fn advance_shown_values(&mut self, shown: &[usize], steps: f32) {
+        if self.timeline.synced {
+            let max_months = self.timeline_months();
+            self.timeline.clock += steps;
+            if max_months > 0.0 {
+                self.timeline.clock = wrapf32(self.timeline.clock, max_months);
+            }
+        }
+        let (synced, clock) = (self.timeline.synced, self.timeline.clock);
+        let origin = self.timeline_origin();
+
+        for (i, value) in self.values.iter_mut().enumerate() {
+            if !value.is_time_stepped() {
+                continue;
+            }
+            if synced && value.linked {
+                value.set_from_clock(clock, origin);
+            } else if shown.contains(&i) {
+                value.advance(steps);
+            }
+        }
+    }
+
+    fn rotate_cameras(&mut self, angle: f32) {
+        if angle == 0.0 {
+            return;
+        }
+        self.camera.rotate_around_look_horizontal(angle);
+        for settings in self.vp_settings.values_mut() {
+            if let Some(ref mut camera) = settings.cam {
+                camera.rotate_around_look_horizontal(angle);
+            }
+        }
+    }
+
+    // The viewports to capture with their rects stretched to cover a whole image
+    pub fn export_viewports(&self, target: i32) -> Vec<ViewPort> {
+        let selected = target as usize;
+        let outer = match self.viewports.get(selected.wrapping_sub(1)) {
+            Some(viewport) if target > 0 => viewport.rect,
+            _ => self.main_viewport,
+        };
+
+        self.viewports
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| target <= 0 || i + 1 == selected)
+            .map(|(_, viewport)| ViewPort {
+                div_id: viewport.div_id,
+                rect: viewport.rect.relative_to(&outer),
+            }).collect()
+    }
+
+    pub fn render_image<F: Facade>(
+        &self,
+        display: &F,
+        size: (u32, u32),
+        viewports: &[ViewPort],
+        legend: bool,
+        title: bool,
+    ) -> Result<RgbaImage, Box<Error>> {
+        let (width, height) = size;
+        let texture = Texture2d::empty_with_format(
+            display,
+            UncompressedFloatFormat::U8U8U8U8,
+            MipmapsOption::NoMipmap,
+            width,
+            height,
+        )?;
+        let depth = DepthRenderBuffer::new(display, DepthFormat::I24, width, height)?;
+        {
+            let mut framebuffer = SimpleFrameBuffer::with_depth_buffer(display, &texture, &depth)?;
+            framebuffer.clear_color_and_depth((1.0, 1.0, 1.0, 1.0), 1.0);
+            let identity: Mat4 = Mat4::identity();
+            for viewport in viewports {
+                self.render_viewport(&mut framebuffer, viewport, *identity.as_ref(), legend);
+            }
+        }
+
+        let raw: RawImage2d<u8> = texture.read();
+        let image = RgbaImage::from_raw(width, height, raw.data.into_owned())
+            .ok_or("Rendered image has the wrong size")?;
+        // OpenGL rows start at the bottom of the image
+        let mut image = imageops::flip_vertical(&image);
+        // The globe shader darkens alpha along with colour, exports are always opaque
+        for pixel in image.pixels_mut() {
+            pixel.data[3] = 255;
+        }
+
+        for viewport in viewports {
+            self.stamp_viewport_text(&mut image, viewport, legend, title);
+        }
+        Ok(image)
+    }
+
+    fn stamp_viewport_text(
+        &self,
+        image: &mut RgbaImage,
+        viewport: &ViewPort,
+        legend: bool,
+        title: bool,
+    ) {
+        let index = match viewport.get_div_selection(&self.divisions.values) {
+            Some(index) => index as usize,
+            None => return,
+        };
+        let value = match self.get_selected(index) {
+            Some(value) => value,
+            None => return,
+        };
+
+        let dimensions = image.dimensions();
+        let rect = viewport.glium_viewport(dimensions);
+        let left = rect.left as i32;
+        let top = dimensions.1 as i32 - (rect.bottom + rect.height) as i32;
+        let scale = if rect.height >= 600 { rect.height / 300 } else { 2 };
+
+        if title {
+            let text = match value.is_time_stepped() {
+                true => format!("{} - {}", value.name.to_str(), value.current_label()),
+                false => value.name.to_str().to_string(),
+            };
+            let margin = 4 * scale as i32;
+            stamp_label(image, &text, (left + margin, top + margin), scale);
+        }
+
+        if !legend {
+            return;
+        }
+        if let Measurement::Is { range, .. } = value.measurement {
+            // Clip space to pixels, matching where render_viewport draws the box
+            let box_left = LEGEND_TRANSLATION[0] - LEGEND_SCALE[0] * 0.5;
+            let box_top = LEGEND_TRANSLATION[1] + LEGEND_SCALE[1] * 0.5;
+            let box_bottom = LEGEND_TRANSLATION[1] - LEGEND_SCALE[1] * 0.5;
+            let x = left + ((box_left + 1.0) * 0.5 * rect.width as f32) as i32;
+            let y_top = top + ((1.0 - box_top) * 0.5 * rect.height as f32) as i32;
+            let y_bottom = top + ((1.0 - box_bottom) * 0.5 * rect.height as f32) as i32;
+            let label_height = (text_height(scale) + 4 * scale) as i32;
+
+            stamp_label(
+                image,
+                &format!("{:.1}", range[1]),
+                (x, y_top - label_height - 2),
+                scale,
+            );
+            stamp_label(image, &format!("{:.1}", range[0]), (x, y_bottom + 2), scale);
+
+            if value.has_data() {
+                let swatch_left = SWATCH_TRANSLATION[0] - SWATCH_SCALE[0] * 0.5;
+                let swatch_middle = SWATCH_TRANSLATION[1];
+                let swatch_x = left + ((swatch_left + 1.0) * 0.5 * rect.width as f32) as i32;
+                let swatch_y = top + ((1.0 - swatch_middle) * 0.5 * rect.height as f32) as i32;
+                let label_x = swatch_x - text_width("No data", scale) as i32 - 6 * scale as i32;
+                let label_y = swatch_y - label_height / 2;
+                stamp_label(image, "No data", (label_x, label_y), scale);
+            }
+        }
+    }
+}
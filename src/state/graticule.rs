@@ -0,0 +1,255 @@
+use glium::{backend::Facade, Rect, Surface};
+use imgui::{ImString, Ui};
+use state::line_render::{LineSet, LineStyle};
+use state::picking::Projector;
+use state::tool_tips::*;
+use state::GlobalState;
+
+pub const TROPIC: f32 = 23.4368;
+pub const POLAR_CIRCLE: f32 = 66.5632;
+const HIGHLIGHT_COLOUR: [f32; 3] = [1.0, 0.8, 0.2];
+// Degrees between the samples used to find where labels go
+const LABEL_STEP: f32 = 2.0;
+
+#[derive(Copy, Clone, Debug)]
+pub struct GraticuleStyle {
+    pub show: bool,
+    pub colour: [f32; 3],
+    pub opacity: f32,
+}
+
+impl GraticuleStyle {
+    pub fn new() -> GraticuleStyle {
+        GraticuleStyle {
+            show: false,
+            colour: [1.0, 1.0, 1.0],
+            opacity: 0.4,
+        }
+    }
+
+    pub fn build_style_ui(&mut self, ui: &Ui, width: f32, hovered: bool) {
+        ui.checkbox(im_str!("Graticule"), &mut self.show);
+        graticule_tt(ui, hovered);
+        if !self.show {
+            return;
+        }
+        ui.with_item_width(width, || {
+            ui.color_edit(im_str!("Grid Colour"), &mut self.colour)
+                .build();
+            ui.slider_float(im_str!("Grid Opacity"), &mut self.opacity, 0.0, 1.0)
+                .build();
+        });
+    }
+}
+
+pub struct Graticule {
+    // Degrees between neighbouring lines
+    pub spacing: i32,
+    // Draws the equator, tropics and polar circles in their own colour
+    pub highlight: bool,
+    pub labels: bool,
+    pub dirty: bool,
+    pub grid: Option<LineSet>,
+    pub special: Option<LineSet>,
+}
+
+// Every latitude the grid draws, the poles are left out
+pub fn parallels(spacing: f32) -> Vec<f32> {
+    let count = (90.0 / spacing).ceil() as i32;
+    (-count..count + 1)
+        .map(|i| i as f32 * spacing)
+        .filter(|lat| lat.abs() < 90.0)
+        .collect()
+}
+
+pub fn meridians(spacing: f32) -> Vec<f32> {
+    let count = (360.0 / spacing).ceil() as i32;
+    (0..count)
+        .map(|i| -180.0 + i as f32 * spacing)
+        .filter(|lon| *lon < 180.0)
+        .collect()
+}
+
+fn parallel_line(lat: f32) -> Vec<[f32; 2]> {
+    (0..73).map(|i| [lat, -180.0 + i as f32 * 5.0]).collect()
+}
+
+fn meridian_line(lon: f32) -> Vec<[f32; 2]> {
+    vec![[-90.0, lon], [90.0, lon]]
+}
+
+pub fn grid_lines(spacing: f32) -> Vec<Vec<[f32; 2]>> {
+    let mut lines: Vec<Vec<[f32; 2]>> = parallels(spacing).into_iter().map(parallel_line).collect();
+    lines.extend(meridians(spacing).into_iter().map(meridian_line));
+    lines
+}
+
+pub fn special_lines() -> Vec<Vec<[f32; 2]>> {
+    [0.0, TROPIC, -TROPIC, POLAR_CIRCLE, -POLAR_CIRCLE]
+        .iter()
+        .map(|lat| parallel_line(*lat))
+        .collect()
+}
+
+pub fn format_latitude(lat: f32) -> String {
+    match lat {
+        lat if lat > 0.0 => format!("{}°N", lat),
+        lat if lat < 0.0 => format!("{}°S", -lat),
+        _ => "0°".to_string(),
+    }
+}
+
+pub fn format_longitude(lon: f32) -> String {
+    match lon {
+        lon if lon > 0.0 => format!("{}°E", lon),
+        lon if lon < 0.0 => format!("{}°W", -lon),
+        _ => "0°".to_string(),
+    }
+}
+
+// Of the visible points along a line, the one that scores lowest
+fn edge_point(
+    projector: &Projector,
+    points: impl Iterator<Item = [f32; 2]>,
+    score: impl Fn([f32; 2]) -> f32,
+) -> Option<[f32; 2]> {
+    points
+        .filter_map(|location| projector.project(location))
+        .fold(None, |best: Option<[f32; 2]>, clip| match best {
+            Some(best) if score(best) <= score(clip) => Some(best),
+            _ => Some(clip),
+        })
+}
+
+impl Graticule {
+    pub fn new() -> Graticule {
+        Graticule {
+            spacing: 15,
+            highlight: true,
+            labels: false,
+            dirty: true,
+            grid: None,
+            special: None,
+        }
+    }
+
+    pub fn build_graticule_ui(&mut self, ui: &Ui, width: f32, hovered: bool) {
+        if !ui.collapsing_header(im_str!("Graticule")).build() {
+            return;
+        }
+        ui.with_item_width(width, || {
+            if ui.slider_int(im_str!("Spacing"), &mut self.spacing, 1, 90)
+                .build()
+            {
+                self.dirty = true;
+            }
+        });
+        graticule_spacing_tt(ui, hovered);
+        ui.checkbox(im_str!("Highlight"), &mut self.highlight);
+        highlight_lines_tt(ui, hovered);
+        ui.same_line(0.0);
+        ui.checkbox(im_str!("Labels"), &mut self.labels);
+        ui.text("Turn the grid on in each viewport's menu");
+    }
+}
+
+impl GlobalState {
+    pub fn update_graticule<F: Facade>(&mut self, display: &F) {
+        if !self.graticule.dirty {
+            return;
+        }
+        let spacing = self.graticule.spacing.max(1) as f32;
+        self.graticule.grid = LineSet::new(display, &grid_lines(spacing));
+        self.graticule.special = LineSet::new(display, &special_lines());
+        self.graticule.dirty = false;
+    }
+
+    pub fn render_graticule<T: Surface + ?Sized>(
+        &self,
+        target: &mut T,
+        style: &GraticuleStyle,
+        view: [[f32; 4]; 4],
+        rect: Rect,
+    ) {
+        let [r, g, b] = style.colour;
+        let mut lines = vec![(&self.graticule.grid, [r, g, b, style.opacity])];
+        if self.graticule.highlight {
+            let [r, g, b] = HIGHLIGHT_COLOUR;
+            lines.push((&self.graticule.special, [r, g, b, style.opacity.max(0.8)]));
+        }
+        for (set, colour) in lines {
+            if let Some(set) = set {
+                self.line_renderer.render(
+                    target,
+                    set,
+                    view,
+                    &self.height_map,
+                    self.variables.height,
+                    LineStyle::new(colour, 1.0),
+                    rect,
+                );
+            }
+        }
+    }
+
+    // Labels each line where it comes closest to the left or bottom of the viewport
+    pub fn build_graticule_labels(
+        &self,
+        ui: &Ui,
+        index: usize,
+        projector: &Projector,
+        rect: Rect,
+        frame_height: f32,
+    ) {
+        let spacing = self.graticule.spacing.max(1) as f32;
+        let samples = |from: f32, to: f32| {
+            let count = ((to - from) / LABEL_STEP) as usize;
+            (0..count + 1).map(move |i| from + i as f32 * LABEL_STEP)
+        };
+
+        let mut labels = vec![];
+        for lat in parallels(spacing) {
+            let points = samples(-180.0, 180.0).map(|lon| [lat, lon]);
+            if let Some(clip) = edge_point(projector, points, |clip| clip[0]) {
+                labels.push((format_latitude(lat), clip));
+            }
+        }
+        for lon in meridians(spacing) {
+            let points = samples(-80.0, 80.0).map(|lat| [lat, lon]);
+            if let Some(clip) = edge_point(projector, points, |clip| clip[1]) {
+                labels.push((format_longitude(lon), clip));
+            }
+        }
+
+        let top = frame_height - (rect.bottom + rect.height) as f32;
+        let bottom = frame_height - rect.bottom as f32 - 30.0;
+        for (i, (text, clip)) in labels.iter().enumerate() {
+            let (x, y) = GlobalState::clip_to_screen(rect, frame_height, *clip);
+            let width = text.chars().count() as f32 * 7.0 + 16.0;
+            // Keep the whole label inside the viewport
+            let x = x.min((rect.left + rect.width) as f32 - width).max(rect.left as f32);
+            let y = y.min(bottom).max(top);
+            let name = ImString::new(format!("##Graticule Label {} {}", index, i));
+            GlobalState::label_window(ui, name.as_ref(), (x, y), width, text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use state::graticule::*;
+
+    #[test]
+    fn lines_skip_the_poles_and_date_line_repeat() {
+        assert_eq!(parallels(30.0), vec![-60.0, -30.0, 0.0, 30.0, 60.0]);
+        assert_eq!(meridians(90.0), vec![-180.0, -90.0, 0.0, 90.0]);
+        assert_eq!(meridians(7.0).len(), 52);
+    }
+
+    #[test]
+    fn labels_name_the_hemisphere() {
+        assert_eq!(format_latitude(-30.0), "30°S");
+        assert_eq!(format_longitude(45.0), "45°E");
+        assert_eq!(format_longitude(0.0), "0°");
+    }
+}
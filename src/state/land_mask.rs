@@ -0,0 +1,88 @@
+use data_grid::DataGrid;
+use glium::backend::Facade;
+use glium::texture::Texture2d;
+use imgui::{ImString, Ui};
+use state::tool_tips::*;
+use state::GlobalState;
+use support::load_land_mask;
+use util::editable_string;
+
+pub struct LandMaskSettings {
+    pub path: ImString,
+    pub status: String,
+    requested: bool,
+    // A mask read from a file, waiting for its texture to be made
+    pending: Option<DataGrid>,
+}
+
+impl LandMaskSettings {
+    pub fn new() -> LandMaskSettings {
+        LandMaskSettings {
+            path: editable_string("assets/Pure B and W Map.png", 256),
+            status: String::new(),
+            requested: false,
+            pending: None,
+        }
+    }
+
+    pub fn build_mask_ui(&mut self, ui: &Ui, width: f32, loaded: bool, hovered: bool) {
+        if !ui.collapsing_header(im_str!("Land Mask")).build() {
+            return;
+        }
+        ui.text(match loaded {
+            true => "A land mask is loaded",
+            false => "No land mask loaded",
+        });
+        ui.with_item_width(width, || {
+            ui.input_text(im_str!("Mask File"), &mut self.path).build();
+        });
+        land_mask_file_tt(ui, hovered);
+        if ui.button(im_str!("Load Mask"), (100.0, 30.0)) {
+            self.requested = true;
+        }
+        if !self.status.is_empty() {
+            ui.text(&self.status);
+        }
+    }
+}
+
+// Land cells become 1.0 and ocean 0.5, leaving 0.0 where the mask is unknown
+pub fn mask_texture<F: Facade>(display: &F, land_mask: Option<&DataGrid>) -> Texture2d {
+    match land_mask {
+        Some(mask) => mask.to_texture(display, [0.0, 1.0]),
+        None => DataGrid::new(1, 1, vec![None]).to_texture(display, [0.0, 1.0]),
+    }
+}
+
+impl GlobalState {
+    pub fn handle_mask_file(&mut self) {
+        if !self.land_mask_settings.requested {
+            return;
+        }
+        self.land_mask_settings.requested = false;
+        let path = self.land_mask_settings.path.to_str().to_string();
+        self.land_mask_settings.status = match load_land_mask(&path) {
+            Ok(mask) => {
+                let status = format!("Loaded a {} by {} mask", mask.width(), mask.height());
+                self.land_mask_settings.pending = Some(mask);
+                status
+            }
+            Err(error) => format!("Load failed: {}", error),
+        };
+    }
+
+    pub fn update_land_mask<F: Facade>(&mut self, display: &F) {
+        let mask = match self.land_mask_settings.pending.take() {
+            Some(mask) => mask,
+            None => return,
+        };
+        self.mask_texture = mask_texture(display, Some(&mask));
+        self.land_mask = Some(mask);
+        // Everything measured against the old mask is out of date
+        self.stats.clear_cache();
+        self.regions.changed();
+        for value in &mut self.values {
+            value.clear_threshold_area();
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use geo::{lat_lon_to_position, lat_lon_to_tex, wrap_longitude};
+use glium::index::{NoIndices, PrimitiveType::LinesList};
+use glium::texture::Texture2d;
+use glium::{self, backend::Facade, Blend, DrawParameters, Program, Rect, Surface, VertexBuffer};
+use util::LineVertex;
+
+// Longer segments are split so lines follow the curve of the globe
+const MAX_SEGMENT_DEGREES: f32 = 1.0;
+
+fn line_vertex(location: [f32; 2]) -> LineVertex {
+    let location = [location[0], wrap_longitude(location[1])];
+    let position = lat_lon_to_position(location);
+    LineVertex {
+        position: [position.x, position.y, position.z],
+        tex_coord: lat_lon_to_tex(location),
+    }
+}
+
+// Polylines of [lat, lon] points uploaded as line segments
+pub struct LineSet {
+    pub buffer: VertexBuffer<LineVertex>,
+}
+
+impl LineSet {
+    pub fn new<F: Facade>(display: &F, lines: &[Vec<[f32; 2]>]) -> Option<LineSet> {
+        let mut vertices = vec![];
+        for line in lines {
+            for pair in line.windows(2) {
+                let (from, to) = (pair[0], pair[1]);
+                // Segments are interpolated in degrees, one that jumps across
+                // the date line is drawn the short way round
+                let mut delta = [to[0] - from[0], to[1] - from[1]];
+                if delta[1] > 180.0 {
+                    delta[1] -= 360.0;
+                } else if delta[1] < -180.0 {
+                    delta[1] += 360.0;
+                }
+                let length = delta[0].abs().max(delta[1].abs());
+                let parts = (length / MAX_SEGMENT_DEGREES).ceil().max(1.0) as usize;
+                let point = |part: usize| {
+                    let t = part as f32 / parts as f32;
+                    line_vertex([from[0] + delta[0] * t, from[1] + delta[1] * t])
+                };
+                for part in 0..parts {
+                    vertices.push(point(part));
+                    vertices.push(point(part + 1));
+                }
+            }
+        }
+        if vertices.is_empty() {
+            return None;
+        }
+        VertexBuffer::new(display, &vertices)
+            .ok()
+            .map(|buffer| LineSet { buffer })
+    }
+}
+
+// How a set of lines is drawn, the width is in pixels
+#[derive(Copy, Clone, Debug)]
+pub struct LineStyle {
+    pub colour: [f32; 4],
+    pub width: f32,
+}
+
+impl LineStyle {
+    pub fn new(colour: [f32; 4], width: f32) -> LineStyle {
+        LineStyle { colour, width }
+    }
+}
+
+// Lines are widened into quads by the geometry shader, as core contexts only
+// have to support glLineWidth up to 1
+pub struct LineRenderer {
+    pub program: Program,
+}
+
+impl LineRenderer {
+    pub fn new(program: Program) -> LineRenderer {
+        LineRenderer { program }
+    }
+
+    pub fn render<T: Surface + ?Sized>(
+        &self,
+        target: &mut T,
+        lines: &LineSet,
+        view: [[f32; 4]; 4],
+        height_map: &Texture2d,
+        height_scale: f32,
+        style: LineStyle,
+        rect: Rect,
+    ) {
+        // Lines sit on the globe, so they are hidden behind it without
+        // hiding each other
+        let draw_parameters = DrawParameters {
+            depth: glium::Depth {
+                test: glium::DepthTest::IfLess,
+                write: false,
+                ..Default::default()
+            },
+            blend: Blend::alpha_blending(),
+            viewport: Some(rect),
+            ..Default::default()
+        };
+        let uniforms = uniform!{
+            view: view,
+            height_map: height_map,
+            height_scale: height_scale,
+            line_colour: style.colour,
+            half_width: style.width.max(1.0) * 0.5,
+            viewport_size: [rect.width as f32, rect.height as f32],
+        };
+
+        target
+            .draw(
+                &lines.buffer,
+                NoIndices(LinesList),
+                &self.program,
+                &uniforms,
+                &draw_parameters,
+            ).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+use geo::{lat_lon_to_position, lat_lon_to_tex};
+use glium::index::{NoIndices, PrimitiveType::TrianglesList};
+use glium::texture::Texture2d;
+use glium::{backend::Facade, DrawParameters, Program, Surface, VertexBuffer};
+use state::markers::Marker;
+use util::MarkerVertex;
+
+// Height of a marker on screen in pixels
+pub const MARKER_SIZE: f32 = 28.0;
+
+const CORNERS: [[f32; 2]; 6] = [
+    [-1.0, -1.0],
+    [1.0, -1.0],
+    [1.0, 1.0],
+    [-1.0, -1.0],
+    [1.0, 1.0],
+    [-1.0, 1.0],
+];
+
+pub struct MarkerRenderer {
+    pub program: Program,
+    pub buffer: Option<VertexBuffer<MarkerVertex>>,
+}
+
+impl MarkerRenderer {
+    pub fn new(program: Program) -> MarkerRenderer {
+        MarkerRenderer {
+            program,
+            buffer: None,
+        }
+    }
+
+    pub fn update<F: Facade>(&mut self, display: &F, markers: &[Marker]) {
+        if markers.is_empty() {
+            self.buffer = None;
+            return;
+        }
+
+        let mut vertices = Vec::with_capacity(markers.len() * CORNERS.len());
+        for marker in markers {
+            let position = lat_lon_to_position(marker.location);
+            let tex_coord = lat_lon_to_tex(marker.location);
+            for corner in CORNERS.iter() {
+                vertices.push(MarkerVertex {
+                    position: [position.x, position.y, position.z],
+                    tex_coord,
+                    corner: *corner,
+                    colour: marker.colour,
+                });
+            }
+        }
+        self.buffer = VertexBuffer::new(display, &vertices).ok();
+    }
+
+    pub fn render<T: Surface + ?Sized>(
+        &self,
+        target: &mut T,
+        view: [[f32; 4]; 4],
+        height_map: &Texture2d,
+        height_scale: f32,
+        viewport_size: (u32, u32),
+        draw_params: &DrawParameters,
+    ) {
+        let buffer = match self.buffer {
+            Some(ref buffer) => buffer,
+            None => return,
+        };
+        // Clip space spans two units across the viewport and so does the quad
+        let scale = [
+            MARKER_SIZE / viewport_size.0.max(1) as f32,
+            MARKER_SIZE / viewport_size.1.max(1) as f32,
+        ];
+        let uniforms = uniform!{
+            view: view,
+            height_map: height_map,
+            height_scale: height_scale,
+            scale: scale,
+        };
+
+        target
+            .draw(
+                buffer,
+                NoIndices(TrianglesList),
+                &self.program,
+                &uniforms,
+                draw_params,
+            ).unwrap();
+    }
+}
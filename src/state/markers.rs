@@ -0,0 +1,310 @@
+use geo::{format_location, lat_lon_to_position, wrap_longitude};
+use geojson::{self, Feature, Geometry};
+use imgui::{ImString, Ui};
+use renderer::{camera::PCamera, Vec3};
+use serde_json::Value as JsonValue;
+use state::tool_tips::*;
+use state::GlobalState;
+use std::error::Error;
+use util::{clampf32, editable_string};
+
+// Seconds a fly to takes
+const FLIGHT_TIME: f32 = 1.0;
+const MARKER_COLOURS: [[f32; 3]; 5] = [
+    [0.9, 0.2, 0.2],
+    [0.2, 0.5, 0.9],
+    [0.2, 0.75, 0.3],
+    [0.95, 0.7, 0.1],
+    [0.6, 0.3, 0.8],
+];
+
+#[derive(Clone, Debug)]
+pub struct Marker {
+    pub label: ImString,
+    // [lat, lon] in degrees
+    pub location: [f32; 2],
+    pub colour: [f32; 3],
+}
+
+impl Marker {
+    pub fn new(label: &str, location: [f32; 2], colour: [f32; 3]) -> Marker {
+        Marker {
+            label: editable_string(label, 64),
+            location,
+            colour,
+        }
+    }
+
+    pub fn to_feature(&self) -> Feature {
+        let mut feature = Feature::new(Geometry::Point(self.location));
+        feature.properties.insert(
+            "name".to_string(),
+            JsonValue::String(self.label.to_str().to_string()),
+        );
+        feature.properties.insert(
+            "marker-color".to_string(),
+            JsonValue::String(colour_to_hex(self.colour)),
+        );
+        feature
+    }
+}
+
+pub fn colour_to_hex(colour: [f32; 3]) -> String {
+    let byte = |value: f32| (clampf32(value, 0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", byte(colour[0]), byte(colour[1]), byte(colour[2]))
+}
+
+pub fn hex_to_colour(hex: &str) -> Option<[f32; 3]> {
+    let hex = hex.trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|part| u8::from_str_radix(part, 16).ok())
+            .map(|value| value as f32 / 255.0)
+    };
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+// Turns every camera to face a location over a short time
+#[derive(Copy, Clone, Debug)]
+pub struct Flight {
+    to: Vec3,
+    from: Option<Vec3>,
+    progress: f32,
+}
+
+impl Flight {
+    pub fn new(location: [f32; 2]) -> Flight {
+        // Keep clear of the poles where the camera's up vector breaks down
+        let lat = clampf32(location[0], -89.0, 89.0);
+        Flight {
+            to: lat_lon_to_position([lat, location[1]]),
+            from: None,
+            progress: 0.0,
+        }
+    }
+
+    // The direction the cameras should face after dt, None once arrived
+    fn advance(&mut self, dt: f32, current: Vec3) -> Option<Vec3> {
+        let from = *self.from.get_or_insert(current.normalize());
+        self.progress += dt / FLIGHT_TIME;
+        if self.progress >= 1.0 {
+            return None;
+        }
+        let t = self.progress * self.progress * (3.0 - 2.0 * self.progress);
+        let angle = clampf32(from.dot(&self.to), -1.0, 1.0).acos();
+        if angle < 1e-4 || angle.sin() < 1e-4 {
+            return None;
+        }
+        let direction = from * ((1.0 - t) * angle).sin() + self.to * (t * angle).sin();
+        Some(direction.normalize())
+    }
+}
+
+fn face_direction(camera: &mut PCamera, direction: &Vec3) {
+    let distance = camera.position.coords.norm();
+    *camera = PCamera::new(
+        direction * distance,
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        camera.projection,
+    );
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum MarkerFile {
+    Load,
+    Save,
+}
+
+pub struct MarkerSettings {
+    pub markers: Vec<Marker>,
+    pub show_labels: bool,
+    pub path: ImString,
+    pub status: String,
+    // Set whenever markers change so their vertex buffer is rebuilt
+    pub dirty: bool,
+    pub flight: Option<Flight>,
+    requested: Option<MarkerFile>,
+}
+
+impl MarkerSettings {
+    pub fn new() -> MarkerSettings {
+        MarkerSettings {
+            markers: vec![],
+            show_labels: true,
+            path: editable_string("markers.geojson", 256),
+            status: String::new(),
+            dirty: true,
+            flight: None,
+            requested: None,
+        }
+    }
+
+    pub fn add(&mut self, location: [f32; 2]) {
+        let count = self.markers.len();
+        let label = format!("Marker {}", count + 1);
+        let colour = MARKER_COLOURS[count % MARKER_COLOURS.len()];
+        self.markers.push(Marker::new(&label, location, colour));
+        self.dirty = true;
+    }
+
+    // Adds every point in the features, returning how many were added
+    pub fn add_features(&mut self, features: &[Feature]) -> usize {
+        let before = self.markers.len();
+        for feature in features {
+            let points = match feature.geometry {
+                Geometry::Point(point) => vec![point],
+                Geometry::MultiPoint(ref points) => points.clone(),
+                _ => continue,
+            };
+            let label = ["name", "label", "title"]
+                .iter()
+                .filter_map(|key| feature.property_str(key))
+                .next();
+            let colour = feature.property_str("marker-color").and_then(hex_to_colour);
+            for point in points {
+                self.add(point);
+                let marker = self.markers.last_mut().unwrap();
+                if let Some(label) = label {
+                    marker.label = editable_string(label, 64);
+                }
+                if let Some(colour) = colour {
+                    marker.colour = colour;
+                }
+            }
+        }
+        self.markers.len() - before
+    }
+
+    pub fn build_marker_ui(&mut self, ui: &Ui, width: f32, hovered: bool) {
+        if !ui.collapsing_header(im_str!("Markers")).build() {
+            return;
+        }
+        ui.text("Shift click a globe to drop a marker");
+        ui.checkbox(im_str!("Show Labels"), &mut self.show_labels);
+
+        let mut remove = None;
+        let mut recoloured = false;
+        for (i, marker) in self.markers.iter_mut().enumerate() {
+            ui.separator();
+            ui.with_item_width(width, || {
+                let label = ImString::new(format!("Label##Marker {}", i));
+                ui.input_text(&label, &mut marker.label).build();
+                let colour = ImString::new(format!("Colour##Marker {}", i));
+                if ui.color_edit(&colour, &mut marker.colour).build() {
+                    recoloured = true;
+                }
+            });
+            ui.text(format_location(marker.location));
+            let go = ImString::new(format!("Fly To##Marker {}", i));
+            if ui.small_button(&go) {
+                self.flight = Some(Flight::new(marker.location));
+            }
+            fly_to_tt(ui, hovered);
+            ui.same_line(0.0);
+            let delete = ImString::new(format!("Delete##Marker {}", i));
+            if ui.small_button(&delete) {
+                remove = Some(i);
+            }
+        }
+        if let Some(i) = remove {
+            self.markers.remove(i);
+            self.dirty = true;
+        }
+        if recoloured {
+            self.dirty = true;
+        }
+
+        ui.separator();
+        ui.with_item_width(width, || {
+            ui.input_text(im_str!("GeoJSON"), &mut self.path).build();
+        });
+        marker_file_tt(ui, hovered);
+        if ui.button(im_str!("Load"), (100.0, 30.0)) {
+            self.requested = Some(MarkerFile::Load);
+        }
+        ui.same_line(0.0);
+        if ui.button(im_str!("Save"), (100.0, 30.0)) {
+            self.requested = Some(MarkerFile::Save);
+        }
+        if !self.markers.is_empty() {
+            if ui.button(im_str!("Clear Markers"), (100.0, 30.0)) {
+                self.markers.clear();
+                self.dirty = true;
+            }
+        }
+        if !self.status.is_empty() {
+            ui.text(&self.status);
+        }
+    }
+
+    pub fn handle_files(&mut self) {
+        let request = match self.requested.take() {
+            Some(request) => request,
+            None => return,
+        };
+        let path = self.path.to_str().to_string();
+        let result: Result<String, Box<Error>> = match request {
+            MarkerFile::Load => geojson::load(&path).map(|features| {
+                let added = self.add_features(&features);
+                format!("Loaded {} markers", added)
+            }),
+            MarkerFile::Save => {
+                let features: Vec<Feature> =
+                    self.markers.iter().map(|marker| marker.to_feature()).collect();
+                geojson::save(&path, &features)
+                    .map(|_| format!("Saved {} markers", features.len()))
+            }
+        };
+        self.status = match result {
+            Ok(status) => status,
+            Err(error) => format!("Failed: {}", error),
+        };
+    }
+}
+
+impl GlobalState {
+    pub fn drop_marker(&mut self, location: [f32; 2]) {
+        let location = [location[0], wrap_longitude(location[1])];
+        self.markers.add(location);
+    }
+
+    pub fn update_flight(&mut self, dt: f32) {
+        let mut flight = match self.markers.flight.take() {
+            Some(flight) => flight,
+            None => return,
+        };
+        let current = self.camera.position.coords;
+        let direction = match flight.advance(dt, current) {
+            Some(direction) => {
+                self.markers.flight = Some(flight);
+                direction
+            }
+            None => flight.to,
+        };
+
+        face_direction(&mut self.camera, &direction);
+        for settings in self.vp_settings.values_mut() {
+            if let Some(ref mut camera) = settings.cam {
+                face_direction(camera, &direction);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use state::markers::*;
+
+    #[test]
+    fn hex_colours_round_trip() {
+        assert_eq!(colour_to_hex([1.0, 0.5, 0.0]), "#ff8000");
+        let colour = hex_to_colour("#ff8000").unwrap();
+        assert_eq!(colour_to_hex(colour), "#ff8000");
+        assert!((colour[1] - 128.0 / 255.0).abs() < 1e-6);
+        assert_eq!(hex_to_colour("ff80"), None);
+    }
+}
@@ -1,40 +1,81 @@
 mod box_render;
+mod contours;
+mod derive;
+mod export;
+mod graticule;
+mod land_mask;
+mod line_render;
+mod marker_render;
+mod markers;
+mod picking;
+mod plot;
+mod probe;
+mod regions;
+mod session;
+mod stats;
 mod tool_tips;
+mod vector_overlay;
+mod vectors;
+pub mod time;
 pub mod value;
 mod variable;
 mod viewports;
 
+use data_grid::DataGrid;
 use evec::Evec;
 use glium;
 use glium::index::{NoIndices, PrimitiveType::TrianglesList};
 use glium::{
-    backend::glutin::Display, draw_parameters::BackfaceCullingMode, texture::Texture2d,
+    backend::Facade, draw_parameters::BackfaceCullingMode, texture::Texture2d,
     DrawParameters, Program, Rect, Surface, VertexBuffer,
 };
 use imgui::*;
 use input::MouseState;
-use renderer::camera::PCamera;
+use renderer::{camera::PCamera, Mat4, Vec2};
 use sphere::Sphere;
-use state::box_render::BoxRenderer;
+use state::box_render::{
+    BoxRenderer, LEGEND_SCALE, LEGEND_TRANSLATION, SWATCH_SCALE, SWATCH_TRANSLATION,
+};
+use state::contours::ContourSettings;
+use state::derive::DeriveSettings;
+use state::export::ExportSettings;
+use state::graticule::Graticule;
+use state::land_mask::{mask_texture, LandMaskSettings};
+use state::line_render::LineRenderer;
+use state::marker_render::{MarkerRenderer, MARKER_SIZE};
+use state::markers::MarkerSettings;
+use state::picking::Projector;
+use state::plot::SeriesPanel;
+use state::probe::ProbeSettings;
+use state::regions::RegionSettings;
+use state::stats::StatsPanel;
+use state::time::TimeAxis;
 use state::tool_tips::*;
 use state::value::*;
 use state::variable::*;
-use state::viewports::{DivDirection, Division, VPSettings, ViewPort, ViewRect};
+use state::vector_overlay::VectorOverlays;
+use state::vectors::VectorSettings;
+use state::viewports::{DivDirection, Division, VPSettings, ViewOffset, ViewPort, ViewRect};
 use std::collections::BTreeMap;
 use std::error::Error;
 use std::mem;
 use std::path::Path;
-use support::load_image;
+use stats::SurfaceFilter;
+use support::{load_image, load_land_mask};
+use threshold::ThresholdRule;
 use util::*;
 
 pub struct GlobalState {
     pub camera: PCamera,
+    view_offset: ViewOffset,
     zoom: Zoom,
     hsv_program: Program,
     colour_program: Program,
 
     sphere: VertexBuffer<Vertex>,
     box_renderer: BoxRenderer,
+    marker_renderer: MarkerRenderer,
+    line_renderer: LineRenderer,
 
     main_viewport: ViewRect,
     divisions: Evec<Division>,
@@ -43,10 +84,27 @@ pub struct GlobalState {
 
     height_map: Texture2d,
     overlay: Texture2d,
+    // 1 on land and 0 over the ocean, None when the overlay could not be read
+    land_mask: Option<DataGrid>,
+    // The land mask for the shaders, see land_mask::mask_texture
+    mask_texture: Texture2d,
     textures: Vec<Texture2d>,
     values: Vec<Value>,
 
     variables: StateVariables,
+    timeline: Timeline,
+    export: ExportSettings,
+    probe: ProbeSettings,
+    series: SeriesPanel,
+    stats: StatsPanel,
+    markers: MarkerSettings,
+    regions: RegionSettings,
+    graticule: Graticule,
+    vectors: VectorOverlays,
+    contours: ContourSettings,
+    vector_settings: VectorSettings,
+    derive: DeriveSettings,
+    land_mask_settings: LandMaskSettings,
     menu_width: f32,
 
     m1_pressed: bool,
@@ -55,8 +113,8 @@ pub struct GlobalState {
 }
 
 impl GlobalState {
-    pub fn new_default_tex(
-        window: &Display,
+    pub fn new_default_tex<F: Facade>(
+        window: &F,
         camera: PCamera,
         height: impl AsRef<Path>,
         overlay: impl AsRef<Path>,
@@ -65,16 +123,11 @@ impl GlobalState {
         hsv_program: Program,
         colour_program: Program,
         box_program: Program,
+        marker_program: Program,
+        line_program: Program,
     ) -> Result<GlobalState, Box<Error>> {
         let image = load_image(window, path);
-        let value = Value {
-            measurement: Measurement::IsNot,
-            name: tex_name,
-            tex_indices: vec![0],
-            selection: 0.0,
-            time: false,
-            time_updated: false,
-        };
+        let value = Value::new(Measurement::IsNot, tex_name, vec![0], TimeAxis::Steps);
 
         let sphere = Sphere::new(16, 36);
         let verts = sphere.generate_vertices();
@@ -90,6 +143,7 @@ impl GlobalState {
             height: 0.1,
             time_multi: 1.0,
             drag_speed: 0.02,
+            bindings: MouseBindings::new(),
         };
         let main_viewport = ViewRect {
             left: 0.3333,
@@ -106,26 +160,23 @@ impl GlobalState {
 
         let mut viewports = vec![];
         division.build_viewports_evec(main_viewport, &divisions, &mut viewports);
+        let land_mask = load_land_mask(overlay.as_ref()).ok();
         let mut vp_settings = BTreeMap::new();
         {
             let first_vp = &viewports[0];
-            vp_settings.insert(
-                first_vp.div_id,
-                VPSettings {
-                    menu_open: true,
-                    show_range: false,
-                    cam: None,
-                },
-            );
+            vp_settings.insert(first_vp.div_id, VPSettings::new());
         }
 
         Ok(GlobalState {
             camera,
+            view_offset: ViewOffset::new(),
             zoom,
             hsv_program,
             colour_program,
             sphere: buffer,
             box_renderer: BoxRenderer::new(window, box_program),
+            marker_renderer: MarkerRenderer::new(marker_program),
+            line_renderer: LineRenderer::new(line_program),
 
             main_viewport,
             divisions,
@@ -133,11 +184,26 @@ impl GlobalState {
             vp_settings,
 
             height_map: load_image(window, height),
+            mask_texture: mask_texture(window, land_mask.as_ref()),
+            land_mask,
             overlay: load_image(window, overlay),
             textures: vec![image],
             values: vec![value],
 
             variables,
+            timeline: Timeline::new(),
+            export: ExportSettings::new(),
+            probe: ProbeSettings::new(),
+            series: SeriesPanel::new(),
+            stats: StatsPanel::new(),
+            markers: MarkerSettings::new(),
+            regions: RegionSettings::new(),
+            graticule: Graticule::new(),
+            vectors: VectorOverlays::new(),
+            contours: ContourSettings::new(),
+            vector_settings: VectorSettings::new(),
+            derive: DeriveSettings::new(),
+            land_mask_settings: LandMaskSettings::new(),
             menu_width: 300.0,
             m1_pressed: false,
             viewport_pressed: None,
@@ -148,19 +214,15 @@ impl GlobalState {
     pub fn add_new_value(
         &mut self,
         mut new_textures: Vec<Texture2d>,
+        grids: Vec<DataGrid>,
         name: ImString,
         measurement: Measurement,
+        axis: TimeAxis,
     ) {
         let len = self.textures.len();
         let indices = (len..len + new_textures.len()).collect();
-        let value = Value {
-            measurement,
-            name,
-            tex_indices: indices,
-            selection: 0.0,
-            time: false,
-            time_updated: false,
-        };
+        let mut value = Value::new(measurement, name, indices, axis);
+        value.grids = grids;
 
         self.values.push(value);
         self.textures.append(&mut new_textures);
@@ -173,20 +235,74 @@ impl GlobalState {
         if !mouse.on_ui {
             self.zoom.add_zoom(-mouse.mouse.wheel);
         }
-        if let Some(drag) = mouse.get_drag_off_ui() {
-            let (x_abs, y_abs) = (drag.x.abs(), drag.y.abs());
-            let drag_x = clampf32(self.zoom.get_scale() * drag.x, -x_abs, x_abs);
-            let drag_y = clampf32(self.zoom.get_scale() * drag.y, -y_abs, y_abs);
-            let drag_speed = self.variables.drag_speed;
-            let camera = match self.mouse_vars.viewport_pressed {
-                Some(id) => {
-                    let div_id = self.viewports[id].div_id;
-                    self.get_vp_camera_mut(div_id)
-                }
-                None => &mut self.camera,
-            };
-            camera.rotate_around_look_horizontal(-drag_x * drag_speed / hidpi);
-            camera.rotate_around_look_vertical(drag_y * drag_speed / hidpi);
+        // Drawing a box or lasso takes over the left button from the camera
+        let aspect = dimensions.0 as f32 / dimensions.1 as f32;
+        let drawing = self.regions.takes_drags();
+        if drawing {
+            self.handle_region_drag(mouse.get_drag_off_ui().is_some(), aspect);
+        }
+        let left = match drawing {
+            true => None,
+            false => mouse.get_drag_off_ui(),
+        };
+
+        let bindings = self.variables.bindings;
+        let drags = [
+            (bindings.left, left),
+            (bindings.right, mouse.get_right_drag_off_ui()),
+            (bindings.middle, mouse.get_middle_drag_off_ui()),
+        ];
+        for (action, drag) in drags.iter() {
+            if let Some(drag) = drag {
+                self.apply_drag(*action, *drag, dimensions, hidpi);
+            }
+        }
+
+        if let Some(position) = self.mouse_vars.clicked {
+            self.handle_click(position, aspect);
+        }
+    }
+
+    fn apply_drag(&mut self, action: DragAction, drag: Vec2, dimensions: (u32, u32), hidpi: f32) {
+        let drag_speed = self.variables.drag_speed;
+        let (div_id, rect) = match self.mouse_vars.viewport_pressed {
+            Some(id) => (Some(self.viewports[id].div_id), self.viewports[id].rect),
+            None => (None, self.main_viewport),
+        };
+
+        match action {
+            DragAction::Rotate => {
+                let (x_abs, y_abs) = (drag.x.abs(), drag.y.abs());
+                let drag_x = clampf32(self.zoom.get_scale() * drag.x, -x_abs, x_abs);
+                let drag_y = clampf32(self.zoom.get_scale() * drag.y, -y_abs, y_abs);
+                let camera = match div_id {
+                    Some(div_id) => self.get_vp_camera_mut(div_id),
+                    None => &mut self.camera,
+                };
+                camera.rotate_around_look_horizontal(-drag_x * drag_speed / hidpi);
+                camera.rotate_around_look_vertical(drag_y * drag_speed / hidpi);
+            }
+            DragAction::Tilt => {
+                let offset = match div_id {
+                    Some(div_id) => self.get_vp_offset_mut(div_id),
+                    None => &mut self.view_offset,
+                };
+                offset.add_roll(drag.x * drag_speed / hidpi);
+                offset.add_tilt(drag.y * drag_speed / hidpi);
+            }
+            DragAction::Pan => {
+                let width = minf32(rect.width() * dimensions.0 as f32, 1.0);
+                let height = minf32(rect.height() * dimensions.1 as f32, 1.0);
+                let offset = match div_id {
+                    Some(div_id) => self.get_vp_offset_mut(div_id),
+                    None => &mut self.view_offset,
+                };
+                offset.add_pan(
+                    2.0 * drag.x * hidpi / width,
+                    -2.0 * drag.y * hidpi / height,
+                );
+            }
+            DragAction::Nothing => (),
         }
     }
 
@@ -259,6 +375,26 @@ impl GlobalState {
         }
     }
 
+    pub fn get_vp_offset(&self, div_id: usize) -> &ViewOffset {
+        match self.vp_settings.get(&div_id) {
+            Some(ref settings) => match settings.offset {
+                Some(ref offset) => &offset,
+                None => &self.view_offset,
+            },
+            None => &self.view_offset,
+        }
+    }
+
+    pub fn get_vp_offset_mut(&mut self, div_id: usize) -> &mut ViewOffset {
+        match self.vp_settings.get_mut(&div_id) {
+            Some(settings) => match settings.offset {
+                Some(ref mut offset) => offset,
+                None => &mut self.view_offset,
+            },
+            None => &mut self.view_offset,
+        }
+    }
+
     pub fn vp_has_cam(&self, div_id: usize) -> bool {
         match self.vp_settings.get(&div_id) {
             Some(settings) => settings.cam.is_some(),
@@ -270,6 +406,7 @@ impl GlobalState {
         match self.vp_settings.get_mut(&div_id) {
             Some(ref mut settings) => {
                 settings.cam = Some(self.camera);
+                settings.offset = Some(self.view_offset);
             }
             None => panic!("Tried to give camera to a missing viewport"),
         }
@@ -277,7 +414,10 @@ impl GlobalState {
 
     pub fn remove_vp_cam(&mut self, div_id: usize) {
         match self.vp_settings.get_mut(&div_id) {
-            Some(ref mut settings) => settings.cam = None,
+            Some(ref mut settings) => {
+                settings.cam = None;
+                settings.offset = None;
+            }
             None => panic!("Tried to remove camera from a missing viewport"),
         }
     }
@@ -297,8 +437,22 @@ impl GlobalState {
     }
 
     pub fn update_time(&mut self, dt: f32) {
+        self.update_flight(dt);
+        self.update_particles(dt);
         self.reset_time_updates();
         let time_multi = self.variables.time_multi;
+        let synced = self.timeline.synced;
+        if synced {
+            self.timeline.advance(dt * time_multi);
+            let (clock, origin) = (self.timeline.clock, self.timeline_origin());
+            for value in &mut self.values {
+                if value.linked && value.is_time_stepped() {
+                    value.set_from_clock(clock, origin);
+                    value.time_updated = true;
+                }
+            }
+        }
+
         let mut viewports = vec![];
         mem::swap(&mut viewports, &mut self.viewports);
 
@@ -326,17 +480,77 @@ impl GlobalState {
     }
 
     pub fn reset_time_values(&mut self) {
+        self.timeline.clock = 0.0;
         for value in &mut self.values {
             value.selection = 0.0;
         }
     }
 
     pub fn set_values_time(&mut self, val: bool) {
+        self.timeline.playing = val;
         for value in &mut self.values {
             value.time = val;
         }
     }
 
+    // The earliest first month of the linked dated values, the timeline clock
+    // counts months from here
+    pub fn timeline_origin(&self) -> f32 {
+        self.values
+            .iter()
+            .filter(|value| value.linked && value.is_time_stepped())
+            .filter_map(|value| value.axis.start_month())
+            .fold(None, |min: Option<f32>, month| match min {
+                Some(min) if min <= month => Some(min),
+                _ => Some(month),
+            }).unwrap_or(0.0)
+    }
+
+    // Months from the origin to the end of the last linked value, so the
+    // timeline covers every step
+    pub fn timeline_months(&self) -> f32 {
+        let origin = self.timeline_origin();
+        self.values
+            .iter()
+            .filter(|value| value.linked && value.is_time_stepped())
+            .map(|value| {
+                let start = value.axis.start_month().unwrap_or(origin) - origin;
+                start + value.tex_indices.len() as f32 * value.axis.step_months()
+            }).fold(0.0, |max, months| if months > max { months } else { max })
+    }
+
+    pub fn find_value(&self, name: &str) -> Option<usize> {
+        self.values
+            .iter()
+            .position(|value| value.name.to_str() == name)
+    }
+
+    // Collapses to a single viewport showing the value at index
+    pub fn show_value(&mut self, index: usize) {
+        self.collapse_all();
+        if let Some(ref mut division) = self.divisions[0] {
+            if let Some(selected) = division.get_selected_mut() {
+                *selected = index as i32;
+            }
+        }
+        self.rebuild_viewports();
+    }
+
+    pub fn set_value_selection(&mut self, index: usize, selection: f32) {
+        if let Some(value) = self.get_selected_mut(index) {
+            value.selection = selection;
+            value.time = false;
+        }
+    }
+
+    pub fn set_value_range(&mut self, index: usize, new_range: [f32; 2]) {
+        if let Some(value) = self.get_selected_mut(index) {
+            if let Measurement::Is { ref mut range, .. } = value.measurement {
+                *range = new_range;
+            }
+        }
+    }
+
     pub fn on_viewport(&self, pos: [f32; 2]) -> Option<usize> {
         for (i, viewport) in self.viewports.iter().enumerate() {
             if viewport.rect.contains(pos) {
@@ -366,6 +580,16 @@ impl GlobalState {
     pub fn lock_globes(&mut self) {
         for settings in self.vp_settings.values_mut() {
             settings.cam = None;
+            settings.offset = None;
+        }
+    }
+
+    pub fn reset_view_offsets(&mut self) {
+        self.view_offset = ViewOffset::new();
+        for settings in self.vp_settings.values_mut() {
+            if let Some(ref mut offset) = settings.offset {
+                *offset = ViewOffset::new();
+            }
         }
     }
 
@@ -386,67 +610,165 @@ impl GlobalState {
             let value = self.vp_settings.get(&id);
             match value {
                 Some(value) => settings.insert(id, *value),
-                None => settings.insert(
-                    id,
-                    VPSettings {
-                        menu_open: true,
-                        show_range: false,
-                        cam: None,
-                    },
-                ),
+                None => settings.insert(id, VPSettings::new()),
             };
         }
         self.vp_settings = settings;
         mem::swap(&mut viewports, &mut self.viewports);
     }
 
+    // Rebuilds marker and line vertices after anything drawn over the globes changed
+    pub fn update_overlays<F: Facade>(&mut self, display: &F) {
+        if self.markers.dirty {
+            self.marker_renderer.update(display, &self.markers.markers);
+            self.markers.dirty = false;
+        }
+        self.update_graticule(display);
+        self.update_vector_overlays(display);
+        self.update_contours(display);
+        self.update_vectors(display);
+        self.update_regions(display);
+        self.update_land_mask(display);
+    }
+
+    // Every value shown in a viewport whose settings pass, each listed once
+    pub fn shown_values<P: Fn(&VPSettings) -> bool>(&self, show: P) -> Vec<usize> {
+        let mut shown = vec![];
+        for viewport in &self.viewports {
+            let showing = self.vp_settings.get(&viewport.div_id).map_or(false, |s| show(s));
+            let index = viewport.get_div_selection(&self.divisions.values);
+            if let (true, Some(index)) = (showing, index) {
+                if !shown.contains(&(index as usize)) {
+                    shown.push(index as usize);
+                }
+            }
+        }
+        shown
+    }
+
+    pub fn viewport_view_matrix(&self, viewport: &ViewPort, aspect: f32) -> Mat4 {
+        let mut camera = *self.get_vp_camera(viewport.div_id);
+        camera.set_aspect(aspect);
+        let offset = self.get_vp_offset(viewport.div_id);
+        viewport.view_matrix(&camera, offset, self.zoom.get_scale())
+    }
+
     pub fn render_viewports<T: Surface + ?Sized>(
         &self,
         target: &mut T,
         model_matrix: [[f32; 4]; 4],
     ) {
-        let frame = target.get_dimensions();
         for viewport in &self.viewports {
-            if !viewport.can_render() {
-                continue;
-            }
-            let rect = viewport.glium_viewport(frame);
+            self.render_viewport(target, viewport, model_matrix, false);
+        }
+    }
+
+    pub fn render_viewport<T: Surface + ?Sized>(
+        &self,
+        target: &mut T,
+        viewport: &ViewPort,
+        model_matrix: [[f32; 4]; 4],
+        force_range: bool,
+    ) {
+        let frame = target.get_dimensions();
+        if !viewport.can_render() {
+            return;
+        }
+        let rect = viewport.glium_viewport(frame);
+        let draw_parameters = DrawParameters {
+            depth: glium::Depth {
+                test: glium::DepthTest::IfLess,
+                write: true,
+                ..Default::default()
+            },
+            backface_culling: BackfaceCullingMode::CullClockwise,
+            viewport: Some(rect),
+            ..Default::default()
+        };
+
+        let id = viewport.div_id;
+        let index = self.divisions[id].unwrap().get_selected().unwrap() as usize;
+        let settings = self.vp_settings.get(&id).expect("No viewport settings");
+
+        let view_matrix = self.viewport_view_matrix(viewport, frame.0 as f32 / frame.1 as f32);
+        let overlay_strength = match settings.raster_overlay {
+            true => 1.0,
+            false => 0.0,
+        };
+        let is_vector = self.values.get(index).map_or(false, |value| value.is_vector());
+        // The first value is the plain world map
+        let globe_index = match is_vector && !self.vector_settings.colour_magnitude {
+            true => 0,
+            false => index,
+        };
+        self.draw_globe(
+            globe_index,
+            target,
+            &draw_parameters,
+            *view_matrix.as_ref(),
+            model_matrix,
+            overlay_strength,
+            settings.surface,
+        );
+
+        if settings.vector_overlays {
+            self.render_vector_overlays(target, *view_matrix.as_ref(), rect);
+        }
+        if settings.contours {
+            self.render_contours(target, index, *view_matrix.as_ref(), rect);
+        }
+        if settings.vectors && is_vector {
+            self.render_vectors(target, index, *view_matrix.as_ref(), rect);
+        }
+        if settings.graticule.show {
+            self.render_graticule(target, &settings.graticule, *view_matrix.as_ref(), rect);
+        }
+        self.render_regions(target, *view_matrix.as_ref(), rect);
+
+        if !self.markers.markers.is_empty() {
             let draw_parameters = DrawParameters {
                 depth: glium::Depth {
                     test: glium::DepthTest::IfLess,
                     write: true,
                     ..Default::default()
                 },
-                backface_culling: BackfaceCullingMode::CullClockwise,
                 viewport: Some(rect),
                 ..Default::default()
             };
-
-            let id = viewport.div_id;
-            let index = self.divisions[id].unwrap().get_selected().unwrap() as usize;
-            let settings = self.vp_settings.get(&id).expect("No viewport settings");
-            let camera = match settings.cam {
-                Some(ref cam) => cam,
-                None => &self.camera,
-            };
-
-            let view_matrix = viewport.view_matrix(camera, self.zoom.get_scale());
-            self.draw_globe(
-                index,
+            self.marker_renderer.render(
                 target,
-                &draw_parameters,
                 *view_matrix.as_ref(),
-                model_matrix,
+                &self.height_map,
+                self.variables.height,
+                (rect.width, rect.height),
+                &draw_parameters,
             );
+        }
 
-            let draw_parameters = DrawParameters {
-                viewport: Some(rect),
-                ..Default::default()
-            };
+        let draw_parameters = DrawParameters {
+            viewport: Some(rect),
+            ..Default::default()
+        };
 
-            if settings.show_range && self.is_selected_measurement(index).is_measurement() {
-                self.box_renderer
-                    .render(target, [0.78, 0.0], [0.1, 1.8], &draw_parameters);
+        let show_range = settings.show_range || force_range;
+        if show_range && self.is_selected_measurement(index).is_measurement() {
+            let diverging = self.get_selected(index).map_or(false, |value| value.diverging);
+            self.box_renderer.render(
+                target,
+                LEGEND_TRANSLATION,
+                LEGEND_SCALE,
+                diverging,
+                &draw_parameters,
+            );
+            if let Some(value) = self.get_selected(index).filter(|value| value.has_data()) {
+                self.box_renderer.render_swatch(
+                    target,
+                    SWATCH_TRANSLATION,
+                    SWATCH_SCALE,
+                    value.no_data_colour,
+                    value.no_data_style == NoDataStyle::Hatch,
+                    &draw_parameters,
+                );
             }
         }
     }
@@ -460,8 +782,19 @@ impl GlobalState {
         draw_params: &DrawParameters,
         view_matrix: [[f32; 4]; 4],
         model_matrix: [[f32; 4]; 4],
+        overlay_strength: f32,
+        surface: SurfaceFilter,
     ) {
         let (tex1, tex2, interp) = self.get_selected_textures(index).unwrap();
+        let threshold = self
+            .get_selected(index)
+            .map_or(ThresholdRule::new(), |value| value.threshold);
+        let diverging = self.get_selected(index).map_or(false, |value| value.diverging);
+        let (no_data_style, no_data_colour) = self
+            .get_selected(index)
+            .map_or((NoDataStyle::Fill, [0.0; 3]), |value| {
+                (value.no_data_style, value.no_data_colour)
+            });
         match self.is_selected_measurement(index) {
             Measurement::Is {
                 normalised,
@@ -470,12 +803,23 @@ impl GlobalState {
             } => {
                 let uniforms = uniform! {
                     overlay: &self.overlay,
+                    overlay_strength: overlay_strength,
                     colour_map1: tex1,
                     colour_map2: tex2,
                     interpolation: interp,
                     init_range: init_range,
                     range: range,
                     normalised: normalised,
+                    threshold_condition: threshold.condition.index(),
+                    thresholds: threshold.ordered_limits(),
+                    threshold_style: threshold.style.index(),
+                    threshold_colour: threshold.colour,
+                    threshold_surface: threshold.surface.index(),
+                    land_mask: &self.mask_texture,
+                    surface_filter: surface.index(),
+                    diverging: diverging,
+                    no_data_colour: no_data_colour,
+                    no_data_style: no_data_style.index(),
                     view: view_matrix,
                     eye: *self.camera.position.coords.as_ref(),
                     rotation: model_matrix,
@@ -495,6 +839,8 @@ impl GlobalState {
                 let uniforms = uniform! {
                     overlay: &self.overlay,
                     colour_map1: tex1,
+                    land_mask: &self.mask_texture,
+                    surface_filter: surface.index(),
                     view: view_matrix,
                     eye: *self.camera.position.coords.as_ref(),
                     rotation: model_matrix,
@@ -554,6 +900,51 @@ impl GlobalState {
         });
     }
 
+    pub fn label_window(ui: &Ui, name: &ImStr, pos: (f32, f32), width: f32, text: &str) {
+        ui.with_color_var(ImGuiCol::WindowBg, [0.0, 0.0, 0.0, 0.3], || {
+            ui.window(name)
+                .position(pos, ImGuiCond::Always)
+                .size((width, 30.0), ImGuiCond::Always)
+                .title_bar(false)
+                .collapsible(false)
+                .resizable(false)
+                .movable(false)
+                .build(|| {
+                    ui.text(text);
+                });
+        });
+    }
+
+    // Converts a point in a viewport's clip space to a window position
+    pub fn clip_to_screen(rect: Rect, frame_height: f32, clip: [f32; 2]) -> (f32, f32) {
+        let x = rect.left as f32 + (clip[0] + 1.0) * 0.5 * rect.width as f32;
+        let y = frame_height - (rect.bottom as f32 + (clip[1] + 1.0) * 0.5 * rect.height as f32);
+        (x, y)
+    }
+
+    fn build_marker_labels(&self, ui: &Ui, index: usize, projector: &Projector, rect: Rect, frame_height: f32) {
+        for (i, marker) in self.markers.markers.iter().enumerate() {
+            let clip = match projector.project(marker.location) {
+                Some(clip) => clip,
+                None => continue,
+            };
+            let (x, y) = GlobalState::clip_to_screen(rect, frame_height, clip);
+            let text = marker.label.to_str();
+            let width = text.chars().count() as f32 * 7.0 + 16.0;
+            let name = ImString::new(format!("##Marker Label {} {}", index, i));
+            let pos = (x + MARKER_SIZE * 0.5, y - MARKER_SIZE - 15.0);
+            GlobalState::label_window(ui, name.as_ref(), pos, width, text);
+        }
+    }
+
+    fn viewport_time_label(&self, viewport: &ViewPort, divisions: &Evec<Division>) -> Option<String> {
+        let index = viewport.get_div_selection(&divisions.values)? as usize;
+        match self.get_selected(index) {
+            Some(value) if value.is_time_stepped() => Some(value.current_label()),
+            _ => None,
+        }
+    }
+
     pub fn build_viewport_uis(&mut self, ui: &Ui) {
         let frame_size = ui.frame_size().logical_size;
         let window_width = self.menu_width - 95.0;
@@ -589,6 +980,32 @@ impl GlobalState {
             if let Some(menu) = self.get_vp_menu_open_mut(viewport.div_id) {
                 *menu = opened;
             }
+
+            if let Some(label) = self.viewport_time_label(viewport, &divisions) {
+                let name = ImString::new(format!("##Time Label {}", i));
+                let label_x = x + rect.width as f32 - 170.0;
+                let label_y = frame_size.1 as f32 - rect.bottom as f32 - 40.0;
+                GlobalState::label_window(ui, name.as_ref(), (label_x, label_y), 160.0, &label);
+            }
+
+            let aspect = (frame_size.0 / frame_size.1) as f32;
+            let frame_height = frame_size.1 as f32;
+            let graticule_labels = self.graticule.labels
+                && self.vp_settings.get(&viewport.div_id).map_or(false, |s| s.graticule.show);
+            let contour_labels = self.contours.labels
+                && self.vp_settings.get(&viewport.div_id).map_or(false, |s| s.contours);
+            let selected = viewport.get_div_selection(&divisions.values);
+            if let Some(projector) = self.projector(viewport, aspect) {
+                if self.markers.show_labels {
+                    self.build_marker_labels(ui, i, &projector, rect, frame_height);
+                }
+                if graticule_labels {
+                    self.build_graticule_labels(ui, i, &projector, rect, frame_height);
+                }
+                if let (true, Some(index)) = (contour_labels, selected) {
+                    self.build_contour_labels(ui, i, index as usize, &projector, rect, frame_height);
+                }
+            }
         }
 
         mem::swap(&mut viewports, &mut self.viewports);
@@ -661,6 +1078,28 @@ impl GlobalState {
                 unlock_tt(ui, hovered);
 
             }
+            if let Some(settings) = self.vp_settings.get_mut(&id) {
+                settings.graticule.build_style_ui(ui, window_width, hovered);
+                ui.checkbox(im_str!("Raster Overlay"), &mut settings.raster_overlay);
+                raster_overlay_tt(ui, hovered);
+                ui.same_line(0.0);
+                ui.checkbox(im_str!("Vector Overlays"), &mut settings.vector_overlays);
+                ui.checkbox(im_str!("Contours"), &mut settings.contours);
+                contours_tt(ui, hovered);
+                ui.same_line(0.0);
+                ui.checkbox(im_str!("Vectors"), &mut settings.vectors);
+                let surfaces = [
+                    im_str!("Everywhere"),
+                    im_str!("Land Only"),
+                    im_str!("Ocean Only"),
+                ];
+                let mut surface = settings.surface.index();
+                ui.with_item_width(window_width * 0.5, || {
+                    ui.combo(im_str!("Show"), &mut surface, &surfaces, 3);
+                });
+                settings.surface = SurfaceFilter::from_index(surface);
+                surface_filter_tt(ui, hovered);
+            }
 
             ui.separator();
             ui.spacing();
@@ -706,11 +1145,88 @@ impl GlobalState {
                 let button_y = 30.0;
                 self.build_projection_options(ui, (button_size, button_y), 12.0);
                 self.variables.build_variable_sliders(ui, button_size * 2.0 + 4.0, self.mouse_vars.hovered);
+                let max_months = self.timeline_months();
+                self.timeline.build_timeline_ui(
+                    ui,
+                    button_size * 2.0 + 4.0,
+                    max_months,
+                    self.mouse_vars.hovered,
+                );
                 self.build_user_buttons(ui, (100.0, 40.0), 12.0);
+                self.export.build_export_ui(
+                    ui,
+                    button_size * 2.0 + 4.0,
+                    self.viewports.len(),
+                    self.mouse_vars.hovered,
+                );
+                let reading = self.probe_reading();
+                let names: Vec<&ImStr> =
+                    self.values.iter().map(|value| value.name.as_ref()).collect();
+                self.probe.build_probe_ui(
+                    ui,
+                    button_size * 2.0 + 4.0,
+                    &names,
+                    reading,
+                    &mut self.series.open,
+                    self.mouse_vars.hovered,
+                );
+                self.derive.build_derive_ui(
+                    ui,
+                    button_size * 2.0 + 4.0,
+                    &names,
+                    self.mouse_vars.hovered,
+                );
+                self.regions.build_region_ui(
+                    ui,
+                    button_size * 2.0 + 4.0,
+                    &names,
+                    self.mouse_vars.hovered,
+                );
+                self.markers.build_marker_ui(
+                    ui,
+                    button_size * 2.0 + 4.0,
+                    self.mouse_vars.hovered,
+                );
+                self.graticule.build_graticule_ui(
+                    ui,
+                    button_size * 2.0 + 4.0,
+                    self.mouse_vars.hovered,
+                );
+                self.land_mask_settings.build_mask_ui(
+                    ui,
+                    button_size * 2.0 + 4.0,
+                    self.land_mask.is_some(),
+                    self.mouse_vars.hovered,
+                );
+                self.vectors.build_overlay_ui(
+                    ui,
+                    button_size * 2.0 + 4.0,
+                    self.mouse_vars.hovered,
+                );
+                self.contours.build_contour_ui(
+                    ui,
+                    button_size * 2.0 + 4.0,
+                    self.mouse_vars.hovered,
+                );
+                self.vector_settings.build_vector_ui(
+                    ui,
+                    button_size * 2.0 + 4.0,
+                    self.mouse_vars.hovered,
+                );
+
+                ui.checkbox(im_str!("Statistics"), &mut self.stats.open);
+                statistics_tt(ui, self.mouse_vars.hovered);
 
                 ui.text(format!("Fps: {:.2}", ui.framerate()));
             });
         self.build_viewport_uis(ui);
+        self.build_series_window(ui);
+        self.build_stats_window(ui);
+        self.handle_probe_exports();
+        self.markers.handle_files();
+        self.vectors.handle_files();
+        self.handle_region_files();
+        self.handle_mask_file();
     }
 
     fn build_user_buttons(&mut self, ui: &Ui, size: (f32, f32), spacing: f32) {
@@ -736,6 +1252,11 @@ impl GlobalState {
         if ui.button(im_str!("Close Menus"), size) {
             self.hide_menus();
         }
+
+        if ui.button(im_str!("Reset View"), size) {
+            self.reset_view_offsets();
+        }
+        reset_view_tt(ui, self.mouse_vars.hovered);
     }
 
     fn build_projection_options(&mut self, ui: &Ui, size: (f32, f32), spacing: f32) {
@@ -774,9 +1295,22 @@ impl GlobalState {
             });
         value_selector_tt(ui, self.mouse_vars.hovered);
 
+        let synced = self.timeline.synced;
+        let origin = self.timeline_origin();
         let selected = &mut self.values[*to_select as usize];
         ui.separator();
         ui.text(&selected.name);
-        selected.build_ui_elements(ui, window_width, self.mouse_vars.hovered);
+        let changed = selected.build_ui_elements(
+            ui,
+            window_width,
+            self.mouse_vars.hovered,
+            synced,
+            self.mouse_vars.pressed.0,
+            self.land_mask.as_ref(),
+        );
+        if changed && synced && selected.linked {
+            self.timeline.clock = selected.clock(origin);
+            self.timeline.playing = false;
+        }
     }
 }
@@ -0,0 +1,107 @@
+use geo::{lat_lon_to_position, position_to_lat_lon};
+use na::Vector4;
+use renderer::{Mat4, Vec3};
+use state::probe::PickMode;
+use state::viewports::ViewPort;
+use state::GlobalState;
+
+// Moves between locations on the globe and a viewport's clip space. The
+// height displacement is ignored so high terrain is slightly off.
+pub struct Projector {
+    matrix: Mat4,
+    inverse: Mat4,
+}
+
+impl Projector {
+    pub fn new(matrix: Mat4) -> Option<Projector> {
+        let inverse = matrix.try_inverse()?;
+        Some(Projector { matrix, inverse })
+    }
+
+    // Where a ray through a point in clip space first meets the globe
+    pub fn ray_hit(&self, clip: [f32; 2]) -> Option<Vec3> {
+        let unproject = |depth: f32| {
+            let point = self.inverse * Vector4::new(clip[0], clip[1], depth, 1.0);
+            Vec3::new(point.x, point.y, point.z) / point.w
+        };
+
+        let near = unproject(-1.0);
+        let direction = (unproject(1.0) - near).normalize();
+        let b = near.dot(&direction);
+        let discriminant = b * b - (near.dot(&near) - 1.0);
+        if discriminant < 0.0 {
+            return None;
+        }
+        Some(near + direction * (-b - discriminant.sqrt()))
+    }
+
+    // The clip space position of a location, None when it is off screen or on
+    // the far side of the globe
+    pub fn project(&self, location: [f32; 2]) -> Option<[f32; 2]> {
+        let position = lat_lon_to_position(location);
+        let point = self.matrix * Vector4::new(position.x, position.y, position.z, 1.0);
+        if point.w <= 0.0 {
+            return None;
+        }
+        let clip = [point.x / point.w, point.y / point.w];
+        if clip[0].abs() > 1.0 || clip[1].abs() > 1.0 {
+            return None;
+        }
+        let hit = self.ray_hit(clip)?;
+        match (hit - position).norm() < 0.02 {
+            true => Some(clip),
+            false => None,
+        }
+    }
+}
+
+impl GlobalState {
+    pub fn projector(&self, viewport: &ViewPort, aspect: f32) -> Option<Projector> {
+        Projector::new(self.viewport_view_matrix(viewport, aspect))
+    }
+
+    // The viewport and [lat, lon] under a normalised window position
+    pub fn pick_location(&self, position: [f32; 2], aspect: f32) -> Option<(usize, [f32; 2])> {
+        let index = self.on_viewport(position)?;
+        let viewport = &self.viewports[index];
+        let rect = viewport.rect;
+        let clip = [
+            2.0 * (position[0] - rect.left) / rect.width() - 1.0,
+            2.0 * (position[1] - rect.bottom) / rect.height() - 1.0,
+        ];
+        let hit = self.projector(viewport, aspect)?.ray_hit(clip)?;
+        Some((index, position_to_lat_lon(&hit)))
+    }
+
+    // Clicks add vertices to a region being drawn, then pick for the probe,
+    // otherwise shift clicks drop markers
+    pub fn handle_click(&mut self, position: [f32; 2], aspect: f32) {
+        if self.regions.takes_clicks() {
+            if let Some((_, location)) = self.pick_location(position, aspect) {
+                self.regions.add_vertex(location);
+            }
+            return;
+        }
+        let picking = self.probe.picking;
+        let shift = self.mouse_vars.click_modifiers.shift;
+        if picking == PickMode::Nothing && !shift {
+            return;
+        }
+        let (index, location) = match self.pick_location(position, aspect) {
+            Some(picked) => picked,
+            None => return,
+        };
+
+        if picking == PickMode::Nothing {
+            self.drop_marker(location);
+            return;
+        }
+        let value = self.viewports[index]
+            .get_div_selection(&self.divisions.values)
+            .unwrap_or(0);
+        if picking == PickMode::Point {
+            self.series.open = true;
+        }
+        self.probe.picked(location, value);
+    }
+}
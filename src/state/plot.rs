@@ -0,0 +1,208 @@
+use geo::format_location;
+use imgui::{ImGuiCond, ImString, Ui};
+use state::tool_tips::*;
+use state::value::{Measurement, Value};
+use state::GlobalState;
+
+const PLOT_SIZE: (f32, f32) = (380.0, 180.0);
+// The picked location uses the first colour, pins cycle through the rest
+const SERIES_COLOURS: [[f32; 4]; 6] = [
+    [1.0, 1.0, 1.0, 1.0],
+    [1.0, 0.45, 0.2, 1.0],
+    [0.3, 0.7, 1.0, 1.0],
+    [0.4, 0.9, 0.4, 1.0],
+    [1.0, 0.85, 0.3, 1.0],
+    [0.9, 0.45, 0.9, 1.0],
+];
+const SELECTION_COLOUR: [f32; 4] = [1.0, 0.2, 0.2, 0.8];
+
+pub struct SeriesPanel {
+    pub open: bool,
+    // Locations kept on the plot for comparison as [lat, lon]
+    pub pinned: Vec<[f32; 2]>,
+}
+
+impl SeriesPanel {
+    pub fn new() -> SeriesPanel {
+        SeriesPanel {
+            open: false,
+            pinned: vec![],
+        }
+    }
+
+    pub fn colour(index: usize) -> [f32; 4] {
+        match index {
+            0 => SERIES_COLOURS[0],
+            _ => SERIES_COLOURS[1 + (index - 1) % (SERIES_COLOURS.len() - 1)],
+        }
+    }
+
+    pub fn pin(&mut self, location: [f32; 2]) {
+        if !self.pinned.contains(&location) {
+            self.pinned.push(location);
+        }
+    }
+}
+
+// The y axis follows the colour range of measurements, anything else is
+// scaled to fit its values
+fn plot_range(value: &Value, series: &[Vec<Option<f32>>]) -> [f32; 2] {
+    if let Measurement::Is { range, .. } = value.measurement {
+        return range;
+    }
+    let mut min = ::std::f32::MAX;
+    let mut max = ::std::f32::MIN;
+    for sample in series.iter().flat_map(|line| line.iter()).filter_map(|v| *v) {
+        min = min.min(sample);
+        max = max.max(sample);
+    }
+    if min > max {
+        [0.0, 1.0]
+    } else if min == max {
+        [min - 1.0, max + 1.0]
+    } else {
+        [min, max]
+    }
+}
+
+impl GlobalState {
+    pub fn build_series_window(&mut self, ui: &Ui) {
+        if !self.series.open {
+            return;
+        }
+        let mut opened = self.series.open;
+        let position = (self.menu_width + 10.0, 10.0);
+        let hovered = self.mouse_vars.hovered;
+
+        ui.window(im_str!("Time Series"))
+            .size((PLOT_SIZE.0 + 40.0, PLOT_SIZE.1 + 200.0), ImGuiCond::FirstUseEver)
+            .position(position, ImGuiCond::FirstUseEver)
+            .collapsible(true)
+            .opened(&mut opened)
+            .build(|| {
+                self.build_series_plot(ui, hovered);
+            });
+        self.series.open = opened;
+    }
+
+    fn build_series_plot(&mut self, ui: &Ui, hovered: bool) {
+        let value = match self.get_selected(self.probe.value as usize) {
+            Some(value) => value,
+            None => return,
+        };
+        ui.text(&value.name);
+        if !value.has_data() {
+            ui.text("This variable has no data grid");
+            return;
+        }
+
+        let mut locations = vec![];
+        if let Some(location) = self.probe.location {
+            locations.push((0, location));
+        }
+        for (i, location) in self.series.pinned.iter().enumerate() {
+            locations.push((i + 1, *location));
+        }
+        let series: Vec<Vec<Option<f32>>> = locations
+            .iter()
+            .map(|(_, location)| value.series_at(*location))
+            .collect();
+        let range = plot_range(value, &series);
+        let steps = value.grids.len();
+
+        ui.text(format!("{:.2} to {:.2}", range[0], range[1]));
+        let origin = ui.get_cursor_screen_pos();
+        // An empty plot draws the frame and reserves its space, the lines go over it
+        ui.plot_lines(im_str!("##Series Plot"), &[])
+            .graph_size(PLOT_SIZE)
+            .scale_min(range[0])
+            .scale_max(range[1])
+            .build();
+        series_plot_tt(ui, hovered);
+
+        let to_screen = |step: f32, sample: f32| {
+            let x = match steps > 1 {
+                true => step / (steps - 1) as f32,
+                false => 0.5,
+            };
+            let y = (sample - range[0]) / (range[1] - range[0]);
+            let y = y.max(0.0).min(1.0);
+            (
+                origin.0 + x * PLOT_SIZE.0,
+                origin.1 + (1.0 - y) * PLOT_SIZE.1,
+            )
+        };
+
+        {
+            let draw_list = ui.get_window_draw_list();
+            let marker = value.shown_selection().min((steps - 1) as f32);
+            draw_list
+                .add_line(
+                    to_screen(marker, range[0]),
+                    to_screen(marker, range[1]),
+                    SELECTION_COLOUR,
+                ).thickness(2.0)
+                .build();
+
+            for ((colour, _), line) in locations.iter().zip(series.iter()) {
+                let colour = SeriesPanel::colour(*colour);
+                let mut previous = None;
+                for (step, sample) in line.iter().enumerate() {
+                    let point = sample.map(|sample| to_screen(step as f32, sample));
+                    if let (Some(from), Some(to)) = (previous, point) {
+                        draw_list.add_line(from, to, colour).thickness(2.0).build();
+                    }
+                    if let Some(point) = point {
+                        draw_list.add_circle(point, 2.5, colour).filled(true).build();
+                    }
+                    previous = point;
+                }
+            }
+        }
+
+        ui.text(format!(
+            "{} - {}",
+            value.axis.label(0),
+            value.axis.label(steps - 1)
+        ));
+        ui.text(format!("Shown: {}", value.current_label()));
+        ui.separator();
+
+        let mut remove = None;
+        for ((colour, location), line) in locations.iter().zip(series.iter()) {
+            let current = line
+                .get(value.current_step())
+                .and_then(|sample| *sample)
+                .map(|sample| format!("{:.2}", sample))
+                .unwrap_or_else(|| "No data".to_string());
+            let text = ImString::new(format!("{}  {}", format_location(*location), current));
+            ui.text_colored(SeriesPanel::colour(*colour), &text);
+            if *colour > 0 {
+                ui.same_line(0.0);
+                let label = ImString::new(format!("Remove##Pin {}", colour));
+                if ui.small_button(&label) {
+                    remove = Some(colour - 1);
+                }
+            }
+        }
+
+        let location = self.probe.location;
+        if let Some(location) = location {
+            if ui.button(im_str!("Pin Location"), (110.0, 30.0)) {
+                self.series.pin(location);
+            }
+            pin_series_tt(ui, hovered);
+        }
+        if let Some(remove) = remove {
+            self.series.pinned.remove(remove);
+        }
+        if !self.series.pinned.is_empty() {
+            if location.is_some() {
+                ui.same_line(0.0);
+            }
+            if ui.button(im_str!("Clear Pins"), (110.0, 30.0)) {
+                self.series.pinned.clear();
+            }
+        }
+    }
+}
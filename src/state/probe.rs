@@ -0,0 +1,319 @@
+use geo::{format_location, wrap_longitude, Region};
+use imgui::{ImStr, ImString, Ui};
+use state::tool_tips::*;
+use state::GlobalState;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use util::{clampf32, editable_string};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PickMode {
+    Nothing,
+    Point,
+    Polygon,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum CsvExport {
+    Series,
+    Region,
+}
+
+pub struct ProbeSettings {
+    pub value: i32,
+    pub location: Option<[f32; 2]>,
+    // South and north bounds of the region box
+    pub latitudes: [f32; 2],
+    // West and east bounds of the region box
+    pub longitudes: [f32; 2],
+    pub polygon: Vec<[f32; 2]>,
+    pub use_polygon: bool,
+    pub picking: PickMode,
+    pub series_path: ImString,
+    pub region_path: ImString,
+    requested: Option<CsvExport>,
+    pub status: String,
+}
+
+impl ProbeSettings {
+    pub fn new() -> ProbeSettings {
+        ProbeSettings {
+            value: 0,
+            location: None,
+            latitudes: [-10.0, 10.0],
+            longitudes: [-10.0, 10.0],
+            polygon: vec![],
+            use_polygon: false,
+            picking: PickMode::Nothing,
+            series_path: editable_string("series.csv", 256),
+            region_path: editable_string("region.csv", 256),
+            requested: None,
+            status: String::new(),
+        }
+    }
+
+    pub fn region(&self) -> Region {
+        match self.use_polygon {
+            true => Region::Polygon(self.polygon.clone()),
+            false => Region::Bounds {
+                south: self.latitudes[0],
+                north: self.latitudes[1],
+                west: self.longitudes[0],
+                east: self.longitudes[1],
+            },
+        }
+    }
+
+    // Handles a click on a globe while picking, location is [lat, lon]
+    pub fn picked(&mut self, location: [f32; 2], value: i32) {
+        match self.picking {
+            PickMode::Point => {
+                self.location = Some(location);
+                self.value = value;
+                self.picking = PickMode::Nothing;
+            }
+            PickMode::Polygon => self.polygon.push(location),
+            PickMode::Nothing => (),
+        }
+    }
+
+    pub fn build_probe_ui(
+        &mut self,
+        ui: &Ui,
+        width: f32,
+        names: &[&ImStr],
+        reading: Option<String>,
+        plot_open: &mut bool,
+        hovered: bool,
+    ) {
+        if !ui.collapsing_header(im_str!("Data Probe")).build() {
+            return;
+        }
+
+        ui.with_item_width(width, || {
+            ui.combo(im_str!("Variable"), &mut self.value, names, 5);
+        });
+
+        let pick_label = match self.picking {
+            PickMode::Point => im_str!("Cancel"),
+            _ => im_str!("Pick Point"),
+        };
+        if ui.button(pick_label, (100.0, 30.0)) {
+            self.picking = match self.picking {
+                PickMode::Point => PickMode::Nothing,
+                _ => PickMode::Point,
+            };
+        }
+        pick_point_tt(ui, hovered);
+
+        match self.location {
+            Some(location) => {
+                ui.text(format_location(location));
+                if let Some(reading) = reading {
+                    ui.text(reading);
+                }
+                let mut edit = location;
+                ui.with_item_width(width, || {
+                    if ui.input_float2(im_str!("Location"), &mut edit).build() {
+                        edit[0] = clampf32(edit[0], -90.0, 90.0);
+                        edit[1] = wrap_longitude(edit[1]);
+                        self.location = Some(edit);
+                    }
+                });
+            }
+            None => ui.text("No location picked"),
+        }
+        if ui.small_button(im_str!("Plot Series")) {
+            *plot_open = true;
+        }
+        plot_series_tt(ui, hovered);
+
+        ui.separator();
+        ui.text("Region");
+        ui.with_item_width(width, || {
+            ui.input_float2(im_str!("Latitude"), &mut self.latitudes).build();
+            ui.input_float2(im_str!("Longitude"), &mut self.longitudes)
+                .build();
+        });
+        region_bounds_tt(ui, hovered);
+
+        ui.checkbox(im_str!("Use Polygon"), &mut self.use_polygon);
+        ui.same_line(0.0);
+        let draw_label = match self.picking {
+            PickMode::Polygon => im_str!("Finish"),
+            _ => im_str!("Draw"),
+        };
+        if ui.small_button(draw_label) {
+            self.picking = match self.picking {
+                PickMode::Polygon => PickMode::Nothing,
+                _ => {
+                    self.polygon.clear();
+                    self.use_polygon = true;
+                    PickMode::Polygon
+                }
+            };
+        }
+        draw_polygon_tt(ui, hovered);
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Clear")) {
+            self.polygon.clear();
+        }
+        ui.text(format!("{} vertices", self.polygon.len()));
+
+        ui.separator();
+        ui.with_item_width(width, || {
+            ui.input_text(im_str!("Series File"), &mut self.series_path)
+                .build();
+            ui.input_text(im_str!("Region File"), &mut self.region_path)
+                .build();
+        });
+        csv_path_tt(ui, hovered);
+
+        if ui.button(im_str!("Save Series"), (100.0, 30.0)) {
+            self.requested = Some(CsvExport::Series);
+        }
+        series_csv_tt(ui, hovered);
+        ui.same_line(0.0);
+        if ui.button(im_str!("Save Region"), (100.0, 30.0)) {
+            self.requested = Some(CsvExport::Region);
+        }
+        region_csv_tt(ui, hovered);
+
+        if !self.status.is_empty() {
+            ui.text(&self.status);
+        }
+    }
+}
+
+// Quotes a field if it would otherwise break the row apart
+fn csv_field(text: &str) -> String {
+    if text.contains(',') || text.contains('"') || text.contains('\n') {
+        format!("\"{}\"", text.replace("\"", "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn csv_value(value: Option<f32>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => String::new(),
+    }
+}
+
+fn create_csv(path: &str) -> Result<BufWriter<File>, Box<Error>> {
+    if let Some(parent) = Path::new(path).parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "lat,lon,time,value")?;
+    Ok(writer)
+}
+
+impl GlobalState {
+    // What the probe reads at its location in the shown step of its value,
+    // None without a location or data grid
+    pub fn probe_reading(&self) -> Option<String> {
+        let location = self.probe.location?;
+        let value = self.get_selected(self.probe.value as usize)?;
+        let grid = value.current_grid()?;
+        let label = value.current_label();
+        Some(match grid.sample(location).filter(|cell| cell.is_finite()) {
+            Some(cell) => format!("Value: {:.3} at {}", cell, label),
+            None => format!("No data at {}", label),
+        })
+    }
+
+    pub fn handle_probe_exports(&mut self) {
+        let export = match self.probe.requested.take() {
+            Some(export) => export,
+            None => return,
+        };
+        let index = self.probe.value as usize;
+        let result = match export {
+            CsvExport::Series => match self.probe.location {
+                Some(location) => {
+                    let path = self.probe.series_path.to_str().to_string();
+                    self.export_series_csv(index, location, &path)
+                }
+                None => Err("Pick a location first".into()),
+            },
+            CsvExport::Region => {
+                let path = self.probe.region_path.to_str().to_string();
+                let region = self.probe.region();
+                self.export_region_csv(index, &region, &path)
+            }
+        };
+
+        self.probe.status = match result {
+            Ok(rows) => format!("Saved {} rows", rows),
+            Err(error) => format!("Export failed: {}", error),
+        };
+    }
+
+    // Writes the value at a location for every time step, returning the rows written
+    pub fn export_series_csv(
+        &self,
+        index: usize,
+        location: [f32; 2],
+        path: &str,
+    ) -> Result<usize, Box<Error>> {
+        let value = self.get_selected(index).ok_or("No such variable")?;
+        if !value.has_data() {
+            return Err(format!("{} has no data grid", value.name.to_str()).into());
+        }
+
+        let mut writer = create_csv(path)?;
+        for (step, grid) in value.grids.iter().enumerate() {
+            let (column, row) = grid.cell_at(location);
+            let centre = grid.cell_centre(column, row);
+            writeln!(
+                writer,
+                "{},{},{},{}",
+                centre[0],
+                centre[1],
+                csv_field(&value.axis.label(step)),
+                csv_value(grid.get(column, row)),
+            )?;
+        }
+        Ok(value.grids.len())
+    }
+
+    // Writes every cell of the current time step inside the region
+    pub fn export_region_csv(
+        &self,
+        index: usize,
+        region: &Region,
+        path: &str,
+    ) -> Result<usize, Box<Error>> {
+        let value = self.get_selected(index).ok_or("No such variable")?;
+        let grid = match value.current_grid() {
+            Some(grid) => grid,
+            None => return Err(format!("{} has no data grid", value.name.to_str()).into()),
+        };
+        let label = csv_field(&value.current_label());
+
+        let mut writer = create_csv(path)?;
+        let mut rows = 0;
+        for (column, row, cell) in grid.cells() {
+            let centre = grid.cell_centre(column, row);
+            if !region.contains(centre) {
+                continue;
+            }
+            writeln!(
+                writer,
+                "{},{},{},{}",
+                centre[0],
+                centre[1],
+                label,
+                csv_value(cell),
+            )?;
+            rows += 1;
+        }
+        Ok(rows)
+    }
+}
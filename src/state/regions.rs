@@ -0,0 +1,538 @@
+use geo::{format_location, Region};
+use geojson::{self, Feature, Geometry};
+use glium::{backend::Facade, Rect, Surface};
+use imgui::{ImStr, ImString, Ui};
+use serde_json::Value as JsonValue;
+use state::line_render::{LineSet, LineStyle};
+use state::markers::{colour_to_hex, hex_to_colour};
+use state::tool_tips::*;
+use state::GlobalState;
+use stats::{summarise_where, Summary};
+use util::editable_string;
+
+const REGION_COLOURS: [[f32; 3]; 4] = [
+    [1.0, 0.85, 0.3],
+    [0.3, 0.9, 0.9],
+    [0.9, 0.45, 0.9],
+    [0.4, 0.9, 0.4],
+];
+const SKETCH_COLOUR: [f32; 4] = [1.0, 1.0, 1.0, 0.8];
+// Degrees the cursor moves before a lasso gains another vertex
+const LASSO_SPACING: f32 = 0.5;
+const PLOT_SIZE: (f32, f32) = (180.0, 80.0);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DrawMode {
+    Box,
+    Polygon,
+    Lasso,
+}
+
+impl DrawMode {
+    pub fn index(&self) -> i32 {
+        match self {
+            DrawMode::Box => 0,
+            DrawMode::Polygon => 1,
+            DrawMode::Lasso => 2,
+        }
+    }
+
+    pub fn from_index(index: i32) -> DrawMode {
+        match index {
+            1 => DrawMode::Polygon,
+            2 => DrawMode::Lasso,
+            _ => DrawMode::Box,
+        }
+    }
+}
+
+pub struct NamedRegion {
+    pub name: ImString,
+    pub region: Region,
+    pub colour: [f32; 3],
+    lines: Option<LineSet>,
+}
+
+impl NamedRegion {
+    pub fn new(name: &str, region: Region, colour: [f32; 3]) -> NamedRegion {
+        NamedRegion {
+            name: editable_string(name, 64),
+            region,
+            colour,
+            lines: None,
+        }
+    }
+
+    pub fn to_feature(&self) -> Feature {
+        let mut feature = Feature::new(Geometry::Polygon(vec![self.region.outline()]));
+        feature.properties.insert(
+            "name".to_string(),
+            JsonValue::String(self.name.to_str().to_string()),
+        );
+        feature.properties.insert(
+            "stroke".to_string(),
+            JsonValue::String(colour_to_hex(self.colour)),
+        );
+        feature
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum RegionFile {
+    Load,
+    Save,
+}
+
+// Statistics of the selected value over the selected region. The series
+// covers every time step so it is only rebuilt when either selection changes
+struct RegionCache {
+    value: usize,
+    region: usize,
+    step: usize,
+    summary: Option<Summary>,
+    series: Vec<Option<f32>>,
+}
+
+pub struct RegionSettings {
+    pub regions: Vec<NamedRegion>,
+    pub selected: i32,
+    pub value: i32,
+    pub mode: DrawMode,
+    pub show: bool,
+    // Vertices of the region being drawn as [lat, lon], None when not drawing
+    drawing: Option<Vec<[f32; 2]>>,
+    sketch: Option<LineSet>,
+    pub path: ImString,
+    pub status: String,
+    requested: Option<RegionFile>,
+    // Set whenever regions change so their outlines are rebuilt
+    pub dirty: bool,
+    cache: Option<RegionCache>,
+}
+
+impl RegionSettings {
+    pub fn new() -> RegionSettings {
+        RegionSettings {
+            regions: vec![],
+            selected: 0,
+            value: 0,
+            mode: DrawMode::Box,
+            show: true,
+            drawing: None,
+            sketch: None,
+            path: editable_string("regions.geojson", 256),
+            status: String::new(),
+            requested: None,
+            dirty: true,
+            cache: None,
+        }
+    }
+
+    pub fn add(&mut self, region: Region) {
+        let count = self.regions.len();
+        let name = format!("Region {}", count + 1);
+        let colour = REGION_COLOURS[count % REGION_COLOURS.len()];
+        self.regions.push(NamedRegion::new(&name, region, colour));
+        self.selected = count as i32;
+        self.changed();
+    }
+
+    pub fn changed(&mut self) {
+        self.dirty = true;
+        self.cache = None;
+    }
+
+    // Adds the outer ring of every polygon, returning how many were added
+    pub fn add_features(&mut self, features: &[Feature]) -> usize {
+        let before = self.regions.len();
+        for feature in features {
+            let rings: Vec<Vec<[f32; 2]>> = match feature.geometry {
+                Geometry::Polygon(ref rings) => rings.iter().take(1).cloned().collect(),
+                Geometry::MultiPolygon(ref polygons) => polygons
+                    .iter()
+                    .filter_map(|rings| rings.first())
+                    .cloned()
+                    .collect(),
+                _ => continue,
+            };
+            let name = feature.property_str("name");
+            let colour = feature.property_str("stroke").and_then(hex_to_colour);
+            for mut ring in rings {
+                // GeoJSON rings repeat their first vertex at the end
+                if ring.len() > 1 && ring.first() == ring.last() {
+                    ring.pop();
+                }
+                if ring.len() < 3 {
+                    continue;
+                }
+                self.add(Region::Polygon(ring));
+                let region = self.regions.last_mut().unwrap();
+                if let Some(name) = name {
+                    region.name = editable_string(name, 64);
+                }
+                if let Some(colour) = colour {
+                    region.colour = colour;
+                }
+            }
+        }
+        self.regions.len() - before
+    }
+
+    // Box and lasso regions are drawn by dragging, which stops the camera turning
+    pub fn takes_drags(&self) -> bool {
+        self.drawing.is_some() && self.mode != DrawMode::Polygon
+    }
+
+    pub fn takes_clicks(&self) -> bool {
+        self.drawing.is_some() && self.mode == DrawMode::Polygon
+    }
+
+    pub fn add_vertex(&mut self, location: [f32; 2]) {
+        if let Some(ref mut points) = self.drawing {
+            points.push(location);
+        }
+    }
+
+    // Follows the cursor while the button is held, location is None when it is off the globe
+    pub fn drag(&mut self, location: Option<[f32; 2]>) {
+        let (points, location) = match (self.drawing.as_mut(), location) {
+            (Some(points), Some(location)) => (points, location),
+            _ => return,
+        };
+        match self.mode {
+            DrawMode::Box => {
+                points.truncate(1);
+                if points.is_empty() {
+                    points.push(location);
+                }
+                points.push(location);
+            }
+            DrawMode::Lasso => {
+                let far_enough = points.last().map_or(true, |last| {
+                    (last[0] - location[0]).abs().max((last[1] - location[1]).abs())
+                        >= LASSO_SPACING
+                });
+                if far_enough {
+                    points.push(location);
+                }
+            }
+            DrawMode::Polygon => (),
+        }
+    }
+
+    // Turns what has been drawn into a region, anything too small is dropped
+    pub fn finish(&mut self) {
+        let points = match self.drawing.take() {
+            Some(points) => points,
+            None => return,
+        };
+        let region = match self.mode {
+            DrawMode::Box if points.len() == 2 && points[0] != points[1] => {
+                Region::from_corners(points[0], points[1])
+            }
+            DrawMode::Polygon | DrawMode::Lasso if points.len() >= 3 => Region::Polygon(points),
+            // Keep drawing rather than ending on a slip of the mouse
+            DrawMode::Polygon => {
+                self.drawing = Some(points);
+                return;
+            }
+            _ => {
+                self.drawing = Some(vec![]);
+                return;
+            }
+        };
+        self.add(region);
+    }
+
+    fn drawing_lines(&self) -> Vec<Vec<[f32; 2]>> {
+        match (self.mode, self.drawing.as_ref()) {
+            (DrawMode::Box, Some(points)) if points.len() == 2 => {
+                vec![Region::from_corners(points[0], points[1]).outline()]
+            }
+            (_, Some(points)) => vec![points.clone()],
+            (_, None) => vec![],
+        }
+    }
+
+    pub fn build_region_ui(&mut self, ui: &Ui, width: f32, names: &[&ImStr], hovered: bool) {
+        if !ui.collapsing_header(im_str!("Regions")).build() {
+            return;
+        }
+        ui.checkbox(im_str!("Show Regions"), &mut self.show);
+        let modes = [im_str!("Box"), im_str!("Polygon"), im_str!("Lasso")];
+        let mut mode = self.mode.index();
+        ui.with_item_width(width, || {
+            if ui.combo(im_str!("Shape"), &mut mode, &modes, 3) {
+                self.mode = DrawMode::from_index(mode);
+                self.drawing = None;
+            }
+        });
+        region_shape_tt(ui, hovered);
+
+        match self.drawing {
+            Some(_) => {
+                if ui.button(im_str!("Cancel"), (100.0, 30.0)) {
+                    self.drawing = None;
+                }
+                if self.mode == DrawMode::Polygon {
+                    ui.same_line(0.0);
+                    if ui.button(im_str!("Finish"), (100.0, 30.0)) {
+                        self.finish();
+                    }
+                }
+                ui.text(match self.mode {
+                    DrawMode::Box => "Drag across a globe",
+                    DrawMode::Polygon => "Click a globe to add vertices",
+                    DrawMode::Lasso => "Drag around an area of a globe",
+                });
+            }
+            None => {
+                if ui.button(im_str!("Draw Region"), (100.0, 30.0)) {
+                    self.drawing = Some(vec![]);
+                }
+            }
+        }
+
+        let mut remove = None;
+        let mut edited = false;
+        for (i, region) in self.regions.iter_mut().enumerate() {
+            ui.separator();
+            let select = ImString::new(format!("##Select Region {}", i));
+            ui.radio_button(&select, &mut self.selected, i as i32);
+            ui.same_line(0.0);
+            ui.with_item_width(width - 30.0, || {
+                let name = ImString::new(format!("Name##Region {}", i));
+                ui.input_text(&name, &mut region.name).build();
+                let colour = ImString::new(format!("Colour##Region {}", i));
+                if ui.color_edit(&colour, &mut region.colour).build() {
+                    edited = true;
+                }
+            });
+            let delete = ImString::new(format!("Remove##Region {}", i));
+            if ui.small_button(&delete) {
+                remove = Some(i);
+            }
+        }
+        if let Some(i) = remove {
+            self.regions.remove(i);
+            self.selected = self.selected.min(self.regions.len() as i32 - 1).max(0);
+            self.changed();
+        } else if edited {
+            self.dirty = true;
+        }
+
+        ui.separator();
+        ui.with_item_width(width, || {
+            ui.input_text(im_str!("Region File"), &mut self.path).build();
+        });
+        region_file_tt(ui, hovered);
+        if ui.button(im_str!("Load Regions"), (100.0, 30.0)) {
+            self.requested = Some(RegionFile::Load);
+        }
+        ui.same_line(0.0);
+        if ui.button(im_str!("Save Regions"), (100.0, 30.0)) {
+            self.requested = Some(RegionFile::Save);
+        }
+        if !self.status.is_empty() {
+            ui.text(&self.status);
+        }
+
+        if self.regions.is_empty() {
+            return;
+        }
+        ui.separator();
+        ui.with_item_width(width, || {
+            ui.combo(im_str!("Variable##Region"), &mut self.value, names, 5);
+        });
+        self.build_region_stats(ui, hovered);
+    }
+
+    fn build_region_stats(&self, ui: &Ui, hovered: bool) {
+        let cache = match self.cache.as_ref() {
+            Some(cache) => cache,
+            None => {
+                ui.text("This variable has no data grid");
+                return;
+            }
+        };
+        match cache.summary {
+            Some(summary) => {
+                ui.text(format!("Mean: {:.3}", summary.mean));
+                area_weighted_tt(ui, hovered);
+                ui.text(format!("Standard Deviation: {:.3}", summary.std_dev));
+                ui.text(format!(
+                    "Min: {:.3} at {}",
+                    summary.min.value,
+                    format_location(summary.min.location)
+                ));
+                ui.text(format!(
+                    "Max: {:.3} at {}",
+                    summary.max.value,
+                    format_location(summary.max.location)
+                ));
+                ui.text(format!("Coverage: {:.1}%", summary.coverage * 100.0));
+            }
+            None => ui.text("No cells with data in the region"),
+        }
+
+        if cache.series.len() < 2 {
+            return;
+        }
+        // Missing steps are left at the bottom of the plot
+        let samples: Vec<f32> = cache.series.iter().filter_map(|sample| *sample).collect();
+        let min = samples.iter().cloned().fold(::std::f32::MAX, f32::min);
+        let max = samples.iter().cloned().fold(::std::f32::MIN, f32::max);
+        if min > max {
+            return;
+        }
+        let plotted: Vec<f32> = cache.series.iter().map(|sample| sample.unwrap_or(min)).collect();
+        ui.text(format!("Region Mean {:.2} to {:.2}", min, max));
+        ui.plot_lines(im_str!("##Region Series"), &plotted)
+            .graph_size(PLOT_SIZE)
+            .scale_min(min)
+            .scale_max(max)
+            .build();
+        region_series_tt(ui, hovered);
+        let missing = cache.series.len() - samples.len();
+        if missing > 0 {
+            ui.text(format!("{} steps have no data", missing));
+        }
+    }
+}
+
+impl GlobalState {
+    pub fn handle_region_drag(&mut self, pressed: bool, aspect: f32) {
+        if !pressed {
+            if self.regions.drawing.as_ref().map_or(false, |points| !points.is_empty()) {
+                self.regions.finish();
+            }
+            return;
+        }
+        let location = self
+            .pick_location(self.mouse_vars.position, aspect)
+            .map(|(_, location)| location);
+        self.regions.drag(location);
+    }
+
+    pub fn handle_region_files(&mut self) {
+        let file = match self.regions.requested.take() {
+            Some(file) => file,
+            None => return,
+        };
+        let path = self.regions.path.to_str().to_string();
+        self.regions.status = match file {
+            RegionFile::Load => match geojson::load(&path) {
+                Ok(features) => {
+                    format!("Loaded {} regions", self.regions.add_features(&features))
+                }
+                Err(error) => format!("Load failed: {}", error),
+            },
+            RegionFile::Save => {
+                let features: Vec<Feature> =
+                    self.regions.regions.iter().map(|region| region.to_feature()).collect();
+                match geojson::save(&path, &features) {
+                    Ok(()) => format!("Saved {} regions", features.len()),
+                    Err(error) => format!("Save failed: {}", error),
+                }
+            }
+        };
+    }
+
+    pub fn update_regions<F: Facade>(&mut self, display: &F) {
+        if self.regions.dirty {
+            for region in &mut self.regions.regions {
+                region.lines = LineSet::new(display, &[region.region.outline()]);
+            }
+            self.regions.dirty = false;
+        }
+        self.regions.sketch = LineSet::new(display, &self.regions.drawing_lines());
+        self.update_region_stats();
+    }
+
+    fn update_region_stats(&mut self) {
+        let (value_index, region_index) =
+            (self.regions.value as usize, self.regions.selected as usize);
+        let previous = self.regions.cache.take();
+        let region = match self.regions.regions.get(region_index) {
+            Some(region) => &region.region,
+            None => return,
+        };
+        let value = match self.values.get(value_index) {
+            Some(value) if value.has_data() => value,
+            _ => return,
+        };
+        let step = value.current_step();
+        let include = |location: [f32; 2]| region.contains(location);
+        let land_mask = self.land_mask.as_ref();
+
+        let cache = match previous {
+            Some(cache) if cache.value == value_index && cache.region == region_index => {
+                match cache.step == step {
+                    true => cache,
+                    false => RegionCache {
+                        step,
+                        summary: value
+                            .current_grid()
+                            .and_then(|grid| summarise_where(grid, land_mask, &include)),
+                        ..cache
+                    },
+                }
+            }
+            _ => {
+                let summaries: Vec<Option<Summary>> = value
+                    .grids
+                    .iter()
+                    .map(|grid| summarise_where(grid, land_mask, &include))
+                    .collect();
+                RegionCache {
+                    value: value_index,
+                    region: region_index,
+                    step,
+                    summary: summaries.get(step).and_then(|summary| *summary),
+                    series: summaries
+                        .iter()
+                        .map(|summary| summary.map(|summary| summary.mean))
+                        .collect(),
+                }
+            }
+        };
+        self.regions.cache = Some(cache);
+    }
+
+    pub fn render_regions<T: Surface + ?Sized>(&self, target: &mut T, view: [[f32; 4]; 4], rect: Rect) {
+        let settings = &self.regions;
+        if settings.show {
+            for (i, region) in settings.regions.iter().enumerate() {
+                let lines = match region.lines {
+                    Some(ref lines) => lines,
+                    None => continue,
+                };
+                let width = match i as i32 == settings.selected {
+                    true => 3.0,
+                    false => 1.5,
+                };
+                let [r, g, b] = region.colour;
+                self.line_renderer.render(
+                    target,
+                    lines,
+                    view,
+                    &self.height_map,
+                    self.variables.height,
+                    LineStyle::new([r, g, b, 1.0], width),
+                    rect,
+                );
+            }
+        }
+        if let Some(ref lines) = settings.sketch {
+            self.line_renderer.render(
+                target,
+                lines,
+                view,
+                &self.height_map,
+                self.variables.height,
+                LineStyle::new(SKETCH_COLOUR, 2.0),
+                rect,
+            );
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use geo::Region;
+use state::markers::Marker;
+use state::regions::NamedRegion;
+use state::GlobalState;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use toml;
+use util::editable_string;
+
+// What is kept between runs of the program
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Session {
+    pub markers: Vec<MarkerRecord>,
+    pub regions: Vec<RegionRecord>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MarkerRecord {
+    pub label: String,
+    pub location: [f32; 2],
+    pub colour: [f32; 3],
+}
+
+impl<'a> From<&'a Marker> for MarkerRecord {
+    fn from(marker: &Marker) -> MarkerRecord {
+        MarkerRecord {
+            label: marker.label.to_str().to_string(),
+            location: marker.location,
+            colour: marker.colour,
+        }
+    }
+}
+
+impl MarkerRecord {
+    pub fn to_marker(&self) -> Marker {
+        Marker {
+            label: editable_string(&self.label, 64),
+            location: self.location,
+            colour: self.colour,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RegionRecord {
+    pub name: String,
+    pub colour: [f32; 3],
+    // South, north, west and east for boxes
+    pub bounds: Option<[f32; 4]>,
+    // [lat, lon] vertices for polygons
+    pub vertices: Vec<[f32; 2]>,
+}
+
+impl<'a> From<&'a NamedRegion> for RegionRecord {
+    fn from(region: &NamedRegion) -> RegionRecord {
+        let (bounds, vertices) = match region.region {
+            Region::Bounds {
+                south,
+                north,
+                west,
+                east,
+            } => (Some([south, north, west, east]), vec![]),
+            Region::Polygon(ref vertices) => (None, vertices.clone()),
+        };
+        RegionRecord {
+            name: region.name.to_str().to_string(),
+            colour: region.colour,
+            bounds,
+            vertices,
+        }
+    }
+}
+
+impl RegionRecord {
+    pub fn to_region(&self) -> NamedRegion {
+        let region = match self.bounds {
+            Some([south, north, west, east]) => Region::Bounds {
+                south,
+                north,
+                west,
+                east,
+            },
+            None => Region::Polygon(self.vertices.clone()),
+        };
+        NamedRegion::new(&self.name, region, self.colour)
+    }
+}
+
+impl GlobalState {
+    pub fn session(&self) -> Session {
+        Session {
+            markers: self.markers.markers.iter().map(MarkerRecord::from).collect(),
+            regions: self.regions.regions.iter().map(RegionRecord::from).collect(),
+        }
+    }
+
+    pub fn apply_session(&mut self, session: &Session) {
+        self.markers.markers = session.markers.iter().map(|record| record.to_marker()).collect();
+        self.markers.dirty = true;
+        self.regions.regions = session.regions.iter().map(|record| record.to_region()).collect();
+        self.regions.changed();
+    }
+
+    pub fn save_session(&self, path: impl AsRef<Path>) -> Result<(), Box<Error>> {
+        let text = toml::to_string(&self.session())?;
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn load_session(&mut self, path: impl AsRef<Path>) -> Result<(), Box<Error>> {
+        let text = fs::read_to_string(path)?;
+        let session: Session = toml::from_str(&text)?;
+        self.apply_session(&session);
+        Ok(())
+    }
+}
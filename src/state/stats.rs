@@ -0,0 +1,227 @@
+use geo::format_location;
+use imgui::{ImGuiCond, ImStr, Ui};
+use state::tool_tips::*;
+use state::GlobalState;
+use stats::{summarise_where, zonal_means_where, Summary, SurfaceFilter};
+
+const PLOT_SIZE: (f32, f32) = (380.0, 160.0);
+const ZONAL_COLOUR: [f32; 4] = [0.3, 0.7, 1.0, 1.0];
+const AXIS_COLOUR: [f32; 4] = [1.0, 1.0, 1.0, 0.3];
+
+// Statistics of one value's shown time step, kept until any of them changes
+struct StatsCache {
+    value: usize,
+    step: usize,
+    surface: SurfaceFilter,
+    summary: Option<Summary>,
+    zonal: Vec<(f32, Option<f32>)>,
+}
+
+pub struct StatsPanel {
+    pub open: bool,
+    pub value: i32,
+    // Restricts every statistic to land or ocean cells
+    pub surface: SurfaceFilter,
+    cache: Option<StatsCache>,
+}
+
+impl StatsPanel {
+    pub fn new() -> StatsPanel {
+        StatsPanel {
+            open: false,
+            value: 0,
+            surface: SurfaceFilter::All,
+            cache: None,
+        }
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.cache = None;
+    }
+}
+
+fn format_mean(mean: Option<f32>) -> String {
+    match mean {
+        Some(mean) => format!("{:.3}", mean),
+        None => "No data".to_string(),
+    }
+}
+
+impl GlobalState {
+    // Recomputes the statistics when the chosen value, its time step or the
+    // surface changed
+    fn update_stats(&mut self) {
+        let index = self.stats.value as usize;
+        let value = match self.get_selected(index) {
+            Some(value) => value,
+            None => {
+                self.stats.cache = None;
+                return;
+            }
+        };
+        let step = value.current_step();
+        let surface = self.stats.surface;
+        let up_to_date = self.stats.cache.as_ref().map_or(false, |cache| {
+            cache.value == index && cache.step == step && cache.surface == surface
+        });
+        if up_to_date {
+            return;
+        }
+        let land_mask = self.land_mask.as_ref();
+        let include = |location| surface.includes(land_mask, location);
+        let (summary, zonal) = match value.current_grid() {
+            Some(grid) => (
+                summarise_where(grid, land_mask, &include),
+                zonal_means_where(grid, &include),
+            ),
+            None => (None, vec![]),
+        };
+        self.stats.cache = Some(StatsCache {
+            value: index,
+            step,
+            surface,
+            summary,
+            zonal,
+        });
+    }
+
+    pub fn build_stats_window(&mut self, ui: &Ui) {
+        if !self.stats.open {
+            return;
+        }
+        self.update_stats();
+        let mut opened = self.stats.open;
+        let mut selected = self.stats.value;
+        let mut surface = self.stats.surface.index();
+        let names: Vec<&ImStr> = self.values.iter().map(|value| value.name.as_ref()).collect();
+        let position = (self.menu_width + 10.0, 420.0);
+        let hovered = self.mouse_vars.hovered;
+
+        ui.window(im_str!("Statistics"))
+            .size((PLOT_SIZE.0 + 40.0, PLOT_SIZE.1 + 260.0), ImGuiCond::FirstUseEver)
+            .position(position, ImGuiCond::FirstUseEver)
+            .collapsible(true)
+            .opened(&mut opened)
+            .build(|| {
+                ui.with_item_width(PLOT_SIZE.0 * 0.6, || {
+                    ui.combo(im_str!("Variable"), &mut selected, &names, 5);
+                    let surfaces = [
+                        im_str!("Everywhere"),
+                        im_str!("Land Only"),
+                        im_str!("Ocean Only"),
+                    ];
+                    ui.combo(im_str!("Cells"), &mut surface, &surfaces, 3);
+                });
+                stats_surface_tt(ui, hovered);
+                self.build_stats(ui, hovered);
+            });
+        self.stats.value = selected;
+        self.stats.surface = SurfaceFilter::from_index(surface);
+        self.stats.open = opened;
+    }
+
+    fn build_stats(&self, ui: &Ui, hovered: bool) {
+        let value = match self.get_selected(self.stats.value as usize) {
+            Some(value) => value,
+            None => return,
+        };
+        ui.text(format!("Shown: {}", value.current_label()));
+        let cache = match self.stats.cache.as_ref() {
+            Some(cache) => cache,
+            None => return,
+        };
+        let summary = match cache.summary {
+            Some(summary) => summary,
+            None if value.has_data() => {
+                ui.text("No cells have data in this time step");
+                return;
+            }
+            None => {
+                ui.text("This variable has no data grid");
+                return;
+            }
+        };
+
+        let mean_label = match self.stats.surface {
+            SurfaceFilter::All => "Global Mean",
+            SurfaceFilter::Land => "Land Mean",
+            SurfaceFilter::Ocean => "Ocean Mean",
+        };
+        ui.text(format!("{}: {:.3}", mean_label, summary.mean));
+        area_weighted_tt(ui, hovered);
+        ui.text(format!("Standard Deviation: {:.3}", summary.std_dev));
+        ui.text(format!(
+            "Min: {:.3} at {}",
+            summary.min.value,
+            format_location(summary.min.location)
+        ));
+        ui.text(format!(
+            "Max: {:.3} at {}",
+            summary.max.value,
+            format_location(summary.max.location)
+        ));
+        ui.text(format!("Coverage: {:.1}%", summary.coverage * 100.0));
+        match self.land_mask {
+            Some(_) => {
+                ui.text(format!("Land Mean: {}", format_mean(summary.land_mean)));
+                ui.text(format!("Ocean Mean: {}", format_mean(summary.ocean_mean)));
+                land_mask_tt(ui, hovered);
+            }
+            None => ui.text("No land mask loaded"),
+        }
+
+        ui.separator();
+        self.build_zonal_plot(ui, &cache.zonal, hovered);
+    }
+
+    // Latitude runs along the x axis from south to north
+    fn build_zonal_plot(&self, ui: &Ui, zonal: &[(f32, Option<f32>)], hovered: bool) {
+        let (mut min, mut max) = (::std::f32::MAX, ::std::f32::MIN);
+        for mean in zonal.iter().filter_map(|(_, mean)| *mean) {
+            min = min.min(mean);
+            max = max.max(mean);
+        }
+        if min > max {
+            return;
+        }
+        if min == max {
+            min -= 1.0;
+            max += 1.0;
+        }
+
+        ui.text("Zonal Mean");
+        ui.text(format!("{:.2} to {:.2}", min, max));
+        let origin = ui.get_cursor_screen_pos();
+        ui.plot_lines(im_str!("##Zonal Plot"), &[])
+            .graph_size(PLOT_SIZE)
+            .scale_min(min)
+            .scale_max(max)
+            .build();
+        zonal_plot_tt(ui, hovered);
+
+        let to_screen = |lat: f32, mean: f32| {
+            let x = (lat + 90.0) / 180.0;
+            let y = (mean - min) / (max - min);
+            (
+                origin.0 + x * PLOT_SIZE.0,
+                origin.1 + (1.0 - y) * PLOT_SIZE.1,
+            )
+        };
+
+        {
+            let draw_list = ui.get_window_draw_list();
+            draw_list
+                .add_line(to_screen(0.0, min), to_screen(0.0, max), AXIS_COLOUR)
+                .build();
+            let mut previous = None;
+            for (lat, mean) in zonal {
+                let point = mean.map(|mean| to_screen(*lat, mean));
+                if let (Some(from), Some(to)) = (previous, point) {
+                    draw_list.add_line(from, to, ZONAL_COLOUR).thickness(2.0).build();
+                }
+                previous = point;
+            }
+        }
+        ui.text("90S                  Equator                  90N");
+    }
+}
@@ -0,0 +1,190 @@
+use util::wrapf32;
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+#[derive(Clone, Debug)]
+pub enum TimeAxis {
+    // Steps without a calendar, labelled by their index
+    Steps,
+    // Twelve monthly means of an average year
+    Climatology,
+    // Consecutive months, month is zero based
+    Monthly { year: i32, month: u32 },
+    Yearly { year: i32 },
+    Labels(Vec<String>),
+}
+
+impl TimeAxis {
+    // How many months of the global timeline one step covers
+    pub fn step_months(&self) -> f32 {
+        match self {
+            TimeAxis::Yearly { .. } => 12.0,
+            _ => 1.0,
+        }
+    }
+
+    // The absolute month (year * 12 + month) of the first step, None for axes
+    // without a calendar
+    pub fn start_month(&self) -> Option<f32> {
+        match self {
+            TimeAxis::Monthly { year, month } => Some((year * 12) as f32 + *month as f32),
+            TimeAxis::Yearly { year } => Some((year * 12) as f32),
+            _ => None,
+        }
+    }
+
+    // The step at an absolute month. Climatologies use the month of the year
+    // and axes without a calendar count steps from the timeline origin
+    pub fn step_at(&self, month: f32, origin: f32) -> f32 {
+        match (self, self.start_month()) {
+            (_, Some(start)) => (month - start) / self.step_months(),
+            (TimeAxis::Climatology, None) => wrapf32(month, 12.0),
+            _ => month - origin,
+        }
+    }
+
+    // The absolute month of a step, climatology steps land in the first year
+    // after the origin
+    pub fn month_at(&self, step: f32, origin: f32) -> f32 {
+        match (self, self.start_month()) {
+            (_, Some(start)) => start + step * self.step_months(),
+            (TimeAxis::Climatology, None) => origin + wrapf32(step - origin, 12.0),
+            _ => origin + step,
+        }
+    }
+
+    pub fn label(&self, step: usize) -> String {
+        match self {
+            TimeAxis::Steps => format!("Step {}", step + 1),
+            TimeAxis::Climatology => MONTHS[step % 12].to_string(),
+            TimeAxis::Monthly { year, month } => {
+                let months = *month as usize + step;
+                format!("{} {}", MONTHS[months % 12], year + (months / 12) as i32)
+            }
+            TimeAxis::Yearly { year } => format!("{}", year + step as i32),
+            TimeAxis::Labels(labels) => match labels.get(step) {
+                Some(label) => label.clone(),
+                None => format!("Step {}", step + 1),
+            },
+        }
+    }
+
+    pub fn is_cyclic(&self) -> bool {
+        match self {
+            TimeAxis::Climatology => true,
+            _ => false,
+        }
+    }
+
+    // Imgui treats the display format as printf input so any '%' in a label
+    // has to be escaped
+    pub fn display_format(&self, step: usize) -> String {
+        self.label(step).replace("%", "%%")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PlayMode {
+    Loop,
+    Bounce,
+    Once,
+}
+
+impl PlayMode {
+    pub fn from_index(index: i32) -> PlayMode {
+        match index {
+            1 => PlayMode::Bounce,
+            2 => PlayMode::Once,
+            _ => PlayMode::Loop,
+        }
+    }
+
+    pub fn index(&self) -> i32 {
+        match self {
+            PlayMode::Loop => 0,
+            PlayMode::Bounce => 1,
+            PlayMode::Once => 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use state::time::TimeAxis;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    #[test]
+    fn start_month_counts_from_year_zero() {
+        assert_eq!(TimeAxis::Monthly { year: 2000, month: 6 }.start_month(), Some(24006.0));
+        assert_eq!(TimeAxis::Yearly { year: 2000 }.start_month(), Some(24000.0));
+        assert_eq!(TimeAxis::Climatology.start_month(), None);
+        assert_eq!(TimeAxis::Steps.start_month(), None);
+    }
+
+    #[test]
+    fn offset_monthly_axes_meet_on_the_same_date() {
+        let july = TimeAxis::Monthly { year: 2000, month: 6 };
+        let january = TimeAxis::Monthly { year: 2001, month: 0 };
+        let origin = 24006.0;
+        // January 2001
+        let month = origin + 6.0;
+
+        assert!(close(july.step_at(month, origin), 6.0));
+        assert!(close(january.step_at(month, origin), 0.0));
+        assert_eq!(july.label(6), january.label(0));
+    }
+
+    #[test]
+    fn climatology_follows_month_of_year() {
+        let series = TimeAxis::Monthly { year: 1990, month: 6 };
+        let origin = series.start_month().unwrap();
+
+        let step = TimeAxis::Climatology.step_at(origin, origin);
+        assert!(close(step, 6.0));
+        assert!(series.label(0).starts_with(&TimeAxis::Climatology.label(step as usize)));
+
+        let step = TimeAxis::Climatology.step_at(origin + 9.0, origin);
+        assert!(close(step, 3.0));
+        assert!(series.label(9).starts_with(&TimeAxis::Climatology.label(step as usize)));
+    }
+
+    #[test]
+    fn yearly_steps_cover_twelve_months() {
+        let yearly = TimeAxis::Yearly { year: 2001 };
+        let origin = TimeAxis::Monthly { year: 2000, month: 0 }.start_month().unwrap();
+
+        assert!(close(yearly.step_at(origin + 18.0, origin), 0.5));
+        assert!(close(yearly.month_at(2.0, origin), origin + 36.0));
+    }
+
+    #[test]
+    fn month_at_inverts_step_at() {
+        let origin = 24003.0;
+        let axes = [
+            TimeAxis::Monthly { year: 2000, month: 5 },
+            TimeAxis::Yearly { year: 2001 },
+            TimeAxis::Climatology,
+            TimeAxis::Steps,
+        ];
+        for axis in &axes {
+            let month = axis.month_at(4.0, origin);
+            assert!(close(axis.step_at(month, origin), 4.0));
+        }
+    }
+}
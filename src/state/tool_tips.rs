@@ -67,6 +67,22 @@ pub fn drag_speed_tt(ui: &Ui, show: bool) {
     );
 }
 
+pub fn drag_binding_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Changes what dragging with this mouse button does\nTilt / Roll tilts the view towards the horizon",
+    );
+}
+
+pub fn reset_view_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Removes any tilt, roll or panning from every globe",
+    );
+}
+
 pub fn time_multi_tt(ui: &Ui, show: bool) {
     tool_tip_with_text(
         ui,
@@ -103,7 +119,39 @@ pub fn time_slider_tt(ui: &Ui, show: bool) {
     tool_tip_with_text(
         ui,
         show,
-        "Changes the month selected for this variable",
+        "Changes the time step selected for this variable",
+    );
+}
+
+pub fn snap_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Moves the time slider one whole time step at a time",
+    );
+}
+
+pub fn link_value_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Keeps this variable on the shared timeline\nunlinked variables can be played separately",
+    );
+}
+
+pub fn sync_time_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Plays every linked variable from one clock so\nside by side globes always show the same month",
+    );
+}
+
+pub fn timeline_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Changes the month shown by every linked variable",
     );
 }
 
@@ -111,7 +159,7 @@ pub fn play_tt(ui: &Ui, show: bool) {
     tool_tip_with_text(
         ui,
         show,
-        "Loops this variable through each month",
+        "Plays this variable through each time step",
     );
 }
 
@@ -119,6 +167,466 @@ pub fn pause_tt(ui: &Ui, show: bool) {
     tool_tip_with_text(
         ui,
         show,
-        "Stop looping this variable through each month",
+        "Stop playing this variable through each time step",
+    );
+}
+
+pub fn step_back_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Moves back to the previous time step",
+    );
+}
+
+pub fn step_forward_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Moves on to the next time step",
+    );
+}
+
+pub fn play_mode_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Loop starts again from the first step\nBounce plays backwards from the last step\nOnce stops at the last step",
+    );
+}
+
+pub fn steps_per_second_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Changes how many time steps are played each second",
+    );
+}
+
+pub fn cyclic_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Treats the last time step as leading back into the first\nsuch as the months of an average year",
+    );
+}
+
+pub fn smooth_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Blends between time steps instead of jumping from one to the next",
+    );
+}
+
+pub fn export_path_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Where the image is saved, relative to the working directory",
+    );
+}
+
+pub fn export_target_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Captures either every globe as laid out on screen or a single globe",
+    );
+}
+
+pub fn export_overlay_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Menus are never captured, these add the range and variable name",
+    );
+}
+
+pub fn animation_prefix_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Frames are saved as prefix_0000.png and the animation as prefix.gif",
+    );
+}
+
+pub fn steps_per_frame_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "How many time steps each frame moves forward\nor months when time is synchronised",
+    );
+}
+
+pub fn animation_rotation_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Degrees the globes turn between frames",
+    );
+}
+
+pub fn gif_delay_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "How long each GIF frame is shown in hundredths of a second",
+    );
+}
+
+pub fn pick_point_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Click a globe to choose the location to probe",
+    );
+}
+
+pub fn region_bounds_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "South and north, then west and east bounds in degrees\na west bound past the east bound crosses the date line",
+    );
+}
+
+pub fn draw_polygon_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Click a globe to add polygon vertices, press Finish when done",
+    );
+}
+
+pub fn csv_path_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Files are written as lat,lon,time,value\nempty values mark missing data",
     );
-}
\ No newline at end of file
+}
+
+pub fn series_csv_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Saves the value at the picked location for every time step",
+    );
+}
+
+pub fn region_csv_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Saves every grid cell inside the region at the current time step",
+    );
+}
+
+pub fn plot_series_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Opens a plot of the variable over time at the picked location",
+    );
+}
+
+pub fn series_plot_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "The red line marks the time step shown on the globes",
+    );
+}
+
+pub fn pin_series_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Keeps this location on the plot so other locations\ncan be picked and compared against it",
+    );
+}
+
+pub fn fly_to_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Turns the globes to face this marker",
+    );
+}
+
+pub fn marker_file_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "A GeoJSON file of points, loading adds to the current markers\nnames and marker-color properties are kept",
+    );
+}
+
+pub fn graticule_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Draws lines of latitude and longitude over this viewport's globe",
+    );
+}
+
+pub fn graticule_spacing_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Degrees between neighbouring grid lines",
+    );
+}
+
+pub fn highlight_lines_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Draws the equator, tropics and polar circles in their own colour",
+    );
+}
+
+pub fn raster_overlay_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Darkens the globe with the outline image, turn off when using vector overlays",
+    );
+}
+
+pub fn vector_file_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "A shapefile (.shp) or GeoJSON file of lines or polygons\nsuch as coastlines, borders or lakes",
+    );
+}
+
+pub fn contours_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Draws isolines of this viewport's variable at the current time step",
+    );
+}
+
+pub fn contour_interval_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Draws a contour at every multiple of the interval inside the shown range\nan interval giving more than 200 contours is widened to fit",
+    );
+}
+
+pub fn contour_list_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Draws contours only at the levels typed in, separated by commas",
+    );
+}
+
+pub fn arrow_spacing_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Degrees between arrows, the strongest flow draws the longest arrow",
+    );
+}
+
+pub fn particle_speed_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Degrees a particle moves each second for every unit of speed",
+    );
+}
+
+pub fn colour_magnitude_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Colours the globe by the strength of the flow\nturn off to see the world map under the vectors",
+    );
+}
+
+pub fn histogram_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "How the shown time step's values are spread\ndrag near either line to move that end of the range",
+    );
+}
+
+pub fn fit_data_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(ui, show, "Sets the range to the smallest and largest values shown");
+}
+
+pub fn percentile_range_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Sets the range to the 2nd and 98th percentiles\nso a few extreme cells don't wash out the colours",
+    );
+}
+
+pub fn symmetric_range_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(ui, show, "Centres the range on zero, wide enough to cover every value");
+}
+
+pub fn region_shape_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Boxes and lassos are drawn by dragging on a globe\npolygons by clicking each vertex then pressing Finish",
+    );
+}
+
+pub fn region_file_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(ui, show, "Regions are loaded from and saved to GeoJSON polygons");
+}
+
+pub fn region_series_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "The area-weighted mean over the selected region for every time step",
+    );
+}
+
+pub fn threshold_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Picks out the cells above, below, between or outside limits\nthey can be hatched, outlined, or everything else greyed out",
+    );
+}
+
+pub fn threshold_area_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "The share of the whole globe's area, cells without data never meet the condition",
+    );
+}
+
+pub fn statistics_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Opens a window of statistics for the shown time step of a variable",
+    );
+}
+
+pub fn area_weighted_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Cells are weighted by the cosine of their latitude\nso each counts by the area of the globe it covers",
+    );
+}
+
+pub fn land_mask_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Land and ocean are told apart by the black and white overlay map\nwhite is land and black is ocean",
+    );
+}
+
+pub fn zonal_plot_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "The mean of every row of cells from the south pole to the north pole",
+    );
+}
+
+pub fn formula_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Values are named in lower case with spaces and symbols as _, or in `backticks`\nuse + - * / ^, comparisons giving 1 or 0, nan and pi\nabs, sqrt, log, exp, min, max, mean, sum and where(test, a, b)\nmin, max, mean and sum of one value reduce over its time steps",
+    );
+}
+
+pub fn derive_kind_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Adds a new variable computed from existing ones\nanomalies subtract the mean over every time step of the baseline\ngrids of different sizes are resampled to the first variable's",
+    );
+}
+
+pub fn temporal_stat_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Each cell's statistic over every time step of the first variable\ntrends are least squares fits, per decade for monthly and yearly data\nstep of max counts from 1, All adds one variable per statistic",
+    );
+}
+
+pub fn step_difference_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "One time step of the first variable minus one of the second\nchoose the same variable twice to compare two of its steps\nsteps count from 1",
+    );
+}
+
+pub fn correlation_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Each cell's Pearson correlation over time with the second variable or an index\nthe index file has one number per line, the last comma separated field\nsignificant only leaves out cells not significant at 95%",
+    );
+}
+
+pub fn diverging_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Shows low values blue and high values red, white in the middle of the range",
+    );
+}
+
+pub fn land_mask_file_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "A black and white image of the whole globe, bright pixels are land\nit replaces the mask read from the raster overlay",
+    );
+}
+
+pub fn surface_filter_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Greys out land or ocean using the land mask, for any variable in this viewport",
+    );
+}
+
+pub fn threshold_surface_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Only cells on land or over the ocean can meet the threshold",
+    );
+}
+
+pub fn stats_surface_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "Takes every statistic over only land or ocean cells\ncoverage is then of that part of the globe",
+    );
+}
+
+pub fn no_data_tt(ui: &Ui, show: bool) {
+    tool_tip_with_text(
+        ui,
+        show,
+        "How cells without data are drawn, the legend shows a swatch of it\nmissing cells are left out of statistics and probes",
+    );
+}
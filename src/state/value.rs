@@ -1,6 +1,13 @@
+use data_grid::DataGrid;
 use imgui::{ImString, Ui};
 use util::*;
+use state::time::{PlayMode, TimeAxis};
 use state::tool_tips::*;
+use stats::{
+    area_fraction_where, data_extent, histogram, percentiles, symmetric_range, SurfaceFilter,
+};
+use threshold::{Condition, ThresholdRule, ThresholdStyle};
+use vector_field::VectorField;
 
 #[derive(Copy, Clone, Debug)]
 pub enum Measurement {
@@ -21,68 +28,514 @@ impl Measurement {
     }
 }
 
+const HISTOGRAM_BINS: usize = 48;
+const HISTOGRAM_HEIGHT: f32 = 60.0;
+const HANDLE_COLOURS: [[f32; 4]; 2] = [[0.3, 0.7, 1.0, 1.0], [1.0, 0.45, 0.2, 1.0]];
+
+// How cells without data are drawn
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NoDataStyle {
+    Fill,
+    Hatch,
+}
+
+impl NoDataStyle {
+    // Also the number frag_hsv.glsl knows the style by
+    pub fn index(&self) -> i32 {
+        match self {
+            NoDataStyle::Fill => 0,
+            NoDataStyle::Hatch => 1,
+        }
+    }
+
+    pub fn from_index(index: i32) -> NoDataStyle {
+        match index {
+            1 => NoDataStyle::Hatch,
+            _ => NoDataStyle::Fill,
+        }
+    }
+}
+
+// The histogram of one time step, rebuilt when the step or weighting changes
+#[derive(Debug)]
+struct HistogramCache {
+    step: usize,
+    area_weighted: bool,
+    // The values the bins are spread over
+    span: [f32; 2],
+    counts: Vec<f32>,
+}
+
 #[derive(Debug)]
 pub struct Value {
     pub measurement: Measurement,
     pub name: ImString,
     pub tex_indices: Vec<usize>,
+    // The physical values behind each texture, empty for plain images
+    pub grids: Vec<DataGrid>,
+    // The components behind each step of a vector value, its grids hold the magnitude
+    pub vectors: Vec<VectorField>,
     pub selection: f32,
     pub time: bool,
     pub time_updated: bool,
+    // Follows the global timeline when time is synchronised
+    pub linked: bool,
+    pub axis: TimeAxis,
+    // Moves the time slider in whole steps
+    pub snap: bool,
+    pub play_mode: PlayMode,
+    // Either 1.0 or -1.0, only bouncing plays backwards
+    pub direction: f32,
+    pub steps_per_second: f32,
+    pub interpolate: bool,
+    // Cyclic values blend from the last step back into the first
+    pub cyclic: bool,
+    pub area_weighted: bool,
+    // Colours run from blue through white to red rather than round the hues
+    pub diverging: bool,
+    pub no_data_style: NoDataStyle,
+    pub no_data_colour: [f32; 3],
+    histogram: Option<HistogramCache>,
+    // The range handle being dragged on the histogram, 0 for min and 1 for max
+    dragging: Option<usize>,
+    pub threshold: ThresholdRule,
+    // The fraction of the globe meeting the rule at a step, kept until either changes
+    threshold_area: Option<(usize, ThresholdRule, f32)>,
 }
 
 impl Value {
-    pub fn build_ui_elements(&mut self, ui: &Ui, window_width: f32, hovered: bool) {
+    pub fn new(
+        measurement: Measurement,
+        name: ImString,
+        tex_indices: Vec<usize>,
+        axis: TimeAxis,
+    ) -> Value {
+        let cyclic = axis.is_cyclic();
+        Value {
+            measurement,
+            name,
+            tex_indices,
+            grids: vec![],
+            vectors: vec![],
+            selection: 0.0,
+            time: false,
+            time_updated: false,
+            linked: true,
+            axis,
+            snap: false,
+            play_mode: PlayMode::Loop,
+            direction: 1.0,
+            steps_per_second: 1.0,
+            interpolate: true,
+            cyclic,
+            area_weighted: true,
+            diverging: false,
+            no_data_style: NoDataStyle::Fill,
+            no_data_colour: [0.45, 0.45, 0.45],
+            histogram: None,
+            dragging: None,
+            threshold: ThresholdRule::new(),
+            threshold_area: None,
+        }
+    }
+
+    // The largest selection the time slider allows, cyclic values can sit
+    // between the last and first steps
+    pub fn max_selection(&self) -> f32 {
+        let len = self.tex_indices.len() as f32;
+        match self.cyclic {
+            true => len,
+            false => len - 1.0,
+        }
+    }
+
+    // The selection actually displayed, playing non-cyclic values hold the
+    // last step for one step before looping
+    pub fn shown_selection(&self) -> f32 {
+        let len = self.tex_indices.len() as f32;
+        match self.cyclic {
+            true => wrapf32(self.selection, len),
+            false => clampf32(self.selection, 0.0, len - 1.0),
+        }
+    }
+
+    pub fn current_step(&self) -> usize {
+        let last = self.tex_indices.len().saturating_sub(1);
+        let step = self.shown_selection().floor() as usize;
+        if step > last {
+            last
+        } else {
+            step
+        }
+    }
+
+    pub fn current_label(&self) -> String {
+        self.axis.label(self.current_step())
+    }
+
+    pub fn has_data(&self) -> bool {
+        !self.grids.is_empty()
+    }
+
+    pub fn current_grid(&self) -> Option<&DataGrid> {
+        self.grids.get(self.current_step())
+    }
+
+    pub fn is_vector(&self) -> bool {
+        !self.vectors.is_empty()
+    }
+
+    pub fn current_field(&self) -> Option<&VectorField> {
+        self.vectors.get(self.current_step())
+    }
+
+    // The value at a location for every time step
+    pub fn series_at(&self, location: [f32; 2]) -> Vec<Option<f32>> {
+        self.grids.iter().map(|grid| grid.sample(location)).collect()
+    }
+
+    pub fn is_time_stepped(&self) -> bool {
+        self.tex_indices.len() > 1
+    }
+
+    // Returns true if the user moved the time selection
+    pub fn build_ui_elements(
+        &mut self,
+        ui: &Ui,
+        window_width: f32,
+        hovered: bool,
+        synced: bool,
+        mouse_down: bool,
+        land_mask: Option<&DataGrid>,
+    ) -> bool {
         let width = window_width - 110.0;
-        if self.tex_indices.len() > 1 {
+        let mut changed = false;
+        if self.is_time_stepped() {
             ui.with_item_width(width, || {
-                self.build_time_ui(ui, width, hovered);
+                changed = self.build_time_ui(ui, hovered, synced);
             });
         }
         ui.with_item_width(width, || {
-            self.build_measurement_ui(ui, window_width - 50.0, hovered);
+            self.build_measurement_ui(ui, window_width - 50.0, hovered, mouse_down);
         });
+        if self.measurement.is_measurement() {
+            ui.with_item_width(width, || {
+                self.build_threshold_ui(ui, hovered, land_mask);
+            });
+        }
+        changed
     }
 
-    pub fn build_time_ui(&mut self, ui: &Ui, width: f32, hovered: bool) {
-        let max = (self.tex_indices.len() as i32) as f32;
+    pub fn clear_threshold_area(&mut self) {
+        self.threshold_area = None;
+    }
 
-        if ui
-            .slider_float(im_str!("Time"), &mut self.selection, 0.0, max)
-            .build()
-        {
-            self.time = false;
+    pub fn build_threshold_ui(&mut self, ui: &Ui, hovered: bool, land_mask: Option<&DataGrid>) {
+        ui.separator();
+        ui.text("Threshold");
+        let conditions = [
+            im_str!("Off"),
+            im_str!("Above"),
+            im_str!("Below"),
+            im_str!("Between"),
+            im_str!("Outside"),
+        ];
+        let mut condition = self.threshold.condition.index();
+        if ui.combo(im_str!("Condition"), &mut condition, &conditions, 5) {
+            self.threshold.condition = Condition::from_index(condition);
         }
-        time_slider_tt(ui, hovered);
+        threshold_tt(ui, hovered);
+        if self.threshold.condition == Condition::Off {
+            return;
+        }
+
+        match self.threshold.condition.is_band() {
+            true => ui.input_float2(im_str!("Limits"), &mut self.threshold.limits)
+                .decimal_precision(3)
+                .build(),
+            false => ui.input_float(im_str!("Limit"), &mut self.threshold.limits[0])
+                .decimal_precision(3)
+                .build(),
+        };
+        let styles = [im_str!("Hatch"), im_str!("Outline"), im_str!("Grey Out Rest")];
+        let mut style = self.threshold.style.index();
+        if ui.combo(im_str!("Style"), &mut style, &styles, 3) {
+            self.threshold.style = ThresholdStyle::from_index(style);
+        }
+        if self.threshold.style != ThresholdStyle::Mask {
+            ui.color_edit(im_str!("Highlight"), &mut self.threshold.colour)
+                .build();
+        }
+        let surfaces = [im_str!("Everywhere"), im_str!("On Land"), im_str!("Over Ocean")];
+        let mut surface = self.threshold.surface.index();
+        if ui.combo(im_str!("Where"), &mut surface, &surfaces, 3) {
+            self.threshold.surface = SurfaceFilter::from_index(surface);
+        }
+        threshold_surface_tt(ui, hovered);
 
-        ui.same_line_spacing(width, 45.0);
-        if self.time {
-            if ui.button(im_str!("Pause"), (40.0, 20.0)) {
-                self.time = false;
+        let step = self.current_step();
+        let rule = self.threshold;
+        let fraction = match self.threshold_area {
+            Some((cached_step, cached_rule, fraction))
+                if cached_step == step && cached_rule == rule =>
+            {
+                Some(fraction)
             }
-            pause_tt(ui, hovered);
+            _ => self.current_grid().map(|grid| {
+                let include = |location| rule.surface.includes(land_mask, location);
+                area_fraction_where(grid, &include, &|cell| rule.matches(cell))
+            }),
+        };
+        self.threshold_area = fraction.map(|fraction| (step, rule, fraction));
+        if let Some(fraction) = fraction {
+            ui.text(format!("{:.1}% of the globe meets it", fraction * 100.0));
+            threshold_area_tt(ui, hovered);
+        }
+    }
+
+    pub fn build_time_ui(&mut self, ui: &Ui, hovered: bool, synced: bool) -> bool {
+        let max = self.max_selection();
+        let following = synced && self.linked;
+        let format = ImString::new(self.axis.display_format(self.current_step()));
+
+        let mut changed = if self.snap {
+            let mut step = self.current_step() as i32;
+            let last = self.tex_indices.len() as i32 - 1;
+            let changed = ui
+                .slider_int(im_str!("Time"), &mut step, 0, last)
+                .display_format(&format)
+                .build();
+            if changed {
+                self.selection = step as f32;
+            }
+            changed
         } else {
-            if ui.button(im_str!("Play"), (40.0, 20.0)) {
-                self.time = true;
+            ui.slider_float(im_str!("Time"), &mut self.selection, 0.0, max)
+                .display_format(&format)
+                .build()
+        };
+        time_slider_tt(ui, hovered);
+
+        let button_size = (40.0, 20.0);
+        if ui.button(im_str!("<"), button_size) {
+            self.step_back();
+            changed = true;
+        }
+        step_back_tt(ui, hovered);
+
+        if !following {
+            ui.same_line(0.0);
+            if self.time {
+                if ui.button(im_str!("Pause"), button_size) {
+                    self.time = false;
+                }
+                pause_tt(ui, hovered);
+            } else {
+                if ui.button(im_str!("Play"), button_size) {
+                    let last = self.tex_indices.len() as f32 - 1.0;
+                    if self.play_mode == PlayMode::Once && self.selection >= last {
+                        self.selection = 0.0;
+                    }
+                    self.time = true;
+                }
+                play_tt(ui, hovered);
             }
-            play_tt(ui, hovered);
         }
+
+        ui.same_line(0.0);
+        if ui.button(im_str!(">"), button_size) {
+            self.step_forward();
+            changed = true;
+        }
+        step_forward_tt(ui, hovered);
+
+        ui.same_line(0.0);
+        ui.checkbox(im_str!("Snap"), &mut self.snap);
+        snap_tt(ui, hovered);
+
+        if synced {
+            ui.same_line(0.0);
+            ui.checkbox(im_str!("Link"), &mut self.linked);
+            link_value_tt(ui, hovered);
+        }
+
+        if changed && !following {
+            self.time = false;
+        }
+        if following {
+            return changed;
+        }
+
+        let modes = [im_str!("Loop"), im_str!("Bounce"), im_str!("Once")];
+        let mut mode = self.play_mode.index();
+        if ui.combo(im_str!("Playback"), &mut mode, &modes, 3) {
+            self.play_mode = PlayMode::from_index(mode);
+            self.direction = 1.0;
+        }
+        play_mode_tt(ui, hovered);
+
+        ui.slider_float(
+            im_str!("Steps/s"),
+            &mut self.steps_per_second,
+            0.1,
+            12.0,
+        ).build();
+        steps_per_second_tt(ui, hovered);
+
+        ui.checkbox(im_str!("Smooth"), &mut self.interpolate);
+        smooth_tt(ui, hovered);
+
+        ui.same_line(0.0);
+        ui.checkbox(im_str!("Cyclic"), &mut self.cyclic);
+        cyclic_tt(ui, hovered);
+        changed
     }
 
-    pub fn build_measurement_ui(&mut self, ui: &Ui, width: f32, hovered: bool) {
+    // Rebuilds the histogram of the current step if it is out of date. The bins
+    // cover the initial range and any data outside it
+    fn update_histogram(&mut self, init_range: [f32; 2]) {
+        let step = self.current_step();
+        let area_weighted = self.area_weighted;
+        let up_to_date = self.histogram.as_ref().map_or(false, |cache| {
+            cache.step == step && cache.area_weighted == area_weighted
+        });
+        if up_to_date {
+            return;
+        }
+        self.histogram = self.current_grid().map(|grid| {
+            let span = match data_extent(grid) {
+                Some([low, high]) => [low.min(init_range[0]), high.max(init_range[1])],
+                None => init_range,
+            };
+            HistogramCache {
+                step,
+                area_weighted,
+                span,
+                counts: histogram(grid, span, HISTOGRAM_BINS, area_weighted),
+            }
+        });
+    }
+
+    // Draws the histogram with a handle at each end of the range, dragging
+    // near a handle moves it
+    fn build_histogram(
+        &mut self,
+        ui: &Ui,
+        width: f32,
+        range: &mut [f32; 2],
+        hovered: bool,
+        mouse_down: bool,
+    ) {
+        let cache = match self.histogram.as_ref() {
+            Some(cache) => cache,
+            None => return,
+        };
+        let span = cache.span;
+        let origin = ui.get_cursor_screen_pos();
+        ui.plot_histogram(im_str!("##Histogram"), &cache.counts)
+            .graph_size((width, HISTOGRAM_HEIGHT))
+            .scale_min(0.0)
+            .build();
+        histogram_tt(ui, hovered);
+
+        let to_x = |value: f32| {
+            let x = clampf32((value - span[0]) / (span[1] - span[0]), 0.0, 1.0);
+            origin.0 + x * width
+        };
+        let mouse = ui.imgui().mouse_pos();
+        if !mouse_down {
+            self.dragging = None;
+        } else if self.dragging.is_none() {
+            let inside = mouse.0 >= origin.0
+                && mouse.0 <= origin.0 + width
+                && mouse.1 >= origin.1
+                && mouse.1 <= origin.1 + HISTOGRAM_HEIGHT;
+            if inside {
+                let to_min = (mouse.0 - to_x(range[0])).abs();
+                let to_max = (mouse.0 - to_x(range[1])).abs();
+                self.dragging = Some(if to_min <= to_max { 0 } else { 1 });
+            }
+        }
+        if let Some(handle) = self.dragging {
+            let x = clampf32((mouse.0 - origin.0) / width, 0.0, 1.0);
+            let value = span[0] + x * (span[1] - span[0]);
+            match handle {
+                0 => range[0] = value.min(range[1]),
+                _ => range[1] = value.max(range[0]),
+            }
+        }
+
+        let draw_list = ui.get_window_draw_list();
+        for (end, colour) in range.iter().zip(HANDLE_COLOURS.iter()) {
+            let x = to_x(*end);
+            draw_list
+                .add_line((x, origin.1), (x, origin.1 + HISTOGRAM_HEIGHT), *colour)
+                .thickness(2.0)
+                .build();
+        }
+    }
+
+    fn build_range_presets(&mut self, ui: &Ui, range: &mut [f32; 2], hovered: bool) {
+        let preset = {
+            let grid = match self.current_grid() {
+                Some(grid) => grid,
+                None => return,
+            };
+            let mut preset = None;
+            if ui.small_button(im_str!("Fit Data")) {
+                preset = data_extent(grid);
+            }
+            fit_data_tt(ui, hovered);
+            ui.same_line(0.0);
+            if ui.small_button(im_str!("2-98%")) {
+                preset = percentiles(grid, [0.02, 0.98], self.area_weighted);
+            }
+            percentile_range_tt(ui, hovered);
+            ui.same_line(0.0);
+            if ui.small_button(im_str!("Symmetric")) {
+                preset = data_extent(grid).map(symmetric_range);
+            }
+            symmetric_range_tt(ui, hovered);
+            preset
+        };
+        // A single value still needs some width for the colours to spread over
+        if let Some([low, high]) = preset {
+            *range = match low < high {
+                true => [low, high],
+                false => [low - 0.5, high + 0.5],
+            };
+        }
+        ui.checkbox(im_str!("Area Weighted"), &mut self.area_weighted);
+        area_weighted_tt(ui, hovered);
+    }
+
+    pub fn build_measurement_ui(&mut self, ui: &Ui, width: f32, hovered: bool, mouse_down: bool) {
+        let (init_range, mut shown) = match self.measurement {
+            Measurement::Is {
+                init_range, range, ..
+            } => (init_range, range),
+            Measurement::IsNot => return,
+        };
+        ui.separator();
+        ui.text("Range");
+        if self.has_data() {
+            self.update_histogram(init_range);
+            self.build_histogram(ui, width, &mut shown, hovered, mouse_down);
+            self.build_range_presets(ui, &mut shown, hovered);
+        }
+
         if let Measurement::Is {
             init_range: [min, max],
             range: ref mut value,
             ..
         } = self.measurement
         {
+            *value = shown;
             let range = max - min;
             let middle = (value[0] + value[1]) * 0.5;
             let min_width = (width * (middle - min) / range).round();
             let min_width = minf32(min_width, 2.0);
 
-            ui.separator();
-            ui.text("Range");
             ui.push_item_width(width);
             ui.input_float2(im_str!("##Range floats"), value)
                 .decimal_precision(3)
@@ -101,34 +554,331 @@ impl Value {
                 .build();
             max_range_tt(ui, hovered);
         }
+        ui.checkbox(im_str!("Diverging Colours"), &mut self.diverging);
+        diverging_tt(ui, hovered);
+
+        let styles = [im_str!("Fill"), im_str!("Hatch")];
+        let mut style = self.no_data_style.index();
+        ui.with_item_width(width * 0.5, || {
+            if ui.combo(im_str!("No Data"), &mut style, &styles, 2) {
+                self.no_data_style = NoDataStyle::from_index(style);
+            }
+        });
+        no_data_tt(ui, hovered);
+        ui.with_item_width(width - 110.0, || {
+            ui.color_edit(im_str!("No Data Colour"), &mut self.no_data_colour)
+                .build();
+        });
     }
 
     pub fn get_textures(&self) -> (usize, usize, f32) {
-        let mut ceil = self.selection.ceil() as usize;
-        if ceil >= self.tex_indices.len() {
-            ceil = 0;
-        }
-        let mut floor = self.selection.floor();
-        if floor >= self.tex_indices.len() as f32 {
-            floor = (self.tex_indices.len() - 1) as f32;
+        let len = self.tex_indices.len();
+        let selection = self.shown_selection();
+        let floor = self.current_step();
+        let ceil = match self.cyclic {
+            true => (floor + 1) % len,
+            false => if floor + 1 < len { floor + 1 } else { floor },
+        };
+        let interpolation = match self.interpolate && ceil != floor {
+            true => clampf32(selection - floor as f32, 0.0, 1.0),
+            false => 0.0,
+        };
+
+        (self.tex_indices[floor], self.tex_indices[ceil], interpolation)
+    }
+
+    // Moves the selection to where the global clock falls in this value's steps,
+    // the clock counts months from the absolute month origin
+    pub fn set_from_clock(&mut self, clock: f32, origin: f32) {
+        let len = self.tex_indices.len() as f32;
+        if len < 1.0 {
+            return;
         }
-        let interpolation = self.selection - floor;
+        let step = self.axis.step_at(origin + clock, origin);
+        self.selection = match self.axis.start_month() {
+            // Dates outside a dated series hold its first or last step
+            Some(_) => clampf32(step, 0.0, len - 1.0),
+            None => wrapf32(step, len),
+        };
+    }
 
-        (
-            self.tex_indices[floor as usize],
-            self.tex_indices[ceil],
-            interpolation,
-        )
+    pub fn clock(&self, origin: f32) -> f32 {
+        self.axis.month_at(self.selection, origin) - origin
+    }
+
+    pub fn step_forward(&mut self) {
+        let last = self.tex_indices.len() as f32 - 1.0;
+        let next = self.selection.floor() + 1.0;
+        self.selection = match self.play_mode {
+            PlayMode::Loop if next > last => 0.0,
+            _ if next > last => last,
+            _ => next,
+        };
+    }
+
+    pub fn step_back(&mut self) {
+        let last = self.tex_indices.len() as f32 - 1.0;
+        let previous = self.selection.ceil() - 1.0;
+        self.selection = match self.play_mode {
+            PlayMode::Loop if previous < 0.0 => last,
+            _ if previous < 0.0 => 0.0,
+            _ => previous,
+        };
     }
 
     pub fn increase_selection(&mut self, dt: f32) {
+        let steps = dt * self.steps_per_second;
+        self.advance(steps);
+    }
+
+    // Moves forward by a number of time steps following the play mode
+    pub fn advance(&mut self, steps: f32) {
         if self.tex_indices.len() < 1 {
             return;
         }
-        self.selection += dt;
+        self.selection += steps * self.direction;
         let max = self.tex_indices.len() as f32;
-        if self.selection > max {
-            self.selection = self.selection - max;
+        let last = max - 1.0;
+        match self.play_mode {
+            PlayMode::Loop => {
+                self.selection = wrapf32(self.selection, max);
+            }
+            PlayMode::Bounce => {
+                if self.selection > last {
+                    self.selection = 2.0 * last - self.selection;
+                    self.direction = -1.0;
+                } else if self.selection < 0.0 {
+                    self.selection = -self.selection;
+                    self.direction = 1.0;
+                }
+            }
+            PlayMode::Once => {
+                if self.selection >= last {
+                    self.selection = last;
+                    self.time = false;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use imgui::ImString;
+    use state::time::{PlayMode, TimeAxis};
+    use state::value::{Measurement, Value};
+
+    fn monthly(cyclic: bool) -> Value {
+        let axis = match cyclic {
+            true => TimeAxis::Climatology,
+            false => TimeAxis::Yearly { year: 2000 },
+        };
+        Value::new(
+            Measurement::IsNot,
+            ImString::new("Test"),
+            (10..22).collect(),
+            axis,
+        )
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn cyclic_interpolates_last_into_first() {
+        let mut value = monthly(true);
+        value.selection = 11.5;
+        let (floor, ceil, interp) = value.get_textures();
+
+        assert_eq!((floor, ceil), (21, 10));
+        assert!(close(interp, 0.5));
+    }
+
+    #[test]
+    fn cyclic_selection_at_len_is_first_step() {
+        let mut value = monthly(true);
+        value.selection = 12.0;
+        let (floor, ceil, interp) = value.get_textures();
+
+        assert_eq!((floor, ceil), (10, 11));
+        assert!(close(interp, 0.0));
+    }
+
+    #[test]
+    fn non_cyclic_holds_last_step() {
+        let mut value = monthly(false);
+        value.selection = 11.5;
+        assert_eq!(value.get_textures(), (21, 21, 0.0));
+
+        value.selection = 12.0;
+        assert_eq!(value.get_textures(), (21, 21, 0.0));
+    }
+
+    #[test]
+    fn first_step_boundaries() {
+        let mut value = monthly(false);
+        value.selection = 0.0;
+        assert_eq!(value.get_textures(), (10, 11, 0.0));
+
+        value.selection = -1.0;
+        assert_eq!(value.get_textures(), (10, 11, 0.0));
+    }
+
+    #[test]
+    fn discrete_stepping_never_interpolates() {
+        let mut value = monthly(true);
+        value.interpolate = false;
+        value.selection = 3.75;
+
+        assert_eq!(value.get_textures(), (13, 14, 0.0));
+    }
+
+    #[test]
+    fn whole_steps_show_only_the_current_grid() {
+        // The shader only falls back on the second texture while blending, so
+        // without interpolation the first one must be the step the CPU reads
+        let mut value = monthly(false);
+        value.interpolate = false;
+        for selection in &[3.0, 3.75, 11.0] {
+            value.selection = *selection;
+            let (first, _, interp) = value.get_textures();
+            assert_eq!(interp, 0.0);
+            assert_eq!(first, value.tex_indices[value.current_step()]);
         }
+
+        // Paused on a whole step with interpolation on
+        value.interpolate = true;
+        value.selection = 5.0;
+        let (first, _, interp) = value.get_textures();
+        assert_eq!(interp, 0.0);
+        assert_eq!(first, value.tex_indices[value.current_step()]);
+    }
+
+    #[test]
+    fn single_texture() {
+        let mut value = Value::new(
+            Measurement::IsNot,
+            ImString::new("Single"),
+            vec![4],
+            TimeAxis::Steps,
+        );
+        value.selection = 0.5;
+        assert_eq!(value.get_textures(), (4, 4, 0.0));
+
+        value.increase_selection(2.5);
+        assert!(close(value.selection, 0.0));
+    }
+
+    #[test]
+    fn loop_wraps_past_end() {
+        let mut value = monthly(true);
+        value.selection = 11.5;
+        value.increase_selection(1.0);
+        assert!(close(value.selection, 0.5));
+
+        value.increase_selection(24.0);
+        assert!(close(value.selection, 0.5));
+    }
+
+    #[test]
+    fn non_cyclic_loop_restarts_after_last_step() {
+        let mut value = monthly(false);
+        value.selection = 10.5;
+        value.increase_selection(1.0);
+        assert!(close(value.selection, 11.5));
+        assert_eq!(value.get_textures(), (21, 21, 0.0));
+
+        value.increase_selection(1.0);
+        assert!(close(value.selection, 0.5));
+    }
+
+    #[test]
+    fn once_stops_at_last_step() {
+        let mut value = monthly(false);
+        value.play_mode = PlayMode::Once;
+        value.time = true;
+        value.selection = 10.5;
+        value.increase_selection(2.0);
+
+        assert!(close(value.selection, 11.0));
+        assert!(!value.time);
+    }
+
+    #[test]
+    fn clock_shows_the_same_month_on_offset_axes() {
+        let mut july = Value::new(
+            Measurement::IsNot,
+            ImString::new("July"),
+            (0..24).collect(),
+            TimeAxis::Monthly { year: 2000, month: 6 },
+        );
+        let mut january = Value::new(
+            Measurement::IsNot,
+            ImString::new("January"),
+            (0..24).collect(),
+            TimeAxis::Monthly { year: 2001, month: 0 },
+        );
+        let mut climatology = monthly(true);
+        let origin = july.axis.start_month().unwrap();
+
+        for clock in &[0.0, 6.0, 13.0] {
+            july.set_from_clock(*clock, origin);
+            january.set_from_clock(*clock, origin);
+            climatology.set_from_clock(*clock, origin);
+
+            let month = july.axis.label(july.current_step());
+            assert!(month.starts_with(&climatology.axis.label(climatology.current_step())));
+            if *clock >= 6.0 {
+                assert_eq!(month, january.axis.label(january.current_step()));
+            }
+        }
+    }
+
+    #[test]
+    fn clock_before_a_series_holds_its_first_step() {
+        let mut value = Value::new(
+            Measurement::IsNot,
+            ImString::new("Late"),
+            (0..12).collect(),
+            TimeAxis::Monthly { year: 2001, month: 0 },
+        );
+        let origin = TimeAxis::Monthly { year: 2000, month: 0 }.start_month().unwrap();
+
+        value.set_from_clock(3.0, origin);
+        assert!(close(value.selection, 0.0));
+
+        value.set_from_clock(30.0, origin);
+        assert!(close(value.selection, 11.0));
+    }
+
+    #[test]
+    fn clock_round_trips_through_the_selection() {
+        let mut value = Value::new(
+            Measurement::IsNot,
+            ImString::new("Offset"),
+            (0..24).collect(),
+            TimeAxis::Monthly { year: 2000, month: 9 },
+        );
+        let origin = TimeAxis::Monthly { year: 2000, month: 0 }.start_month().unwrap();
+
+        value.set_from_clock(14.0, origin);
+        assert!(close(value.selection, 5.0));
+        assert!(close(value.clock(origin), 14.0));
+    }
+
+    #[test]
+    fn bounce_reverses_at_ends() {
+        let mut value = monthly(false);
+        value.play_mode = PlayMode::Bounce;
+        value.selection = 10.5;
+        value.increase_selection(1.0);
+        assert!(close(value.selection, 10.5));
+        assert!(close(value.direction, -1.0));
+
+        value.selection = 0.5;
+        value.increase_selection(1.0);
+        assert!(close(value.selection, 0.5));
+        assert!(close(value.direction, 1.0));
     }
 }
@@ -1,8 +1,12 @@
+use glium::glutin::ModifiersState;
 use state::viewports::ViewPort;
 use input::MouseState;
 use imgui::Ui;
 use state::tool_tips::*;
 
+// How far in pixels the mouse can move between press and release to count as a click
+const CLICK_DISTANCE: f32 = 4.0;
+
 pub struct Zoom {
     pub rate: f32,
     pub level: f32,
@@ -31,10 +35,78 @@ impl Zoom {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DragAction {
+    Rotate,
+    Tilt,
+    Pan,
+    Nothing,
+}
+
+impl DragAction {
+    pub fn from_index(index: i32) -> DragAction {
+        match index {
+            0 => DragAction::Rotate,
+            1 => DragAction::Tilt,
+            2 => DragAction::Pan,
+            _ => DragAction::Nothing,
+        }
+    }
+
+    pub fn index(&self) -> i32 {
+        match self {
+            DragAction::Rotate => 0,
+            DragAction::Tilt => 1,
+            DragAction::Pan => 2,
+            DragAction::Nothing => 3,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct MouseBindings {
+    pub left: DragAction,
+    pub right: DragAction,
+    pub middle: DragAction,
+}
+
+impl MouseBindings {
+    pub fn new() -> MouseBindings {
+        MouseBindings {
+            left: DragAction::Rotate,
+            right: DragAction::Tilt,
+            middle: DragAction::Pan,
+        }
+    }
+
+    pub fn build_binding_combos(&mut self, ui: &Ui, hovered: bool) {
+        let items = [
+            im_str!("Rotate"),
+            im_str!("Tilt / Roll"),
+            im_str!("Pan"),
+            im_str!("Nothing"),
+        ];
+        let buttons = [
+            (im_str!("Left Drag"), &mut self.left),
+            (im_str!("Right Drag"), &mut self.right),
+            (im_str!("Middle Drag"), &mut self.middle),
+        ];
+
+        for (label, action) in buttons.iter_mut() {
+            let mut index = action.index();
+            if ui.combo(label, &mut index, &items, 4) {
+                **action = DragAction::from_index(index);
+            }
+            drag_binding_tt(ui, hovered);
+        }
+    }
+}
+
 pub struct StateVariables {
     pub height: f32,
     pub time_multi: f32,
     pub drag_speed: f32,
+    pub bindings: MouseBindings,
 }
 
 impl StateVariables {
@@ -59,6 +131,52 @@ impl StateVariables {
                 10.0,
             ).build();
             time_multi_tt(ui, hovered);
+
+            self.bindings.build_binding_combos(ui, hovered);
+        });
+    }
+}
+
+pub struct Timeline {
+    pub synced: bool,
+    pub playing: bool,
+    // Months since the timeline origin, the earliest start of the linked values
+    pub clock: f32,
+}
+
+impl Timeline {
+    pub fn new() -> Timeline {
+        Timeline {
+            synced: false,
+            playing: false,
+            clock: 0.0,
+        }
+    }
+
+    pub fn advance(&mut self, dt: f32) {
+        if self.synced && self.playing {
+            self.clock += dt;
+        }
+    }
+
+    pub fn build_timeline_ui(&mut self, ui: &Ui, width: f32, max_months: f32, hovered: bool) {
+        ui.checkbox(im_str!("Synchronise Time"), &mut self.synced);
+        sync_time_tt(ui, hovered);
+
+        if !self.synced {
+            return;
+        }
+        if max_months > 0.0 {
+            self.clock %= max_months;
+        }
+        ui.with_item_width(width, || {
+            if ui
+                .slider_float(im_str!("Timeline"), &mut self.clock, 0.0, max_months)
+                .build()
+            {
+                self.playing = false;
+            }
+            timeline_tt(ui, hovered);
         });
     }
 }
@@ -67,8 +185,13 @@ pub struct MouseVariables {
     pub position: [f32; 2],
     pub hover_time: f32,
     pub hovered: bool,
-    pub m1_pressed: bool,
+    pub pressed: (bool, bool, bool),
     pub viewport_pressed: Option<usize>,
+    // Where the left button went down in pixels, None if it was on the ui
+    click_start: Option<[f32; 2]>,
+    // Set for one frame when the left button is released without dragging
+    pub clicked: Option<[f32; 2]>,
+    pub click_modifiers: ModifiersState,
 }
 
 impl MouseVariables {
@@ -77,8 +200,11 @@ impl MouseVariables {
             position: [0.0; 2],
             hover_time: 0.25,
             hovered: false,
-            m1_pressed: false,
+            pressed: (false, false, false),
             viewport_pressed: None,
+            click_start: None,
+            clicked: None,
+            click_modifiers: ModifiersState::default(),
         }
     }
 
@@ -103,14 +229,35 @@ impl MouseVariables {
         self.position = [x, y];
         self.hovered = mouse.hovered >= self.hover_time;
 
-        let m1 = mouse.mouse.pressed.0;
+        // The viewport is chosen by whichever button starts the drag, so holding
+        // a second button keeps affecting the same viewport
+        let (m1, m2, m3) = mouse.mouse.pressed;
+        let any_pressed = m1 || m2 || m3;
+        let was_pressed = self.pressed.0 || self.pressed.1 || self.pressed.2;
         if !mouse.on_ui {
-            if m1 && !self.m1_pressed {
+            if any_pressed && !was_pressed {
                 self.viewport_pressed = self.on_viewport(viewports);
-            } else if !m1 && self.m1_pressed {
+            } else if !any_pressed && was_pressed {
                 self.viewport_pressed = None;
             }
         }
-        self.m1_pressed = m1;
+
+        self.clicked = None;
+        let pixel = *mouse.mouse.position.as_ref();
+        if m1 && !self.pressed.0 {
+            self.click_start = match mouse.on_ui {
+                true => None,
+                false => Some(pixel),
+            };
+        } else if !m1 && self.pressed.0 {
+            if let Some(start) = self.click_start.take() {
+                let moved = (pixel[0] - start[0]).abs() + (pixel[1] - start[1]).abs();
+                if moved <= CLICK_DISTANCE {
+                    self.clicked = Some(self.position);
+                    self.click_modifiers = mouse.modifiers;
+                }
+            }
+        }
+        self.pressed = (m1, m2, m3);
     }
 }
\ No newline at end of file
@@ -0,0 +1,166 @@
+use geojson;
+use glium::{backend::Facade, Rect, Surface};
+use imgui::{ImString, Ui};
+use shapefile;
+use state::line_render::{LineSet, LineStyle};
+use state::tool_tips::*;
+use state::GlobalState;
+use std::error::Error;
+use std::path::Path;
+use util::editable_string;
+
+const LAYER_COLOURS: [[f32; 3]; 4] = [
+    [0.05, 0.05, 0.05],
+    [1.0, 1.0, 1.0],
+    [0.2, 0.45, 0.9],
+    [0.9, 0.3, 0.2],
+];
+
+pub struct VectorLayer {
+    pub name: String,
+    pub show: bool,
+    pub colour: [f32; 3],
+    pub width: f32,
+    // Lines waiting to be uploaded, taken once the line set is built
+    pending: Option<Vec<Vec<[f32; 2]>>>,
+    lines: Option<LineSet>,
+}
+
+// Reads the outlines out of a shapefile, or anything else as GeoJSON
+pub fn load_lines(path: &str) -> Result<Vec<Vec<[f32; 2]>>, Box<Error>> {
+    let is_shapefile = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("shp"));
+    let lines: Vec<Vec<[f32; 2]>> = match is_shapefile {
+        true => shapefile::load(path)?,
+        false => geojson::load(path)?
+            .iter()
+            .flat_map(|feature| feature.geometry.lines())
+            .collect(),
+    };
+    match lines.iter().any(|line| line.len() > 1) {
+        true => Ok(lines),
+        false => Err("No lines or polygons in the file".into()),
+    }
+}
+
+pub struct VectorOverlays {
+    pub layers: Vec<VectorLayer>,
+    pub path: ImString,
+    pub status: String,
+    requested: bool,
+}
+
+impl VectorOverlays {
+    pub fn new() -> VectorOverlays {
+        VectorOverlays {
+            layers: vec![],
+            path: editable_string("assets/coastlines.geojson", 256),
+            status: String::new(),
+            requested: false,
+        }
+    }
+
+    pub fn add(&mut self, path: &str, lines: Vec<Vec<[f32; 2]>>) {
+        let name = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string());
+        self.layers.push(VectorLayer {
+            name,
+            show: true,
+            colour: LAYER_COLOURS[self.layers.len() % LAYER_COLOURS.len()],
+            width: 1.5,
+            pending: Some(lines),
+            lines: None,
+        });
+    }
+
+    pub fn build_overlay_ui(&mut self, ui: &Ui, width: f32, hovered: bool) {
+        if !ui.collapsing_header(im_str!("Vector Overlays")).build() {
+            return;
+        }
+        ui.with_item_width(width, || {
+            ui.input_text(im_str!("Overlay File"), &mut self.path).build();
+        });
+        vector_file_tt(ui, hovered);
+        if ui.button(im_str!("Load Overlay"), (100.0, 30.0)) {
+            self.requested = true;
+        }
+        if !self.status.is_empty() {
+            ui.text(&self.status);
+        }
+
+        let mut remove = None;
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            ui.separator();
+            let show = ImString::new(format!("{}##Layer {}", layer.name, i));
+            ui.checkbox(&show, &mut layer.show);
+            ui.with_item_width(width, || {
+                let colour = ImString::new(format!("Colour##Layer {}", i));
+                ui.color_edit(&colour, &mut layer.colour).build();
+                let line_width = ImString::new(format!("Width##Layer {}", i));
+                ui.slider_float(&line_width, &mut layer.width, 1.0, 5.0)
+                    .build();
+            });
+            let delete = ImString::new(format!("Remove##Layer {}", i));
+            if ui.small_button(&delete) {
+                remove = Some(i);
+            }
+        }
+        if let Some(i) = remove {
+            self.layers.remove(i);
+        }
+    }
+
+    pub fn handle_files(&mut self) {
+        if !self.requested {
+            return;
+        }
+        self.requested = false;
+        let path = self.path.to_str().to_string();
+        self.status = match load_lines(&path) {
+            Ok(lines) => {
+                let status = format!("Loaded {} lines", lines.len());
+                self.add(&path, lines);
+                status
+            }
+            Err(error) => format!("Failed: {}", error),
+        };
+    }
+}
+
+impl GlobalState {
+    pub fn update_vector_overlays<F: Facade>(&mut self, display: &F) {
+        for layer in &mut self.vectors.layers {
+            if let Some(lines) = layer.pending.take() {
+                layer.lines = LineSet::new(display, &lines);
+            }
+        }
+    }
+
+    pub fn render_vector_overlays<T: Surface + ?Sized>(
+        &self,
+        target: &mut T,
+        view: [[f32; 4]; 4],
+        rect: Rect,
+    ) {
+        for layer in self.vectors.layers.iter().filter(|layer| layer.show) {
+            let lines = match layer.lines {
+                Some(ref lines) => lines,
+                None => continue,
+            };
+            let [r, g, b] = layer.colour;
+            self.line_renderer.render(
+                target,
+                lines,
+                view,
+                &self.height_map,
+                self.variables.height,
+                LineStyle::new([r, g, b, 1.0], layer.width),
+                rect,
+            );
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use data_grid::{DataGrid, NORMALISED};
+use glium::{backend::Facade, Rect, Surface};
+use imgui::{ImString, Ui};
+use state::line_render::{LineSet, LineStyle};
+use state::time::TimeAxis;
+use state::tool_tips::*;
+use state::value::Measurement;
+use state::GlobalState;
+use std::collections::{BTreeMap, VecDeque};
+use vector_field::{advect, arrow_lines, VectorField};
+
+// Seconds a particle lives is picked between these before it respawns
+const PARTICLE_LIFE: [f32; 2] = [2.0, 6.0];
+const MAX_PARTICLES: i32 = 5000;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum VectorMode {
+    Arrows,
+    Particles,
+}
+
+impl VectorMode {
+    pub fn index(&self) -> i32 {
+        match self {
+            VectorMode::Arrows => 0,
+            VectorMode::Particles => 1,
+        }
+    }
+
+    pub fn from_index(index: i32) -> VectorMode {
+        match index {
+            1 => VectorMode::Particles,
+            _ => VectorMode::Arrows,
+        }
+    }
+}
+
+struct Particle {
+    // Newest position first, as [lat, lon]
+    trail: VecDeque<[f32; 2]>,
+    age: f32,
+    life: f32,
+}
+
+struct ArrowCache {
+    step: usize,
+    spacing: f32,
+    // The magnitude drawn at full length, the top of the value's range
+    max: f32,
+    lines: Option<LineSet>,
+}
+
+struct ParticleSystem {
+    particles: Vec<Particle>,
+    lines: Option<LineSet>,
+}
+
+pub struct VectorSettings {
+    pub mode: VectorMode,
+    // Degrees between neighbouring arrows
+    pub spacing: f32,
+    // Degrees a particle moves each second per unit of speed
+    pub speed: f32,
+    pub particle_count: i32,
+    // Positions kept behind each particle
+    pub trail_length: i32,
+    pub colour: [f32; 3],
+    pub width: f32,
+    // Colours the globe under a vector value by its magnitude, when off the
+    // world map is shown instead
+    pub colour_magnitude: bool,
+    arrows: BTreeMap<usize, ArrowCache>,
+    particles: BTreeMap<usize, ParticleSystem>,
+    seed: u32,
+}
+
+impl VectorSettings {
+    pub fn new() -> VectorSettings {
+        VectorSettings {
+            mode: VectorMode::Arrows,
+            spacing: 5.0,
+            speed: 0.2,
+            particle_count: 1500,
+            trail_length: 8,
+            colour: [1.0, 1.0, 1.0],
+            width: 1.5,
+            colour_magnitude: true,
+            arrows: BTreeMap::new(),
+            particles: BTreeMap::new(),
+            seed: 0x9e37_79b9,
+        }
+    }
+
+    // Xorshift, plenty for scattering particles
+    fn random(&mut self) -> f32 {
+        let mut x = self.seed;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.seed = x;
+        (x >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    // Spread evenly over the sphere rather than bunched at the poles
+    fn spawn(&mut self) -> Particle {
+        let lat = (self.random() * 2.0 - 1.0).asin().to_degrees();
+        let lon = self.random() * 360.0 - 180.0;
+        let life = PARTICLE_LIFE[0] + self.random() * (PARTICLE_LIFE[1] - PARTICLE_LIFE[0]);
+        let mut trail = VecDeque::new();
+        trail.push_front([lat, lon]);
+        Particle {
+            trail,
+            age: 0.0,
+            life,
+        }
+    }
+
+    pub fn build_vector_ui(&mut self, ui: &Ui, width: f32, hovered: bool) {
+        if !ui.collapsing_header(im_str!("Vectors")).build() {
+            return;
+        }
+        let modes = [im_str!("Arrows"), im_str!("Particles")];
+        let mut mode = self.mode.index();
+        ui.with_item_width(width, || {
+            if ui.combo(im_str!("Style"), &mut mode, &modes, 2) {
+                self.mode = VectorMode::from_index(mode);
+            }
+            match self.mode {
+                VectorMode::Arrows => {
+                    ui.slider_float(im_str!("Arrow Spacing"), &mut self.spacing, 1.0, 30.0)
+                        .build();
+                    arrow_spacing_tt(ui, hovered);
+                }
+                VectorMode::Particles => {
+                    ui.slider_int(im_str!("Particles"), &mut self.particle_count, 100, MAX_PARTICLES)
+                        .build();
+                    ui.slider_float(im_str!("Speed"), &mut self.speed, 0.01, 2.0)
+                        .build();
+                    particle_speed_tt(ui, hovered);
+                    ui.slider_int(im_str!("Trail"), &mut self.trail_length, 2, 30)
+                        .build();
+                }
+            }
+            ui.color_edit(im_str!("Vector Colour"), &mut self.colour)
+                .build();
+            ui.slider_float(im_str!("Vector Width"), &mut self.width, 1.0, 5.0)
+                .build();
+        });
+        ui.checkbox(im_str!("Colour By Magnitude"), &mut self.colour_magnitude);
+        colour_magnitude_tt(ui, hovered);
+    }
+}
+
+impl GlobalState {
+    // Adds a value drawn as the magnitude of the fields with arrows or particles over it
+    pub fn add_vector_value<F: Facade>(
+        &mut self,
+        display: &F,
+        fields: Vec<VectorField>,
+        name: ImString,
+        axis: TimeAxis,
+    ) {
+        let grids: Vec<DataGrid> = fields.iter().map(|field| field.magnitude()).collect();
+        let max = grids
+            .iter()
+            .flat_map(|grid| grid.cells())
+            .filter_map(|(_, _, cell)| cell)
+            .fold(0.0, f32::max);
+        let range = [0.0, max.max(1e-3)];
+        let textures = grids.iter().map(|grid| grid.to_texture(display, range)).collect();
+        self.add_new_value(
+            textures,
+            grids,
+            name,
+            Measurement::Is {
+                normalised: NORMALISED,
+                init_range: range,
+                range,
+            },
+            axis,
+        );
+        if let Some(value) = self.values.last_mut() {
+            value.vectors = fields;
+        }
+    }
+
+    fn shown_vector_values(&self) -> Vec<usize> {
+        self.shown_values(|settings| settings.vectors)
+            .into_iter()
+            .filter(|index| self.values.get(*index).map_or(false, |value| value.is_vector()))
+            .collect()
+    }
+
+    pub fn update_particles(&mut self, dt: f32) {
+        let shown = match self.vector_settings.mode {
+            VectorMode::Particles => self.shown_vector_values(),
+            VectorMode::Arrows => vec![],
+        };
+        self.vector_settings.particles.retain(|index, _| shown.contains(index));
+
+        let count = self.vector_settings.particle_count.max(0).min(MAX_PARTICLES) as usize;
+        let trail_length = self.vector_settings.trail_length.max(2) as usize;
+        let scale = self.vector_settings.speed * dt;
+        for index in shown {
+            let field = match self.values[index].current_field() {
+                Some(field) => field,
+                None => continue,
+            };
+            let settings = &mut self.vector_settings;
+            let mut system = settings.particles.remove(&index).unwrap_or(ParticleSystem {
+                particles: vec![],
+                lines: None,
+            });
+            system.particles.truncate(count);
+            while system.particles.len() < count {
+                let particle = settings.spawn();
+                system.particles.push(particle);
+            }
+
+            for particle in &mut system.particles {
+                particle.age += dt;
+                let head = particle.trail[0];
+                match field.sample(head) {
+                    Some(velocity) if particle.age < particle.life => {
+                        particle.trail.push_front(advect(head, velocity, scale));
+                        particle.trail.truncate(trail_length);
+                    }
+                    _ => *particle = settings.spawn(),
+                }
+            }
+            settings.particles.insert(index, system);
+        }
+    }
+
+    pub fn update_vectors<F: Facade>(&mut self, display: &F) {
+        let shown = self.shown_vector_values();
+        if self.vector_settings.mode == VectorMode::Particles {
+            for system in self.vector_settings.particles.values_mut() {
+                let trails: Vec<Vec<[f32; 2]>> = system
+                    .particles
+                    .iter()
+                    .filter(|particle| particle.trail.len() > 1)
+                    .map(|particle| particle.trail.iter().cloned().collect())
+                    .collect();
+                system.lines = LineSet::new(display, &trails);
+            }
+            return;
+        }
+
+        let spacing = self.vector_settings.spacing.max(0.5);
+        self.vector_settings.arrows.retain(|index, _| shown.contains(index));
+        for index in shown {
+            let value = &self.values[index];
+            let step = value.current_step();
+            let max = match value.measurement {
+                Measurement::Is { range, .. } => range[1],
+                Measurement::IsNot => 1.0,
+            };
+            let up_to_date = self.vector_settings.arrows.get(&index).map_or(false, |cache| {
+                cache.step == step && cache.spacing == spacing && cache.max == max
+            });
+            let field = match value.current_field() {
+                Some(field) if !up_to_date => field,
+                _ => continue,
+            };
+            let cache = ArrowCache {
+                step,
+                spacing,
+                max,
+                lines: LineSet::new(display, &arrow_lines(field, spacing, max)),
+            };
+            self.vector_settings.arrows.insert(index, cache);
+        }
+    }
+
+    pub fn render_vectors<T: Surface + ?Sized>(
+        &self,
+        target: &mut T,
+        index: usize,
+        view: [[f32; 4]; 4],
+        rect: Rect,
+    ) {
+        let settings = &self.vector_settings;
+        let lines = match settings.mode {
+            VectorMode::Arrows => settings.arrows.get(&index).and_then(|cache| cache.lines.as_ref()),
+            VectorMode::Particles => settings
+                .particles
+                .get(&index)
+                .and_then(|system| system.lines.as_ref()),
+        };
+        let lines = match lines {
+            Some(lines) => lines,
+            None => return,
+        };
+        let [r, g, b] = settings.colour;
+        self.line_renderer.render(
+            target,
+            lines,
+            view,
+            &self.height_map,
+            self.variables.height,
+            LineStyle::new([r, g, b, 1.0], settings.width),
+            rect,
+        );
+    }
+}
@@ -1,6 +1,12 @@
 use evec::Evec;
 use glium::Rect;
-use renderer::{camera::PCamera, Mat4};
+use renderer::{camera::PCamera, Mat4, Vec3};
+use state::graticule::GraticuleStyle;
+use stats::SurfaceFilter;
+use std::f32::consts::PI;
+use util::clampf32;
+
+const MAX_TILT: f32 = PI * 0.45;
 
 #[derive(Copy, Clone, Debug)]
 pub enum DivDirection {
@@ -222,11 +228,11 @@ pub struct ViewRect {
 }
 
 impl ViewRect {
-    fn height(&self) -> f32 {
+    pub fn height(&self) -> f32 {
         self.top - self.bottom
     }
 
-    fn width(&self) -> f32 {
+    pub fn width(&self) -> f32 {
         self.right - self.left
     }
 
@@ -243,6 +249,17 @@ impl ViewRect {
         self.width() / self.height()
     }
 
+    // Re-expresses this rect as a fraction of outer, used when outer is
+    // rendered to fill an entire image
+    pub fn relative_to(&self, outer: &ViewRect) -> ViewRect {
+        ViewRect {
+            left: (self.left - outer.left) / outer.width(),
+            right: (self.right - outer.left) / outer.width(),
+            bottom: (self.bottom - outer.bottom) / outer.height(),
+            top: (self.top - outer.bottom) / outer.height(),
+        }
+    }
+
     pub fn contains(&self, pos: [f32; 2]) -> bool {
         let x = pos[0];
         let y = pos[1];
@@ -289,11 +306,15 @@ impl ViewPort {
         }
     }
 
-    pub fn view_matrix(&self, camera: &PCamera, zoom: f32) -> Mat4 {
+    pub fn view_matrix(&self, camera: &PCamera, offset: &ViewOffset, zoom: f32) -> Mat4 {
         let mut projection = camera.projection;
         let aspect = projection.aspect_ratio();
         projection.set_aspect(aspect * self.rect.aspect_ratio());
-        projection.zoomed_matrix(zoom) * camera.look_at_matrix()
+        let distance = camera.position.coords.norm();
+        offset.pan_matrix()
+            * projection.zoomed_matrix(zoom)
+            * offset.tilt_matrix(distance)
+            * camera.look_at_matrix()
     }
 
     pub fn get_div_selection(&self, divisions: &[Option<Division>]) -> Option<i32> {
@@ -320,9 +341,82 @@ impl ViewPort {
     }
 }
 
+#[derive(Copy, Clone, Debug)]
+pub struct ViewOffset {
+    pub roll: f32,
+    pub tilt: f32,
+    pub pan: [f32; 2],
+}
+
+impl ViewOffset {
+    pub fn new() -> ViewOffset {
+        ViewOffset {
+            roll: 0.0,
+            tilt: 0.0,
+            pan: [0.0; 2],
+        }
+    }
+
+    pub fn add_roll(&mut self, roll: f32) {
+        self.roll = (self.roll + roll) % (2.0 * PI);
+    }
+
+    pub fn add_tilt(&mut self, tilt: f32) {
+        self.tilt = clampf32(self.tilt + tilt, 0.0, MAX_TILT);
+    }
+
+    // Pan is in normalised device coordinates so the globe can't be dragged
+    // entirely out of its viewport
+    pub fn add_pan(&mut self, x: f32, y: f32) {
+        self.pan[0] = clampf32(self.pan[0] + x, -1.0, 1.0);
+        self.pan[1] = clampf32(self.pan[1] + y, -1.0, 1.0);
+    }
+
+    // Rolls around the view direction then tilts around the point on the
+    // surface closest to the camera, giving a view towards the horizon
+    pub fn tilt_matrix(&self, distance: f32) -> Mat4 {
+        let pivot = distance - 1.0;
+        let to_surface = Mat4::new_translation(&Vec3::new(0.0, 0.0, pivot));
+        let from_surface = Mat4::new_translation(&Vec3::new(0.0, 0.0, -pivot));
+        let tilt = Mat4::new_rotation(Vec3::new(self.tilt, 0.0, 0.0));
+        let roll = Mat4::new_rotation(Vec3::new(0.0, 0.0, self.roll));
+
+        from_surface * tilt * to_surface * roll
+    }
+
+    pub fn pan_matrix(&self) -> Mat4 {
+        Mat4::new_translation(&Vec3::new(self.pan[0], self.pan[1], 0.0))
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct VPSettings {
     pub menu_open: bool,
     pub show_range: bool,
     pub cam: Option<PCamera>,
+    pub offset: Option<ViewOffset>,
+    pub graticule: GraticuleStyle,
+    pub raster_overlay: bool,
+    pub vector_overlays: bool,
+    pub contours: bool,
+    pub vectors: bool,
+    // Land or ocean can be hidden with the land mask
+    pub surface: SurfaceFilter,
+}
+
+impl VPSettings {
+    pub fn new() -> VPSettings {
+        VPSettings {
+            menu_open: true,
+            show_range: false,
+            cam: None,
+            offset: None,
+            graticule: GraticuleStyle::new(),
+            raster_overlay: true,
+            vector_overlays: true,
+            contours: false,
+            vectors: true,
+            surface: SurfaceFilter::All,
+        }
+    }
 }
@@ -0,0 +1,365 @@
+use data_grid::DataGrid;
+
+// Cells shrink towards the poles, so each is weighted by the cosine of the
+// latitude at its centre
+pub fn cell_weight(grid: &DataGrid, row: usize) -> f32 {
+    grid.cell_centre(0, row)[0].to_radians().cos().max(0.0)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Extreme {
+    pub value: f32,
+    // Centre of the cell it was found in as [lat, lon]
+    pub location: [f32; 2],
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Summary {
+    pub mean: f32,
+    pub std_dev: f32,
+    pub min: Extreme,
+    pub max: Extreme,
+    // Fraction of the globe's area that has data
+    pub coverage: f32,
+    pub land_mean: Option<f32>,
+    pub ocean_mean: Option<f32>,
+}
+
+#[derive(Copy, Clone, Default)]
+struct Mean {
+    sum: f64,
+    weight: f64,
+}
+
+impl Mean {
+    fn add(&mut self, value: f32, weight: f32) {
+        self.sum += f64::from(value) * f64::from(weight);
+        self.weight += f64::from(weight);
+    }
+
+    fn get(&self) -> Option<f32> {
+        match self.weight > 0.0 {
+            true => Some((self.sum / self.weight) as f32),
+            false => None,
+        }
+    }
+}
+
+// Whether the mask marks a location as land, bright cells are land
+pub fn is_land(mask: &DataGrid, location: [f32; 2]) -> Option<bool> {
+    mask.sample(location).map(|cell| cell >= 0.5)
+}
+
+// Which cells a view, statistic or threshold keeps by the land mask
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SurfaceFilter {
+    All,
+    Land,
+    Ocean,
+}
+
+impl SurfaceFilter {
+    // Also the number the shaders know the filter by
+    pub fn index(&self) -> i32 {
+        match self {
+            SurfaceFilter::All => 0,
+            SurfaceFilter::Land => 1,
+            SurfaceFilter::Ocean => 2,
+        }
+    }
+
+    pub fn from_index(index: i32) -> SurfaceFilter {
+        match index {
+            1 => SurfaceFilter::Land,
+            2 => SurfaceFilter::Ocean,
+            _ => SurfaceFilter::All,
+        }
+    }
+
+    // Without a mask land and ocean cannot be told apart, so everything is kept
+    pub fn includes(&self, land_mask: Option<&DataGrid>, location: [f32; 2]) -> bool {
+        let land = match (self, land_mask.and_then(|mask| is_land(mask, location))) {
+            (SurfaceFilter::All, _) | (_, None) => return true,
+            (_, Some(land)) => land,
+        };
+        land == (*self == SurfaceFilter::Land)
+    }
+}
+
+// Area-weighted statistics of the cells with data, None when there are none
+pub fn summarise(grid: &DataGrid, land_mask: Option<&DataGrid>) -> Option<Summary> {
+    summarise_where(grid, land_mask, &|_| true)
+}
+
+// Statistics of only the cells whose centres pass include, coverage is then
+// the fraction of their area with data
+pub fn summarise_where(
+    grid: &DataGrid,
+    land_mask: Option<&DataGrid>,
+    include: &Fn([f32; 2]) -> bool,
+) -> Option<Summary> {
+    let weights: Vec<f32> = (0..grid.height()).map(|row| cell_weight(grid, row)).collect();
+    let mut all = Mean::default();
+    let mut land = Mean::default();
+    let mut ocean = Mean::default();
+    let mut total_weight = 0.0;
+    let mut min: Option<Extreme> = None;
+    let mut max: Option<Extreme> = None;
+
+    let mut included = vec![false; grid.width() * grid.height()];
+    for (column, row, cell) in grid.cells() {
+        let location = grid.cell_centre(column, row);
+        if !include(location) {
+            continue;
+        }
+        included[row * grid.width() + column] = true;
+        let weight = weights[row];
+        total_weight += f64::from(weight);
+        let cell = match cell {
+            Some(cell) if cell.is_finite() => cell,
+            _ => continue,
+        };
+        all.add(cell, weight);
+        match land_mask.and_then(|mask| is_land(mask, location)) {
+            Some(true) => land.add(cell, weight),
+            Some(false) => ocean.add(cell, weight),
+            None => (),
+        }
+        let extreme = Extreme {
+            value: cell,
+            location,
+        };
+        if min.map_or(true, |min| cell < min.value) {
+            min = Some(extreme);
+        }
+        if max.map_or(true, |max| cell > max.value) {
+            max = Some(extreme);
+        }
+    }
+
+    let mean = all.get()?;
+    // A second pass keeps the spread accurate for values far from zero
+    let mut spread = Mean::default();
+    for (column, row, cell) in grid.cells() {
+        if !included[row * grid.width() + column] {
+            continue;
+        }
+        if let Some(cell) = cell.filter(|cell| cell.is_finite()) {
+            spread.add((cell - mean) * (cell - mean), weights[row]);
+        }
+    }
+    Some(Summary {
+        mean,
+        std_dev: spread.get().unwrap_or(0.0).sqrt(),
+        min: min?,
+        max: max?,
+        coverage: (all.weight / total_weight) as f32,
+        land_mean: land.get(),
+        ocean_mean: ocean.get(),
+    })
+}
+
+// The fraction of the globe's area covered by cells with data passing test
+pub fn area_fraction(grid: &DataGrid, test: &Fn(f32) -> bool) -> f32 {
+    area_fraction_where(grid, &|_| true, test)
+}
+
+// As area_fraction, counting only the cells whose centres pass include
+pub fn area_fraction_where(
+    grid: &DataGrid,
+    include: &Fn([f32; 2]) -> bool,
+    test: &Fn(f32) -> bool,
+) -> f32 {
+    let (mut passed, mut total) = (0.0, 0.0);
+    for (column, row, cell) in grid.cells() {
+        let weight = f64::from(cell_weight(grid, row));
+        total += weight;
+        let counted = cell.map_or(false, |cell| test(cell));
+        if counted && include(grid.cell_centre(column, row)) {
+            passed += weight;
+        }
+    }
+    match total > 0.0 {
+        true => (passed / total) as f32,
+        false => 0.0,
+    }
+}
+
+// The mean of every row from south to north with the latitude of its centre.
+// Cells in a row share a weight so the plain mean is the area-weighted one
+pub fn zonal_means(grid: &DataGrid) -> Vec<(f32, Option<f32>)> {
+    zonal_means_where(grid, &|_| true)
+}
+
+// Zonal means of only the cells whose centres pass include
+pub fn zonal_means_where(
+    grid: &DataGrid,
+    include: &Fn([f32; 2]) -> bool,
+) -> Vec<(f32, Option<f32>)> {
+    let mut rows = vec![Mean::default(); grid.height()];
+    for (column, row, cell) in grid.cells() {
+        if !include(grid.cell_centre(column, row)) {
+            continue;
+        }
+        if let Some(cell) = cell.filter(|cell| cell.is_finite()) {
+            rows[row].add(cell, 1.0);
+        }
+    }
+    rows.iter()
+        .enumerate()
+        .map(|(row, mean)| (grid.cell_centre(0, row)[0], mean.get()))
+        .collect()
+}
+
+// The smallest and largest values with data
+pub fn data_extent(grid: &DataGrid) -> Option<[f32; 2]> {
+    grid.cells()
+        .filter_map(|(_, _, cell)| cell)
+        .filter(|cell| cell.is_finite())
+        .fold(None, |extent, cell| match extent {
+            Some([min, max]) => Some([cell.min(min), cell.max(max)]),
+            None => Some([cell, cell]),
+        })
+}
+
+fn weighted_cells(grid: &DataGrid, area_weighted: bool) -> Vec<(f32, f32)> {
+    let weights: Vec<f32> = (0..grid.height())
+        .map(|row| match area_weighted {
+            true => cell_weight(grid, row),
+            false => 1.0,
+        }).collect();
+    grid.cells()
+        .filter_map(|(_, row, cell)| cell.map(|cell| (cell, weights[row])))
+        .filter(|(cell, _)| cell.is_finite())
+        .collect()
+}
+
+// How much of the grid falls in each of bins equal parts of span, values
+// outside it are counted in the end bins
+pub fn histogram(grid: &DataGrid, span: [f32; 2], bins: usize, area_weighted: bool) -> Vec<f32> {
+    let mut counts = vec![0.0; bins];
+    let width = span[1] - span[0];
+    if bins == 0 || width.is_nan() || width <= 0.0 {
+        return counts;
+    }
+    for (cell, weight) in weighted_cells(grid, area_weighted) {
+        let bin = ((cell - span[0]) / width * bins as f32).floor();
+        let bin = bin.max(0.0).min((bins - 1) as f32) as usize;
+        counts[bin] += weight;
+    }
+    counts
+}
+
+// The values below which each fraction of the cells (or of their area) falls
+pub fn percentiles(grid: &DataGrid, fractions: [f32; 2], area_weighted: bool) -> Option<[f32; 2]> {
+    let mut cells = weighted_cells(grid, area_weighted);
+    if cells.is_empty() {
+        return None;
+    }
+    cells.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    let total: f32 = cells.iter().map(|(_, weight)| weight).sum();
+    let find = |fraction: f32| {
+        let target = fraction * total;
+        let mut sum = 0.0;
+        for (cell, weight) in &cells {
+            sum += weight;
+            if sum >= target {
+                return *cell;
+            }
+        }
+        cells[cells.len() - 1].0
+    };
+    Some([find(fractions[0]), find(fractions[1])])
+}
+
+// A range centred on zero that still covers the extent
+pub fn symmetric_range(extent: [f32; 2]) -> [f32; 2] {
+    let spread = extent[0].abs().max(extent[1].abs());
+    match spread > 0.0 {
+        true => [-spread, spread],
+        false => [-1.0, 1.0],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data_grid::DataGrid;
+    use stats::*;
+
+    #[test]
+    fn means_are_weighted_by_area() {
+        // Rows centred on -45 and 45 weigh the same, -67.5 much less than -22.5
+        let grid = DataGrid::new(2, 2, vec![Some(0.0), Some(0.0), Some(4.0), None]);
+        let summary = summarise(&grid, None).unwrap();
+        assert!((summary.mean - 4.0 / 3.0).abs() < 1e-4);
+        assert!((summary.coverage - 0.75).abs() < 1e-4);
+        assert_eq!(summary.max.value, 4.0);
+        assert_eq!(summary.max.location, grid.cell_centre(0, 1));
+        assert!(summary.land_mean.is_none());
+
+        let grid = DataGrid::new(1, 4, vec![Some(1.0), Some(0.0), Some(0.0), Some(0.0)]);
+        let mean = summarise(&grid, None).unwrap().mean;
+        assert!(mean > 0.0 && mean < 0.25);
+        assert!(summarise(&DataGrid::new(1, 1, vec![None]), None).is_none());
+    }
+
+    #[test]
+    fn land_and_ocean_split_by_mask() {
+        let grid = DataGrid::new(2, 1, vec![Some(2.0), Some(6.0)]);
+        let land = [1.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0];
+        let mask = DataGrid::new(4, 2, land.iter().map(|cell| Some(*cell)).collect());
+        let summary = summarise(&grid, Some(&mask)).unwrap();
+        assert_eq!(summary.land_mean, Some(2.0));
+        assert_eq!(summary.ocean_mean, Some(6.0));
+        assert_eq!(summary.std_dev, 2.0);
+
+        let east = summarise_where(&grid, None, &|location| location[1] > 0.0).unwrap();
+        assert_eq!(east.mean, 6.0);
+        assert_eq!(east.std_dev, 0.0);
+        assert_eq!(east.coverage, 1.0);
+    }
+
+    #[test]
+    fn surface_filters_follow_the_mask() {
+        let grid = DataGrid::new(2, 1, vec![Some(2.0), Some(6.0)]);
+        let land = [1.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0];
+        let mask = DataGrid::new(4, 2, land.iter().map(|cell| Some(*cell)).collect());
+        let (west, east) = (grid.cell_centre(0, 0), grid.cell_centre(1, 0));
+        assert!(SurfaceFilter::Land.includes(Some(&mask), west));
+        assert!(!SurfaceFilter::Land.includes(Some(&mask), east));
+        assert!(SurfaceFilter::Ocean.includes(Some(&mask), east));
+        assert!(SurfaceFilter::Ocean.includes(None, west));
+
+        let ocean = |location| SurfaceFilter::Ocean.includes(Some(&mask), location);
+        assert_eq!(area_fraction_where(&grid, &ocean, &|_| true), 0.5);
+        assert_eq!(zonal_means_where(&grid, &ocean)[0].1, Some(6.0));
+    }
+
+    #[test]
+    fn histograms_and_percentiles() {
+        let values = (0..10).map(|i| Some(i as f32)).collect();
+        let grid = DataGrid::new(10, 1, values);
+        assert_eq!(data_extent(&grid), Some([0.0, 9.0]));
+        let counts = histogram(&grid, [0.0, 5.0], 5, false);
+        assert_eq!(counts, vec![1.0, 1.0, 1.0, 1.0, 6.0]);
+        assert_eq!(percentiles(&grid, [0.2, 0.8], false), Some([1.0, 7.0]));
+        assert_eq!(symmetric_range([-2.0, 5.0]), [-5.0, 5.0]);
+        assert_eq!(data_extent(&DataGrid::new(1, 1, vec![None])), None);
+    }
+
+    #[test]
+    fn area_fraction_counts_the_whole_globe() {
+        let grid = DataGrid::new(2, 2, vec![Some(1.0), Some(5.0), None, Some(5.0)]);
+        assert_eq!(area_fraction(&grid, &|cell| cell > 2.0), 0.5);
+        assert_eq!(area_fraction(&grid, &|_| true), 0.75);
+    }
+
+    #[test]
+    fn zonal_means_run_south_to_north() {
+        let grid = DataGrid::new(2, 2, vec![Some(1.0), Some(3.0), None, None]);
+        let zonal = zonal_means(&grid);
+        assert_eq!(zonal[0].1, Some(2.0));
+        assert_eq!(zonal[1].1, None);
+        assert_eq!(zonal[0].0, -45.0);
+    }
+}
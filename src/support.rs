@@ -1,4 +1,5 @@
-use glium::backend::glutin::Display;
+use data_grid::DataGrid;
+use glium::backend::{glutin::Display, Facade};
 use glium::texture::{texture2d::Texture2d, RawImage2d};
 use glium::{
     glutin::{ElementState, Event, EventsLoop, VirtualKeyCode, WindowEvent},
@@ -13,13 +14,38 @@ use std::path::Path;
 use std::time::Instant;
 use window::Window;
 
-pub fn load_image(display: &Display, path: impl AsRef<Path>) -> Texture2d {
+pub fn load_image<F: Facade>(display: &F, path: impl AsRef<Path>) -> Texture2d {
     let image = image::open(path).expect("Cannot open image").to_rgba();
     let dims = image.dimensions();
     let raw = RawImage2d::from_raw_rgba_reversed(&image.into_raw(), dims);
     Texture2d::new(display, raw).unwrap()
 }
 
+// Widest land mask kept on the CPU, larger maps are sampled down to it
+const MASK_WIDTH: u32 = 1440;
+
+// Reads a black and white map into a grid of 1 for land (bright) and 0 for
+// ocean, laid out like the textures with row 0 at the bottom
+pub fn load_land_mask(path: impl AsRef<Path>) -> Result<DataGrid, Box<Error>> {
+    let image = image::open(path)?.to_luma();
+    let (image_width, image_height) = image.dimensions();
+    if image_width == 0 || image_height == 0 {
+        return Err("The land mask is empty".into());
+    }
+    let width = image_width.min(MASK_WIDTH);
+    let height = (image_height * width / image_width).max(1);
+    let mut values = Vec::with_capacity((width * height) as usize);
+    for row in 0..height {
+        let y = (height - 1 - row) * image_height / height;
+        for column in 0..width {
+            let x = column * image_width / width;
+            let land = image.get_pixel(x, y).data[0] >= 128;
+            values.push(Some(if land { 1.0 } else { 0.0 }));
+        }
+    }
+    Ok(DataGrid::new(width as usize, height as usize, values))
+}
+
 fn build_imgui(window: &Window, hidpi_factor: f32) -> (ImGui, Renderer) {
     let mut imgui = ImGui::init();
     let imrender =
@@ -86,7 +112,7 @@ fn set_special_keys(imgui: &mut ImGui) {
 
 pub fn run<F>(window: &mut Window, events_loop: &mut EventsLoop, mut func: F)
 where
-    F: FnMut(&mut Frame, &Ui, &MouseState, &Vec<Event>, f32, bool) -> bool,
+    F: FnMut(&Display, &mut Frame, &Ui, &MouseState, &Vec<Event>, f32, bool) -> bool,
 {
     let hdp = window.display.gl_window().get_hidpi_factor();
     let hidpi_factor = hdp.round();
@@ -130,7 +156,15 @@ where
         mouse.update_on_ui(&ui);
 
         let mut target = window.display.draw();
-        if !func(&mut target, &ui, &mouse, &events, delta_s, no_render) {
+        if !func(
+            &window.display,
+            &mut target,
+            &ui,
+            &mouse,
+            &events,
+            delta_s,
+            no_render,
+        ) {
             break;
         }
         if no_render {
@@ -0,0 +1,350 @@
+use data_grid::DataGrid;
+use derived::regrid;
+use std::error::Error;
+
+pub const STAT_COUNT: usize = 6;
+
+// Statistics of each cell taken over every time step of a value
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TemporalStat {
+    Trend,
+    Mean,
+    StdDev,
+    Min,
+    Max,
+    // The step the largest value fell in, counted from 1
+    StepOfMax,
+}
+
+impl TemporalStat {
+    pub fn all() -> [TemporalStat; STAT_COUNT] {
+        [
+            TemporalStat::Trend,
+            TemporalStat::Mean,
+            TemporalStat::StdDev,
+            TemporalStat::Min,
+            TemporalStat::Max,
+            TemporalStat::StepOfMax,
+        ]
+    }
+
+    pub fn from_index(index: i32) -> TemporalStat {
+        match index {
+            1 => TemporalStat::Mean,
+            2 => TemporalStat::StdDev,
+            3 => TemporalStat::Min,
+            4 => TemporalStat::Max,
+            5 => TemporalStat::StepOfMax,
+            _ => TemporalStat::Trend,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TemporalStat::Trend => "Trend",
+            TemporalStat::Mean => "Mean",
+            TemporalStat::StdDev => "Standard Deviation",
+            TemporalStat::Min => "Min",
+            TemporalStat::Max => "Max",
+            TemporalStat::StepOfMax => "Step of Max",
+        }
+    }
+
+    // Applies the statistic to one cell's values as (step, value) pairs
+    fn apply(&self, samples: &[(usize, f32)], step_length: f32) -> Option<f32> {
+        if samples.is_empty() {
+            return None;
+        }
+        let count = samples.len() as f32;
+        let mean = samples.iter().map(|(_, value)| value).sum::<f32>() / count;
+        let largest = || {
+            samples.iter().fold(None, |best: Option<(usize, f32)>, &(step, value)| match best {
+                Some((_, max)) if max >= value => best,
+                _ => Some((step, value)),
+            })
+        };
+        match self {
+            TemporalStat::Trend => {
+                let timed: Vec<(f32, f32)> = samples
+                    .iter()
+                    .map(|&(step, value)| (step as f32 * step_length, value))
+                    .collect();
+                linear_trend(&timed)
+            }
+            TemporalStat::Mean => Some(mean),
+            TemporalStat::StdDev => {
+                let variance = samples
+                    .iter()
+                    .map(|(_, value)| (value - mean) * (value - mean))
+                    .sum::<f32>()
+                    / count;
+                Some(variance.sqrt())
+            }
+            TemporalStat::Min => samples.iter().map(|(_, value)| *value).fold(None, |min, value| {
+                Some(min.map_or(value, |min: f32| min.min(value)))
+            }),
+            TemporalStat::Max => largest().map(|(_, max)| max),
+            TemporalStat::StepOfMax => largest().map(|(step, _)| (step + 1) as f32),
+        }
+    }
+}
+
+// The least squares slope of value against time, None without two distinct times
+pub fn linear_trend(samples: &[(f32, f32)]) -> Option<f32> {
+    let count = samples.len() as f64;
+    if samples.len() < 2 {
+        return None;
+    }
+    let mean_time = samples.iter().map(|(time, _)| f64::from(*time)).sum::<f64>() / count;
+    let mean_value = samples.iter().map(|(_, value)| f64::from(*value)).sum::<f64>() / count;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (time, value) in samples {
+        let dt = f64::from(*time) - mean_time;
+        covariance += dt * (f64::from(*value) - mean_value);
+        variance += dt * dt;
+    }
+    match variance > 0.0 {
+        true => Some((covariance / variance) as f32),
+        false => None,
+    }
+}
+
+// Pearson's correlation coefficient, None without two samples that both vary
+pub fn pearson(samples: &[(f32, f32)]) -> Option<f32> {
+    let count = samples.len() as f64;
+    if samples.len() < 2 {
+        return None;
+    }
+    let mean_a = samples.iter().map(|(a, _)| f64::from(*a)).sum::<f64>() / count;
+    let mean_b = samples.iter().map(|(_, b)| f64::from(*b)).sum::<f64>() / count;
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (a, b) in samples {
+        let (da, db) = (f64::from(*a) - mean_a, f64::from(*b) - mean_b);
+        covariance += da * db;
+        variance_a += da * da;
+        variance_b += db * db;
+    }
+    match variance_a > 0.0 && variance_b > 0.0 {
+        true => Some((covariance / (variance_a * variance_b).sqrt()) as f32),
+        false => None,
+    }
+}
+
+// Two tailed 95% critical values of the t distribution for 2 to 4 degrees of
+// freedom, where its expansion about the normal distribution is too small
+const SMALL_T_CRITICAL: [f64; 3] = [4.303, 3.182, 2.776];
+
+// The smallest correlation significant at 95%, two tailed, from this many
+// samples. Above 4 degrees of freedom the t distribution's critical value
+// comes from its expansion about the normal one, which is within 1% there
+pub fn critical_correlation(count: usize) -> Option<f32> {
+    if count < 4 {
+        return None;
+    }
+    let freedom = (count - 2) as f64;
+    let t = match SMALL_T_CRITICAL.get(count - 4) {
+        Some(t) => *t,
+        None => {
+            let z: f64 = 1.959_964;
+            z + (z.powi(3) + z) / (4.0 * freedom)
+                + (5.0 * z.powi(5) + 16.0 * z.powi(3) + 3.0 * z) / (96.0 * freedom * freedom)
+        }
+    };
+    Some((t / (freedom + t * t).sqrt()) as f32)
+}
+
+// What each cell's values are correlated against
+pub enum Against<'a> {
+    // Another value with the same number of steps, regridded to match
+    Grids(&'a [DataGrid]),
+    // One number per step, such as a climate index
+    Series(&'a [Option<f32>]),
+}
+
+// The correlation over time of each cell with the other series, leaving out
+// steps either has no data at. Cells not significant at 95% have no data
+// when masked
+pub fn correlation_map(
+    grids: &[DataGrid],
+    against: Against,
+    mask_insignificant: bool,
+) -> Result<DataGrid, Box<Error>> {
+    let first = grids.first().ok_or("The variable has no data grids")?;
+    let other_steps = match against {
+        Against::Grids(other) => other.len(),
+        Against::Series(series) => series.len(),
+    };
+    if other_steps != grids.len() {
+        return Err(format!(
+            "The steps do not match, {} against {}",
+            grids.len(),
+            other_steps
+        ).into());
+    }
+    let (width, height) = (first.width(), first.height());
+    let regridded = |grids: &[DataGrid]| -> Vec<DataGrid> {
+        grids.iter().map(|grid| regrid(grid, width, height)).collect()
+    };
+    let grids = regridded(grids);
+    let other = match against {
+        Against::Grids(other) => regridded(other),
+        Against::Series(_) => vec![],
+    };
+
+    let mut samples = Vec::with_capacity(grids.len());
+    let values = first
+        .cells()
+        .map(|(column, row, _)| {
+            samples.clear();
+            for (step, grid) in grids.iter().enumerate() {
+                let b = match against {
+                    Against::Grids(_) => other[step].get(column, row),
+                    Against::Series(series) => series[step],
+                };
+                if let (Some(a), Some(b)) = (grid.get(column, row), b) {
+                    if a.is_finite() && b.is_finite() {
+                        samples.push((a, b));
+                    }
+                }
+            }
+            let r = pearson(&samples)?;
+            match mask_insignificant {
+                true => critical_correlation(samples.len()).filter(|&c| r.abs() >= c).map(|_| r),
+                false => Some(r),
+            }
+        }).collect();
+    Ok(DataGrid::new(width, height, values))
+}
+
+// Reads an index with one number per line. The number is the last field of
+// comma separated lines, so a leading date column is skipped, while lines
+// whose last field is not a number are taken as headers and left out. Blank
+// fields, NaN and fill values from -99 down are missing
+pub fn parse_index_series(text: &str) -> Result<Vec<Option<f32>>, Box<Error>> {
+    let mut series = vec![];
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let field = line.rsplit(',').next().unwrap_or("").trim();
+        if field.is_empty() {
+            series.push(None);
+            continue;
+        }
+        match field.parse::<f32>() {
+            Ok(value) if value.is_finite() && value > -99.0 => series.push(Some(value)),
+            Ok(_) => series.push(None),
+            Err(_) if series.is_empty() => continue,
+            Err(_) => return Err(format!("Could not read {} as a number", field).into()),
+        }
+    }
+    if series.is_empty() {
+        return Err("The index has no values".into());
+    }
+    Ok(series)
+}
+
+// The statistic for every cell over all steps, which are regridded to the
+// first step's size. Time runs in units of step_length per step, so a trend
+// is the change per unit of time
+pub fn temporal_stat(
+    grids: &[DataGrid],
+    stat: TemporalStat,
+    step_length: f32,
+) -> Result<DataGrid, Box<Error>> {
+    let first = grids.first().ok_or("The variable has no data grids")?;
+    if grids.len() < 2 {
+        return Err("Statistics over time need more than one time step".into());
+    }
+    let (width, height) = (first.width(), first.height());
+    let grids: Vec<DataGrid> = grids.iter().map(|grid| regrid(grid, width, height)).collect();
+
+    let mut samples = Vec::with_capacity(grids.len());
+    let values = first
+        .cells()
+        .map(|(column, row, _)| {
+            samples.clear();
+            for (step, grid) in grids.iter().enumerate() {
+                if let Some(value) = grid.get(column, row).filter(|value| value.is_finite()) {
+                    samples.push((step, value));
+                }
+            }
+            stat.apply(&samples, step_length)
+        }).collect();
+    Ok(DataGrid::new(width, height, values))
+}
+
+#[cfg(test)]
+mod tests {
+    use data_grid::DataGrid;
+    use temporal::*;
+
+    fn steps(values: &[&[Option<f32>]]) -> Vec<DataGrid> {
+        values
+            .iter()
+            .map(|cells| DataGrid::new(cells.len(), 1, cells.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn trends_are_per_unit_of_time() {
+        let grids = steps(&[&[Some(1.0), Some(5.0)], &[Some(2.0), None], &[Some(3.0), None]]);
+        // Monthly steps with the trend wanted per decade
+        let trend = temporal_stat(&grids, TemporalStat::Trend, 1.0 / 120.0).unwrap();
+        assert!((trend.get(0, 0).unwrap() - 120.0).abs() < 1e-2);
+        assert_eq!(trend.get(1, 0), None);
+        assert_eq!(linear_trend(&[(0.0, 1.0), (0.0, 2.0)]), None);
+    }
+
+    #[test]
+    fn summarises_each_cell_over_time() {
+        let grids = steps(&[&[Some(1.0)], &[Some(7.0)], &[None], &[Some(4.0)]]);
+        let stat = |stat| temporal_stat(&grids, stat, 1.0).unwrap().get(0, 0).unwrap();
+        assert_eq!(stat(TemporalStat::Mean), 4.0);
+        assert!((stat(TemporalStat::StdDev) - 6.0f32.sqrt()).abs() < 1e-5);
+        assert_eq!(stat(TemporalStat::Min), 1.0);
+        assert_eq!(stat(TemporalStat::Max), 7.0);
+        assert_eq!(stat(TemporalStat::StepOfMax), 2.0);
+        assert!(temporal_stat(&grids[..1], TemporalStat::Mean, 1.0).is_err());
+    }
+
+    #[test]
+    fn correlates_cells_over_time() {
+        let a = steps(&[&[Some(1.0), Some(1.0)], &[Some(2.0), Some(1.0)], &[Some(3.0), Some(1.0)]]);
+        let b = steps(&[&[Some(6.0), Some(0.0)], &[Some(4.0), Some(1.0)], &[Some(2.0), Some(2.0)]]);
+        let map = correlation_map(&a, Against::Grids(&b), false).unwrap();
+        assert!((map.get(0, 0).unwrap() + 1.0).abs() < 1e-6);
+        // A constant cell has no correlation
+        assert_eq!(map.get(1, 0), None);
+
+        let index = [Some(0.5), None, Some(1.5)];
+        let map = correlation_map(&a, Against::Series(&index), false).unwrap();
+        assert!((map.get(0, 0).unwrap() - 1.0).abs() < 1e-6);
+        assert!(correlation_map(&a, Against::Series(&index[..2]), false).is_err());
+        // Three samples are too few to be significant
+        assert_eq!(correlation_map(&a, Against::Grids(&b), true).unwrap().get(0, 0), None);
+    }
+
+    #[test]
+    fn significance_needs_stronger_correlation_from_fewer_samples() {
+        // Tabulated critical values at 95% for 10 and 30 samples
+        assert!((critical_correlation(10).unwrap() - 0.632).abs() < 0.01);
+        assert!((critical_correlation(30).unwrap() - 0.361).abs() < 0.005);
+        assert_eq!(critical_correlation(3), None);
+    }
+
+    #[test]
+    fn small_samples_use_exact_critical_values() {
+        // Tabulated critical values at 95% for 4, 5, 6 and 7 samples
+        assert!((critical_correlation(4).unwrap() - 0.950).abs() < 0.001);
+        assert!((critical_correlation(5).unwrap() - 0.878).abs() < 0.001);
+        assert!((critical_correlation(6).unwrap() - 0.811).abs() < 0.001);
+        assert!((critical_correlation(7).unwrap() - 0.754).abs() < 0.005);
+    }
+
+    #[test]
+    fn reads_index_series() {
+        let text = "date,nino34\n1950-01,-1.5\n1950-02,\n1950-03,-99.9\n\n0.7\n";
+        let series = parse_index_series(text).unwrap();
+        assert_eq!(series, vec![Some(-1.5), None, None, Some(0.7)]);
+        assert!(parse_index_series("1.0\nnot a number").is_err());
+        assert!(parse_index_series("header only").is_err());
+    }
+}
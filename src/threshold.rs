@@ -0,0 +1,134 @@
+use stats::SurfaceFilter;
+
+// Which cells a threshold rule picks out of a value
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Condition {
+    Off,
+    Above,
+    Below,
+    Between,
+    Outside,
+}
+
+impl Condition {
+    // Also the number frag_hsv.glsl knows the condition by
+    pub fn index(&self) -> i32 {
+        match self {
+            Condition::Off => 0,
+            Condition::Above => 1,
+            Condition::Below => 2,
+            Condition::Between => 3,
+            Condition::Outside => 4,
+        }
+    }
+
+    pub fn from_index(index: i32) -> Condition {
+        match index {
+            1 => Condition::Above,
+            2 => Condition::Below,
+            3 => Condition::Between,
+            4 => Condition::Outside,
+            _ => Condition::Off,
+        }
+    }
+
+    // Whether the rule needs both limits rather than just the first
+    pub fn is_band(&self) -> bool {
+        match self {
+            Condition::Between | Condition::Outside => true,
+            _ => false,
+        }
+    }
+}
+
+// How cells meeting the condition are shown
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ThresholdStyle {
+    Hatch,
+    Outline,
+    // Greys out every cell that does not meet the condition
+    Mask,
+}
+
+impl ThresholdStyle {
+    pub fn index(&self) -> i32 {
+        match self {
+            ThresholdStyle::Hatch => 0,
+            ThresholdStyle::Outline => 1,
+            ThresholdStyle::Mask => 2,
+        }
+    }
+
+    pub fn from_index(index: i32) -> ThresholdStyle {
+        match index {
+            1 => ThresholdStyle::Outline,
+            2 => ThresholdStyle::Mask,
+            _ => ThresholdStyle::Hatch,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ThresholdRule {
+    pub condition: Condition,
+    // Above and below only use the first limit
+    pub limits: [f32; 2],
+    pub style: ThresholdStyle,
+    pub colour: [f32; 3],
+    // Only cells on this part of the land mask can meet the condition
+    pub surface: SurfaceFilter,
+}
+
+impl ThresholdRule {
+    pub fn new() -> ThresholdRule {
+        ThresholdRule {
+            condition: Condition::Off,
+            limits: [0.0, 1.0],
+            style: ThresholdStyle::Hatch,
+            colour: [1.0, 1.0, 1.0],
+            surface: SurfaceFilter::All,
+        }
+    }
+
+    // The limits in order, so a band can be entered either way round
+    pub fn ordered_limits(&self) -> [f32; 2] {
+        let [a, b] = self.limits;
+        match self.condition.is_band() {
+            true => [a.min(b), a.max(b)],
+            false => [a, a],
+        }
+    }
+
+    pub fn matches(&self, value: f32) -> bool {
+        let [low, high] = self.ordered_limits();
+        match self.condition {
+            Condition::Off => false,
+            Condition::Above => value > low,
+            Condition::Below => value < low,
+            Condition::Between => value >= low && value <= high,
+            Condition::Outside => value < low || value > high,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use threshold::*;
+
+    #[test]
+    fn conditions_match_their_limits() {
+        let mut rule = ThresholdRule::new();
+        rule.limits = [30.0, 10.0];
+        assert!(!rule.matches(50.0));
+        rule.condition = Condition::Above;
+        assert!(rule.matches(31.0) && !rule.matches(30.0));
+        rule.condition = Condition::Below;
+        assert!(rule.matches(29.0) && !rule.matches(31.0));
+        rule.condition = Condition::Between;
+        assert_eq!(rule.ordered_limits(), [10.0, 30.0]);
+        assert!(rule.matches(10.0) && rule.matches(20.0) && !rule.matches(40.0));
+        rule.condition = Condition::Outside;
+        assert!(rule.matches(5.0) && rule.matches(35.0) && !rule.matches(20.0));
+        assert!(!rule.matches(::std::f32::NAN));
+    }
+}
@@ -1,5 +1,7 @@
-use glium::{backend::glutin::Display, texture::Texture2d, VertexBuffer};
+use data_grid::{from_heat_map, grid_with_texture, DataGrid};
+use glium::{backend::Facade, texture::Texture2d, VertexBuffer};
 use heat_map;
+use imgui::ImString;
 use std::path::Path;
 
 #[derive(Copy, Clone, Debug)]
@@ -19,7 +21,28 @@ pub struct BoxVertex {
 
 implement_vertex!(BoxVertex, position, hue);
 
-pub fn build_box(display: &Display, width: f32, height: f32) -> VertexBuffer<BoxVertex> {
+#[derive(Copy, Clone, Debug)]
+pub struct MarkerVertex {
+    pub position: [f32; 3],
+    pub tex_coord: [f32; 2],
+    pub corner: [f32; 2],
+    pub colour: [f32; 3],
+}
+
+implement_vertex!(MarkerVertex, position, tex_coord, corner, colour);
+
+#[derive(Copy, Clone, Debug)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+    pub tex_coord: [f32; 2],
+}
+
+implement_vertex!(LineVertex, position, tex_coord);
+
+// The textures of every time step along with the grids they were made from
+pub type LoadedValue = (Vec<Texture2d>, Vec<DataGrid>);
+
+pub fn build_box<F: Facade>(display: &F, width: f32, height: f32) -> VertexBuffer<BoxVertex> {
     let right = width * 0.5;
     let left = -right;
     let top = height * 0.5;
@@ -53,63 +76,80 @@ pub fn load_temp_grid(
     heat_map::grid::Grid::load_from_bin(path).unwrap()
 }
 
-pub fn load_temp_values(
-    display: &Display,
+pub fn load_temp_values<F: Facade>(
+    display: &F,
     path: impl AsRef<Path>,
-    range: heat_map::math::Range<f32>,
-    std_range: heat_map::math::Range<f32>,
-) -> (Vec<Texture2d>, Vec<Texture2d>, Vec<Texture2d>) {
+    range: [f32; 2],
+    std_range: [f32; 2],
+) -> (LoadedValue, LoadedValue, LoadedValue) {
     let temp_grid: heat_map::grid::Grid<Option<heat_map::data::YearlyData<f32>>> =
         heat_map::grid::Grid::load_from_bin(path).unwrap();
 
     let monthly_temps = load_monthly_values(display, &temp_grid, range);
-    let avg_temp = vec![load_yearly_average(display, &temp_grid, range)];
-    let stddev = vec![load_yearly_stddev(display, &temp_grid, std_range)];
-    (avg_temp, monthly_temps, stddev)
+    let (avg_texture, avg_grid) = load_yearly_average(display, &temp_grid, range);
+    let (std_texture, std_grid) = load_yearly_stddev(display, &temp_grid, std_range);
+    (
+        (vec![avg_texture], vec![avg_grid]),
+        monthly_temps,
+        (vec![std_texture], vec![std_grid]),
+    )
+}
+
+// The monthly grids of one component of a vector field, left on the CPU as
+// the magnitude is drawn instead
+pub fn load_component_grids(path: impl AsRef<Path>) -> Vec<DataGrid> {
+    let grid = load_temp_grid(path);
+    (0..12)
+        .map(|i| {
+            from_heat_map(grid.into_grid_with(|yearly| match yearly {
+                Some(data) => data.get_month_average(i),
+                None => None,
+            }))
+        }).collect()
 }
 
-pub fn load_monthly_values(
-    display: &Display,
+pub fn load_monthly_values<F: Facade>(
+    display: &F,
     temp_grid: &heat_map::grid::Grid<Option<heat_map::data::YearlyData<f32>>>,
-    range: heat_map::math::Range<f32>,
-) -> Vec<Texture2d> {
+    range: [f32; 2],
+) -> LoadedValue {
     let mut textures = Vec::with_capacity(12);
+    let mut grids = Vec::with_capacity(12);
 
     for i in 0..12 {
         let month = temp_grid.into_grid_with(|yearly_temp| match yearly_temp {
             Some(data) => data.get_month_average(i),
             None => None,
         });
-        let (texture, _) = month.into_texture(display, Some(range));
+        let (texture, grid) = grid_with_texture(display, month, range);
         textures.push(texture);
+        grids.push(grid);
     }
-    textures
+    (textures, grids)
 }
 
-pub fn load_yearly_average(
-    display: &Display,
+pub fn load_yearly_average<F: Facade>(
+    display: &F,
     temp_grid: &heat_map::grid::Grid<Option<heat_map::data::YearlyData<f32>>>,
-    range: heat_map::math::Range<f32>,
-) -> Texture2d {
-    temp_grid
-        .into_grid_with(|temp| match temp {
-            Some(data) => data.yearly_average(),
-            None => None,
-        }).into_texture(display, Some(range))
-        .0
+    range: [f32; 2],
+) -> (Texture2d, DataGrid) {
+    let average = temp_grid.into_grid_with(|temp| match temp {
+        Some(data) => data.yearly_average(),
+        None => None,
+    });
+    grid_with_texture(display, average, range)
 }
 
-pub fn load_yearly_stddev(
-    display: &Display,
+pub fn load_yearly_stddev<F: Facade>(
+    display: &F,
     temp_grid: &heat_map::grid::Grid<Option<heat_map::data::YearlyData<f32>>>,
-    range: heat_map::math::Range<f32>,
-) -> Texture2d {
-    temp_grid
-        .into_grid_with(|temp| match temp {
-            Some(data) => data.standard_dev(),
-            None => None,
-        }).into_texture(display, Some(range))
-        .0
+    range: [f32; 2],
+) -> (Texture2d, DataGrid) {
+    let stddev = temp_grid.into_grid_with(|temp| match temp {
+        Some(data) => data.standard_dev(),
+        None => None,
+    });
+    grid_with_texture(display, stddev, range)
 }
 
 pub fn minf32(value: f32, min: f32) -> f32 {
@@ -137,3 +177,19 @@ pub fn clampf32(value: f32, min: f32, max: f32) -> f32 {
         value
     }
 }
+
+pub fn wrapf32(value: f32, max: f32) -> f32 {
+    let wrapped = value % max;
+    if wrapped < 0.0 {
+        wrapped + max
+    } else {
+        wrapped
+    }
+}
+
+// Imgui text inputs can only grow up to the capacity of their buffer
+pub fn editable_string(text: &str, capacity: usize) -> ImString {
+    let mut string = ImString::new(text);
+    string.reserve(capacity);
+    string
+}
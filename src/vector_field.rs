@@ -0,0 +1,148 @@
+use data_grid::DataGrid;
+use geo::wrap_longitude;
+use util::clampf32;
+
+// Arrow heads are drawn back from the tip at this angle either side
+const HEAD_ANGLE: f32 = 150.0;
+const HEAD_LENGTH: f32 = 0.35;
+
+// The eastward (u) and northward (v) components of one time step
+#[derive(Clone, Debug)]
+pub struct VectorField {
+    pub u: DataGrid,
+    pub v: DataGrid,
+}
+
+impl VectorField {
+    pub fn new(u: DataGrid, v: DataGrid) -> VectorField {
+        assert!(
+            u.width() == v.width() && u.height() == v.height(),
+            "Vector components have different grid sizes"
+        );
+        VectorField { u, v }
+    }
+
+    // [u, v] at a location, None unless both components have data
+    pub fn sample(&self, location: [f32; 2]) -> Option<[f32; 2]> {
+        Some([self.u.sample(location)?, self.v.sample(location)?])
+    }
+
+    pub fn magnitude(&self) -> DataGrid {
+        let values = self
+            .u
+            .cells()
+            .zip(self.v.cells())
+            .map(|((_, _, u), (_, _, v))| match (u, v) {
+                (Some(u), Some(v)) => Some(u.hypot(v)),
+                _ => None,
+            }).collect();
+        DataGrid::new(self.u.width(), self.u.height(), values)
+    }
+}
+
+// Moves a location along a velocity, scale is degrees per unit of speed
+pub fn advect(location: [f32; 2], velocity: [f32; 2], scale: f32) -> [f32; 2] {
+    let lat = location[0] + velocity[1] * scale;
+    // Degrees of longitude get shorter towards the poles
+    let shrink = location[0].to_radians().cos().max(0.05);
+    let lon = location[1] + velocity[0] * scale / shrink;
+    [clampf32(lat, -89.9, 89.9), wrap_longitude(lon)]
+}
+
+// Points roughly spacing degrees apart, thinned towards the poles so they
+// stay evenly spread over the globe
+pub fn lattice(spacing: f32) -> Vec<[f32; 2]> {
+    let mut points = vec![];
+    if spacing.is_nan() || spacing <= 0.0 {
+        return points;
+    }
+    let mut lat = -90.0 + spacing * 0.5;
+    while lat < 90.0 {
+        let count = (360.0 * lat.to_radians().cos() / spacing).round().max(1.0) as usize;
+        let step = 360.0 / count as f32;
+        for i in 0..count {
+            points.push([lat, -180.0 + (i as f32 + 0.5) * step]);
+        }
+        lat += spacing;
+    }
+    points
+}
+
+fn rotate(direction: [f32; 2], degrees: f32) -> [f32; 2] {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    [
+        direction[0] * cos - direction[1] * sin,
+        direction[0] * sin + direction[1] * cos,
+    ]
+}
+
+// A shaft and head for every lattice point with data, the longest arrow
+// reaching most of the way to its neighbour
+pub fn arrow_lines(field: &VectorField, spacing: f32, max_magnitude: f32) -> Vec<Vec<[f32; 2]>> {
+    let mut lines = vec![];
+    if max_magnitude.is_nan() || max_magnitude <= 0.0 {
+        return lines;
+    }
+    for point in lattice(spacing) {
+        let velocity = match field.sample(point) {
+            Some(velocity) => velocity,
+            None => continue,
+        };
+        let magnitude = velocity[0].hypot(velocity[1]);
+        if magnitude <= 0.0 {
+            continue;
+        }
+        let length = spacing * 0.9 * (magnitude / max_magnitude).min(1.0);
+        let direction = [velocity[0] / magnitude, velocity[1] / magnitude];
+        let tip = advect(point, direction, length);
+        let head = length * HEAD_LENGTH;
+        let left = advect(tip, rotate(direction, HEAD_ANGLE), head);
+        let right = advect(tip, rotate(direction, -HEAD_ANGLE), head);
+        lines.push(vec![point, tip]);
+        lines.push(vec![left, tip, right]);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use data_grid::DataGrid;
+    use vector_field::*;
+
+    fn field(u: f32, v: f32) -> VectorField {
+        VectorField::new(
+            DataGrid::new(4, 2, vec![Some(u); 8]),
+            DataGrid::new(4, 2, vec![Some(v); 8]),
+        )
+    }
+
+    #[test]
+    fn advects_east_and_north() {
+        let moved = advect([0.0, 170.0], [20.0, 0.0], 1.0);
+        assert!((moved[1] - -170.0).abs() < 1e-3);
+        let moved = advect([60.0, 0.0], [1.0, 1.0], 1.0);
+        assert!((moved[0] - 61.0).abs() < 1e-4);
+        assert!((moved[1] - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn magnitude_needs_both_components() {
+        let mut field = field(3.0, 4.0);
+        field.u = DataGrid::new(4, 2, (0..8).map(|i| if i == 0 { None } else { Some(3.0) }).collect());
+        let magnitude = field.magnitude();
+        assert_eq!(magnitude.get(0, 0), None);
+        assert_eq!(magnitude.get(1, 0), Some(5.0));
+    }
+
+    #[test]
+    fn lattice_thins_towards_the_poles() {
+        let points = lattice(30.0);
+        let equator = points.iter().filter(|p| p[0] == 15.0).count();
+        let polar = points.iter().filter(|p| p[0] == 75.0).count();
+        assert_eq!(equator, 12);
+        assert!(polar < equator && polar > 0);
+        // Two lines per arrow, calm fields draw nothing
+        assert_eq!(arrow_lines(&field(1.0, 0.0), 30.0, 1.0).len(), points.len() * 2);
+        assert!(arrow_lines(&field(0.0, 0.0), 30.0, 1.0).is_empty());
+    }
+}